@@ -0,0 +1,245 @@
+//! Derive macros for Orgish. These should generally be used through the `derive` feature of the
+//! `orgish` crate, which re-exports them.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input,
+    punctuated::Punctuated,
+    Data, DeriveInput, Fields, Ident, LitStr, Token,
+};
+
+/// Derives the `Keyword` trait for an `enum` of keywords. Each unit variant is a keyword, written
+/// as the variant's name in uppercase by default (so `Todo` will be `TODO`), which can be changed
+/// with `#[keyword("NAME")]`.
+///
+/// Exactly one variant must be marked as the catch-all with `#[keyword(other)]`, and it must hold
+/// a single `String`. This will be used for keywords that aren't otherwise known (see
+/// `Keyword::other`).
+///
+/// Variants can also be marked as done states with `#[keyword(done)]`, in which case
+/// `WorkflowKeyword` will be derived as well, with the states in the order of the variants (which
+/// means the type must also be `Clone`). Done states must come after all the others.
+///
+/// # Examples
+///
+/// ```
+/// use orgish::{keyword::WorkflowKeyword, Keyword};
+///
+/// #[derive(Keyword, Clone, Debug, PartialEq)]
+/// enum MyKeyword {
+///     Todo,
+///     #[keyword("WAITING")]
+///     Wait,
+///     #[keyword(done)]
+///     Done,
+///     #[keyword("CANCELLED", done)]
+///     Kill,
+///     #[keyword(other)]
+///     Other(String),
+/// }
+///
+/// assert_eq!(MyKeyword::from_str("WAITING"), Some(MyKeyword::Wait));
+/// assert_eq!(MyKeyword::Kill.into_string(), "CANCELLED");
+/// assert!(MyKeyword::Done.is_done());
+/// ```
+///
+/// Leaving out the catch-all variant is a compile-time error:
+///
+/// ```compile_fail
+/// use orgish::Keyword;
+///
+/// #[derive(Keyword)]
+/// enum MyKeyword {
+///     Todo,
+///     Done,
+/// }
+/// ```
+#[proc_macro_derive(Keyword, attributes(keyword))]
+pub fn derive_keyword(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match keyword_impl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// A single argument to the `#[keyword(..)]` attribute.
+enum KeywordArg {
+    /// The name of the keyword in documents.
+    Name(LitStr),
+    /// Marks the catch-all variant.
+    Other,
+    /// Marks a done state.
+    Done,
+}
+impl Parse for KeywordArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Self::Name(input.parse()?));
+        }
+        let ident: Ident = input.parse()?;
+        match ident.to_string().as_str() {
+            "other" => Ok(Self::Other),
+            "done" => Ok(Self::Done),
+            _ => Err(syn::Error::new_spanned(
+                ident,
+                "expected a keyword name, `other`, or `done`",
+            )),
+        }
+    }
+}
+
+/// A keyword variant, parsed from the input.
+struct State {
+    ident: Ident,
+    name: String,
+    done: bool,
+}
+
+/// Generates the implementations for `#[derive(Keyword)]`.
+fn keyword_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`Keyword` can only be derived for enums",
+            ))
+        }
+    };
+
+    let mut states: Vec<State> = Vec::new();
+    let mut other = None;
+    for variant in &data.variants {
+        let mut name = None;
+        let mut is_other = false;
+        let mut done = false;
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("keyword"))
+        {
+            let args =
+                attr.parse_args_with(Punctuated::<KeywordArg, Token![,]>::parse_terminated)?;
+            for arg in args {
+                match arg {
+                    KeywordArg::Name(lit) => name = Some(lit),
+                    KeywordArg::Other => is_other = true,
+                    KeywordArg::Done => done = true,
+                }
+            }
+        }
+
+        if is_other {
+            if name.is_some() || done {
+                return Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "the `#[keyword(other)]` variant can't have a name or be a done state",
+                ));
+            }
+            if !matches!(&variant.fields, Fields::Unnamed(fields) if fields.unnamed.len() == 1) {
+                return Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "the `#[keyword(other)]` variant must hold a single `String`",
+                ));
+            }
+            if other.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "only one variant can be marked with `#[keyword(other)]`",
+                ));
+            }
+            other = Some(variant.ident.clone());
+            continue;
+        }
+
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                "keyword variants must be unit variants (other than the `#[keyword(other)]` variant)",
+            ));
+        }
+        let name = match name {
+            Some(lit) => {
+                let name = lit.value();
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "keywords must be non-empty and can't contain whitespace",
+                    ));
+                }
+                name
+            }
+            None => variant.ident.to_string().to_uppercase(),
+        };
+        if states.iter().any(|state| state.name == name) {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                format!("duplicate keyword `{name}`"),
+            ));
+        }
+        if !done && states.iter().any(|state| state.done) {
+            return Err(syn::Error::new_spanned(
+                &variant.ident,
+                "done states must come after all the others",
+            ));
+        }
+        states.push(State {
+            ident: variant.ident.clone(),
+            name,
+            done,
+        });
+    }
+    let other = other.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "a catch-all variant holding a `String` must be marked with `#[keyword(other)]`",
+        )
+    })?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let idents = states.iter().map(|state| &state.ident).collect::<Vec<_>>();
+    let names = states.iter().map(|state| &state.name).collect::<Vec<_>>();
+    let mut tokens = quote! {
+        impl #impl_generics ::orgish::keyword::Keyword for #ident #ty_generics #where_clause {
+            fn from_str(keyword: &str) -> ::std::option::Option<Self> {
+                match keyword {
+                    #(#names => ::std::option::Option::Some(Self::#idents),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+            fn into_string(self) -> ::std::string::String {
+                match self {
+                    #(Self::#idents => ::std::string::ToString::to_string(#names),)*
+                    Self::#other(keyword) => keyword,
+                }
+            }
+            fn other(keyword: ::std::string::String) -> Self {
+                Self::#other(keyword)
+            }
+        }
+    };
+
+    if states.iter().any(|state| state.done) {
+        let done_idents = states
+            .iter()
+            .filter(|state| state.done)
+            .map(|state| &state.ident);
+        tokens.extend(quote! {
+            impl #impl_generics ::orgish::keyword::WorkflowKeyword for #ident #ty_generics #where_clause {
+                fn states() -> ::std::vec::Vec<Self> {
+                    ::std::vec![#(Self::#idents),*]
+                }
+                fn is_done(&self) -> bool {
+                    ::std::matches!(self, #(Self::#done_idents)|*)
+                }
+            }
+        });
+    }
+
+    Ok(tokens)
+}
@@ -1,11 +1,18 @@
 #![cfg(feature = "cli")]
 
 use anyhow::{bail, Context};
-use chrono::{Local, NaiveDateTime};
-use clap::{Parser, Subcommand};
-use orgish::{Document, Format, Keyword};
-use orgish_tools::{mark_nodes_done, refile_to_file, CompletedNode};
+use chrono::{Local, Months, NaiveDate, NaiveDateTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+use orgish::{
+    keyword::KeywordSet, timestamp::TimestampApplies, Document, Format, Keyword, ParseId, Timestamp,
+};
+use orgish_tools::{
+    agenda, export_ics, import_ics, mark_nodes_done, merge_to_file, query, refile_to_file,
+    AgendaEntry, AgendaEntryKind, AgendaOptions, CompletedNode, IcsImportOptions, MatchQuery,
+    QueryMatch,
+};
 use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<(), anyhow::Error> {
     // Parse the CLI options
@@ -16,47 +23,38 @@ fn main() -> Result<(), anyhow::Error> {
         _ => bail!("invalid format, expected markdown or org"),
     };
 
-    // Read from stdin into a string until EOF
-    let mut input = Vec::new();
-    for line in io::stdin().lock().lines() {
-        let line = line.unwrap();
-        input.push(line);
-    }
-    let input = input.join("\n");
-
-    // Parse that as a document (it *should* only be one heading, but we might do an en-masse
-    // refile)
-    let document = Document::<GenericKeyword>::from_str(&input, format)
-        .with_context(|| "failed to parse stdin as orgish document")?;
-    // Make sure there's no root text (that would be a bad selection)
-    if document.root.body.is_some() {
-        bail!("invalid selection, expected no root contents")
-    }
-    let fragment = document.root.into_children();
-
     match opts.command {
         Command::MarkDone {
             target,
+            keywords,
             keyword,
             repeating_keyword,
             no_last_repeat,
             last_repeat,
         } => {
-            let keyword = GenericKeyword { keyword };
-            let repeating_keyword = GenericKeyword {
-                keyword: repeating_keyword,
+            let fragment = read_stdin_fragment(format)?;
+            // The old single-keyword flags are still accepted, and just make a set of two states
+            let keywords = if keyword.is_some() || repeating_keyword.is_some() {
+                eprintln!(
+                    "warning: --keyword and --repeating-keyword are deprecated, use --keywords instead"
+                );
+                format!(
+                    "{} | {}",
+                    repeating_keyword.as_deref().unwrap_or("TODO"),
+                    keyword.as_deref().unwrap_or("DONE")
+                )
+            } else {
+                keywords
             };
+            let keywords =
+                KeywordSet::from_str(&keywords).with_context(|| "invalid keyword set")?;
 
             let now = Local::now();
             let parsed_nodes = mark_nodes_done(
                 fragment,
-                repeating_keyword,
-                keyword,
-                if no_last_repeat {
-                    None
-                } else {
-                    Some(last_repeat.unwrap_or(now.naive_local()))
-                },
+                &keywords,
+                last_repeat.unwrap_or(now.naive_local()),
+                !no_last_repeat,
             );
 
             let mut to_refile = Vec::new();
@@ -87,11 +85,343 @@ fn main() -> Result<(), anyhow::Error> {
                 refile_to_file(to_refile, target, format)?;
             }
         }
+        Command::Agenda {
+            paths,
+            start,
+            span,
+            json,
+            warning_days,
+            done_keywords,
+            timezone,
+            tags,
+        } => {
+            let today = Local::now().date_naive();
+            let from = start.unwrap_or(today);
+            let to = match span {
+                AgendaSpan::Day => from,
+                AgendaSpan::Week => from + chrono::Duration::try_days(6).unwrap(),
+                AgendaSpan::Month => (from + Months::new(1)).pred_opt().unwrap(),
+            };
+
+            let (files, documents) = read_documents(paths, format)?;
+            let options = AgendaOptions {
+                from,
+                to,
+                today,
+                warning_days,
+                timezone,
+            };
+            let keywords = done_keywords_override(done_keywords);
+            let mut entries = agenda(&documents, &options, keywords.as_ref())
+                .with_context(|| "failed to build agenda")?;
+            entries.retain(|entry| tags.iter().all(|tag| entry.tags.contains(tag)));
+
+            if json {
+                let entries = entries
+                    .into_iter()
+                    .map(|entry| entry_json(entry, &files))
+                    .collect::<Vec<_>>();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else {
+                print_agenda(entries, &files, from, to);
+            }
+        }
+        Command::Export {
+            format:
+                ExportFormat::Ics {
+                    paths,
+                    output,
+                    done_keywords,
+                },
+        } => {
+            let (_, documents) = read_documents(paths, format)?;
+            let keywords = done_keywords_override(done_keywords);
+            let calendar = export_ics(&documents, Utc::now().naive_utc(), keywords.as_ref())
+                .with_context(|| "failed to export calendar")?;
+            match output {
+                Some(output) => std::fs::write(&output, calendar)
+                    .with_context(|| format!("failed to write to '{}'", output.display()))?,
+                None => print!("{calendar}"),
+            }
+        }
+        Command::Import {
+            format:
+                ImportFormat::Ics {
+                    path,
+                    target,
+                    todo_keyword,
+                    done_keyword,
+                    timezone,
+                },
+        } => {
+            let calendar = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read '{}'", path.display()))?;
+            let options = IcsImportOptions {
+                todo_keyword: GenericKeyword {
+                    keyword: todo_keyword,
+                },
+                done_keyword: GenericKeyword {
+                    keyword: done_keyword,
+                },
+                timezone,
+            };
+            let nodes = import_ics::<_, orgish::StringId>(&calendar, &options)
+                .with_context(|| format!("failed to import '{}'", path.display()))?;
+            merge_to_file(nodes, target, format)?;
+        }
+        Command::Query {
+            query: query_str,
+            paths,
+            json,
+        } => {
+            let match_query = MatchQuery::parse(&query_str).with_context(|| "invalid query")?;
+            let (files, documents) = read_documents(paths, format)?;
+            let matches = query(&documents, &match_query, Local::now().naive_local());
+
+            if json {
+                let matches = matches
+                    .into_iter()
+                    .map(|m| match_json(m, &files))
+                    .collect::<Vec<_>>();
+                println!("{}", serde_json::to_string_pretty(&matches)?);
+            } else {
+                for m in matches {
+                    print_match(m, &files);
+                }
+            }
+        }
+        Command::Timestamp {
+            input,
+            relative_to,
+            inactive,
+        } => {
+            let relative_to = relative_to.unwrap_or(Local::now().date_naive());
+            let mut timestamp = Timestamp::from_user_input(&input.join(" "), relative_to)
+                .with_context(|| "failed to parse timestamp input")?;
+            timestamp.active = !inactive;
+            println!("{}", timestamp.into_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a document fragment from stdin until EOF.
+fn read_stdin_fragment(format: Format) -> Result<Vec<orgish::Node<GenericKeyword>>, anyhow::Error> {
+    let mut input = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap();
+        input.push(line);
+    }
+    let input = input.join("\n");
+
+    // Parse that as a document (it *should* only be one heading, but we might do an en-masse
+    // refile)
+    let document = Document::<GenericKeyword>::from_str(&input, format)
+        .with_context(|| "failed to parse stdin as orgish document")?;
+    // Make sure there's no root text (that would be a bad selection)
+    if document.root.body.is_some() {
+        bail!("invalid selection, expected no root contents")
+    }
+    Ok(document.root.into_children())
+}
+
+/// Gets the keyword set to use instead of each document's own, if any done keywords were given on
+/// the command line.
+fn done_keywords_override(done_keywords: Vec<String>) -> Option<KeywordSet> {
+    (!done_keywords.is_empty()).then_some(KeywordSet {
+        todo: Vec::new(),
+        done: done_keywords,
+    })
+}
+
+/// Reads and parses all the documents at the given paths (searching through directories for files
+/// in the given format). This returns the paths to the files alongside the parsed documents.
+#[allow(clippy::type_complexity)]
+fn read_documents(
+    paths: Vec<PathBuf>,
+    format: Format,
+) -> Result<(Vec<PathBuf>, Vec<Document<GenericKeyword>>), anyhow::Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_files(&path, format, &mut files)?;
+    }
+    let mut documents = Vec::new();
+    for file in &files {
+        let contents = std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read '{}'", file.display()))?;
+        let document = Document::<GenericKeyword>::from_str(&contents, format)
+            .with_context(|| format!("failed to parse '{}'", file.display()))?;
+        documents.push(document);
+    }
+
+    Ok((files, documents))
+}
+
+/// Adds the given path to the list of files if it's a file, or, if it's a directory, adds all the
+/// files underneath it with the right extension for the given format (in alphabetical order).
+fn collect_files(
+    path: &Path,
+    format: Format,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), anyhow::Error> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)
+        .with_context(|| format!("failed to read directory '{}'", path.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for entry in entries {
+        let extension = entry.extension().and_then(|ext| ext.to_str());
+        if entry.is_dir() {
+            collect_files(&entry, format, files)?;
+        } else if match format {
+            Format::Markdown => matches!(extension, Some("md" | "markdown")),
+            Format::Org => extension == Some("org"),
+        } {
+            files.push(entry);
+        }
     }
 
     Ok(())
 }
 
+/// Prints the given agenda entries as plain text, grouped by date. Every date in the span will be
+/// printed, even if it has no entries.
+fn print_agenda(
+    entries: Vec<AgendaEntry<GenericKeyword>>,
+    files: &[PathBuf],
+    from: NaiveDate,
+    to: NaiveDate,
+) {
+    let mut entries = entries.into_iter().peekable();
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        println!("{}", date.format("%A %Y-%m-%d"));
+        while let Some(entry) = entries.next_if(|entry| entry.date == date) {
+            let file = files[entry.document]
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let keyword = entry
+                .keyword
+                .map(|keyword| format!("{} ", keyword.keyword))
+                .unwrap_or_default();
+            let path = if entry.path.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", entry.path.join("/"))
+            };
+            println!(
+                "  {:<12}{:<12}{:<14}{}{}{}",
+                format!("{file}:"),
+                applies_string(entry.applies),
+                kind_string(entry.kind),
+                keyword,
+                entry.title,
+                path
+            );
+        }
+    }
+}
+
+/// Converts the given agenda entry into JSON.
+fn entry_json(entry: AgendaEntry<GenericKeyword>, files: &[PathBuf]) -> serde_json::Value {
+    let (kind, days) = match entry.kind {
+        AgendaEntryKind::Scheduled => ("scheduled", None),
+        AgendaEntryKind::ScheduledOverdue { days } => ("scheduled_overdue", Some(days)),
+        AgendaEntryKind::Deadline => ("deadline", None),
+        AgendaEntryKind::DeadlineOverdue { days } => ("deadline_overdue", Some(days)),
+        AgendaEntryKind::DeadlineUpcoming { days } => ("deadline_upcoming", Some(days)),
+        AgendaEntryKind::Timestamp => ("timestamp", None),
+    };
+    serde_json::json!({
+        "date": entry.date,
+        "kind": kind,
+        "days": days,
+        "applies": entry.applies,
+        "timestamp": entry.timestamp.into_string(),
+        "keyword": entry.keyword.map(|keyword| keyword.keyword),
+        "title": entry.title,
+        "tags": entry.tags,
+        "path": entry.path,
+        "file": files[entry.document],
+    })
+}
+
+/// Prints the given query match as a line of plain text.
+fn print_match(m: QueryMatch<GenericKeyword, orgish::StringId>, files: &[PathBuf]) {
+    let file = files[m.document]
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let keyword = m
+        .node
+        .keyword
+        .as_ref()
+        .map(|keyword| format!("{} ", keyword.keyword))
+        .unwrap_or_default();
+    let tags = if m.node.tags.is_empty() {
+        String::new()
+    } else {
+        format!(" :{}:", m.node.tags.join(":"))
+    };
+    let path = if m.path.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", m.path.join("/"))
+    };
+    println!("{file}: {keyword}{}{tags}{path}", m.node.title);
+}
+
+/// Converts the given query match into JSON.
+fn match_json(
+    m: QueryMatch<GenericKeyword, orgish::StringId>,
+    files: &[PathBuf],
+) -> serde_json::Value {
+    let node = m.node;
+    serde_json::json!({
+        "file": files[m.document],
+        "path": m.path,
+        "level": node.level(),
+        "keyword": node.keyword.as_ref().map(|keyword| &keyword.keyword),
+        "priority": node.priority.0,
+        "title": node.title,
+        "tags": m.tags,
+        "id": node.properties.id.is_some().then(|| node.properties.id.clone().into_string()),
+        "scheduled": node.planning.scheduled.clone().map(Timestamp::into_string),
+        "deadline": node.planning.deadline.clone().map(Timestamp::into_string),
+        "closed": node.planning.closed.clone().map(Timestamp::into_string),
+    })
+}
+
+/// Gets a short description of when an agenda entry applies on its date.
+fn applies_string(applies: TimestampApplies) -> String {
+    match applies {
+        TimestampApplies::Block(start, end) => {
+            format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+        }
+        TimestampApplies::Start(start) => start.format("%H:%M").to_string(),
+        TimestampApplies::End(end) => format!("-{}", end.format("%H:%M")),
+        TimestampApplies::AllDay | TimestampApplies::None => String::new(),
+    }
+}
+
+/// Gets an Org mode-style description of why an agenda entry appears on its date.
+fn kind_string(kind: AgendaEntryKind) -> String {
+    match kind {
+        AgendaEntryKind::Scheduled => "Scheduled:".to_string(),
+        AgendaEntryKind::ScheduledOverdue { days } => format!("Sched.{days}x:"),
+        AgendaEntryKind::Deadline => "Deadline:".to_string(),
+        AgendaEntryKind::DeadlineOverdue { days } => format!("{days} d. ago:"),
+        AgendaEntryKind::DeadlineUpcoming { days } => format!("In {days} d.:"),
+        AgendaEntryKind::Timestamp => String::new(),
+    }
+}
+
 /// Performs a variety of operations on Orgish nodes to add support for Org mode-style
 /// functionality to any editor and workflow
 #[derive(Parser, Debug)]
@@ -108,22 +438,144 @@ enum Command {
     MarkDone {
         /// Where to put the non-repeating nodes which are marked as done (repeating nodes
         /// will be returned through stdout in their new form). If not set, non-repeating
-        /// nodes will be returned with a `DONE` keyword
+        /// nodes will be returned with the first done keyword
         #[arg(short, long)]
         target: Option<String>,
-        /// An alternative keyword to use for the `DONE` state of nodes that don't repeat
-        #[arg(short, long, default_value = "DONE")]
-        keyword: String,
-        /// An alternative keyword to use for nodes that don't repeat
-        #[arg(long, default_value = "TODO")]
-        repeating_keyword: String,
+        /// The keyword states, in the format of Org's `#+TODO` (e.g. `TODO NEXT | DONE KILL`).
+        /// Nodes that don't repeat will be given the first done keyword
+        #[arg(long, default_value = "TODO | DONE", conflicts_with_all = ["keyword", "repeating_keyword"])]
+        keywords: String,
+        /// Deprecated: use `--keywords` instead. The keyword to use for the done state of nodes
+        /// that don't repeat
+        #[arg(short, long)]
+        keyword: Option<String>,
+        /// Deprecated: use `--keywords` instead. The keyword to give nodes that repeat
+        #[arg(long)]
+        repeating_keyword: Option<String>,
         /// Disable setting the `LAST_REPEAT` property
         #[arg(long)]
         no_last_repeat: bool,
-        /// Set a custom completion time for `LAST_REPEAT`
+        /// Set a custom completion time (used for `LAST_REPEAT` and for progressing `++` and `.+`
+        /// repeaters)
         #[arg(long)]
         last_repeat: Option<NaiveDateTime>,
     },
+    /// Prints an agenda of the scheduled items, deadlines, and active timestamps in the given files
+    Agenda {
+        /// The files to build the agenda from (directories will be searched for files in the
+        /// given format)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// The first date in the agenda (defaults to today)
+        #[arg(short, long)]
+        start: Option<NaiveDate>,
+        /// How long the agenda should cover
+        #[arg(long, value_enum, default_value = "week")]
+        span: AgendaSpan,
+        /// Print the agenda as JSON rather than plain text
+        #[arg(long)]
+        json: bool,
+        /// How many days before a deadline to start warning about it, if it doesn't specify
+        /// its own warning period
+        #[arg(long, default_value_t = 14)]
+        warning_days: u32,
+        /// The keywords that mark a node as done (these won't be shown as overdue). This can be
+        /// given more than once, and overrides the done keywords of each file's `#+TODO`
+        #[arg(long = "done-keyword")]
+        done_keywords: Vec<String>,
+        /// The timezone to show the agenda in (e.g. `Europe/London`). Timestamps in nodes with a
+        /// `TZ` property, or in documents with a `#+TIMEZONE`, will be converted to it
+        #[arg(long)]
+        timezone: Option<chrono_tz::Tz>,
+        /// Only show entries for nodes with this tag, including inherited tags (this can be given
+        /// more than once)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Exports the given files into another format
+    Export {
+        #[command(subcommand)]
+        format: ExportFormat,
+    },
+    /// Imports nodes from another format, merging them into a file by their IDs
+    Import {
+        #[command(subcommand)]
+        format: ImportFormat,
+    },
+    /// Finds the nodes in the given files that match an Org mode tags/property match query (e.g.
+    /// `+work-meeting&PRIORITY="A"|TODO="NEXT"&DEADLINE<"<+1w>"`)
+    Query {
+        /// The query to match nodes against
+        query: String,
+        /// The files to search (directories will be searched for files in the given format)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Print the matches as JSON rather than plain text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Parses human input (e.g. `next mon 14:00`, `+2d`, or `tomorrow +1w`) into a timestamp and
+    /// prints it
+    Timestamp {
+        /// The input to parse (this may be given as multiple arguments)
+        #[arg(required = true, allow_hyphen_values = true, trailing_var_arg = true)]
+        input: Vec<String>,
+        /// The date the input is relative to (defaults to today)
+        #[arg(short, long)]
+        relative_to: Option<NaiveDate>,
+        /// Produce an inactive timestamp
+        #[arg(long)]
+        inactive: bool,
+    },
+}
+/// The formats documents can be exported to.
+#[derive(Subcommand, Debug)]
+enum ExportFormat {
+    /// Exports timestamps as events, and scheduled items and deadlines as todos, in an iCalendar
+    /// (`.ics`) file
+    Ics {
+        /// The files to export (directories will be searched for files in the given format)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// The file to write the calendar to (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// The keywords that mark a node as done (these will be exported as completed todos). This
+        /// can be given more than once, and overrides the done keywords of each file's `#+TODO`
+        #[arg(long = "done-keyword")]
+        done_keywords: Vec<String>,
+    },
+}
+/// The formats nodes can be imported from.
+#[derive(Subcommand, Debug)]
+enum ImportFormat {
+    /// Imports events and todos from an iCalendar (`.ics`) file, using their UIDs as IDs, so
+    /// importing the same calendar again updates the nodes rather than duplicating them
+    Ics {
+        /// The calendar to import
+        path: PathBuf,
+        /// Where to put nodes not already in the target file, given as a path, optionally followed
+        /// by a double colon and a heading path (e.g. `calendar.org::Work`)
+        #[arg(short, long)]
+        target: String,
+        /// The keyword to use for todos that haven't been completed
+        #[arg(long, default_value = "TODO")]
+        todo_keyword: String,
+        /// The keyword to use for completed todos
+        #[arg(long, default_value = "DONE")]
+        done_keyword: String,
+        /// The timezone to write times in (e.g. `Europe/London`). Otherwise, times are written as
+        /// they are in the calendar
+        #[arg(long)]
+        timezone: Option<chrono_tz::Tz>,
+    },
+}
+/// The spans of time an agenda can cover.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum AgendaSpan {
+    Day,
+    Week,
+    Month,
 }
 
 /// A generic keyword detection system for Orgish that calls any completely uppercase word a valid
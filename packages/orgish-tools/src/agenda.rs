@@ -0,0 +1,343 @@
+//! Org mode-style agendas, which gather the scheduled items, deadlines, and active timestamps
+//! across a set of documents onto the dates they apply to.
+
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use orgish::{
+    error::TimezoneError,
+    keyword::{KeywordSet, Workflow},
+    tags::TagConfig,
+    timestamp::{Occurrence, TimestampApplies},
+    Document, Format, Keyword, Node, ParseId, Timestamp,
+};
+
+/// The options for building an agenda.
+#[derive(Debug, Clone)]
+pub struct AgendaOptions {
+    /// The first date the agenda should cover.
+    pub from: NaiveDate,
+    /// The last date the agenda should cover (inclusive).
+    pub to: NaiveDate,
+    /// The current date. Overdue items and upcoming deadlines will be shown on this date, if it's
+    /// in the agenda's span.
+    pub today: NaiveDate,
+    /// How many days before a deadline it should start appearing, if the deadline doesn't specify
+    /// its own warning period. Org mode uses 14 days by default.
+    pub warning_days: u32,
+    /// The timezone of the person viewing the agenda. If this is set, timed timestamps in nodes
+    /// with a known timezone (from their `TZ` property, an ancestor's, or their document's
+    /// `#+TIMEZONE`) will be converted to it. Otherwise, all timestamps are taken as they're
+    /// written.
+    pub timezone: Option<Tz>,
+}
+
+/// A single entry in an agenda.
+#[derive(Debug, Clone)]
+pub struct AgendaEntry<K: Keyword> {
+    /// The date this entry appears on.
+    pub date: NaiveDate,
+    /// Why this entry appears on this date.
+    pub kind: AgendaEntryKind,
+    /// When the timestamp behind this entry applies on its date.
+    pub applies: TimestampApplies,
+    /// The timestamp behind this entry.
+    pub timestamp: Timestamp,
+    /// The keyword of the node this entry is for.
+    pub keyword: Option<K>,
+    /// The title of the node this entry is for.
+    pub title: String,
+    /// The effective tags of the node this entry is for, including those it inherits (see
+    /// [`Document::effective_tags`]).
+    pub tags: Vec<String>,
+    /// The titles of the ancestors of the node this entry is for, from the top level down.
+    pub path: Vec<String>,
+    /// The index of the document this entry came from, in the order the documents were given.
+    pub document: usize,
+}
+
+/// The reasons an entry can appear in an agenda.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgendaEntryKind {
+    /// The node is scheduled for this date.
+    Scheduled,
+    /// The node was scheduled this many days ago, and hasn't been done yet. These only appear on
+    /// the current date.
+    ScheduledOverdue { days: i64 },
+    /// The node has a deadline on this date.
+    Deadline,
+    /// The node's deadline passed this many days ago, and it hasn't been done yet. These only
+    /// appear on the current date.
+    DeadlineOverdue { days: i64 },
+    /// The node has a deadline in this many days, and we're inside its warning period. These only
+    /// appear on the current date.
+    DeadlineUpcoming { days: i64 },
+    /// The node has an active timestamp (in its heading or body) that applies on this date.
+    Timestamp,
+}
+
+/// Builds an Org mode-style agenda over the given documents. This will find the scheduled items,
+/// deadlines, and active timestamps (in both headings and bodies) in every node of every document,
+/// and produce an entry for each date in the agenda's span on which they apply. Range timestamps
+/// produce an entry on each of the dates they cover.
+///
+/// Nodes with done keywords will still appear on the dates of their timestamps, but they won't be
+/// shown as overdue, nor will their deadlines be warned about. Which keywords are done is worked
+/// out from each document's keyword set (from `#+TODO`, defaulting to `TODO | DONE`), unless
+/// `keywords` is given, in which case it's used for every document instead.
+///
+/// The entries will be sorted by date, with timed entries first (in order of time), and otherwise
+/// in document order. Each entry has the effective tags of its node, worked out with the tag
+/// configuration of its document (from `#+TAGS`), so entries can be filtered by them.
+///
+/// This will fail if any document's `#+TIMEZONE`, or any node's `TZ` property, isn't a known
+/// timezone, since the times of the entries affected couldn't be trusted.
+pub fn agenda<'d, K, I>(
+    documents: impl IntoIterator<Item = &'d Document<K, I>>,
+    options: &AgendaOptions,
+    keywords: Option<&KeywordSet>,
+) -> Result<Vec<AgendaEntry<K>>, TimezoneError>
+where
+    K: Keyword + Clone + 'd,
+    I: ParseId + 'd,
+{
+    let mut entries = Vec::new();
+    for (idx, document) in documents.into_iter().enumerate() {
+        let mut path = Vec::new();
+        // Invalid tag configurations are ignored, as if they weren't given
+        let tag_config = document
+            .tag_config()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        // As are invalid keyword sets
+        let document_keywords = document
+            .keyword_set()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        let context = NodeContext {
+            document: idx,
+            zone: document.timezone().transpose()?,
+            tags: tag_config.passed_down(&[], &document.root.tags),
+            tag_config: &tag_config,
+            keywords: keywords.unwrap_or(&document_keywords),
+        };
+        for node in document.root.children() {
+            add_node_entries(node, &context, &mut path, options, &mut entries)?;
+        }
+    }
+
+    // This is a stable sort, so document order is preserved otherwise
+    entries.sort_by_key(|entry| {
+        let time = applies_time(entry.applies);
+        (entry.date, time.is_none(), time)
+    });
+    Ok(entries)
+}
+
+/// What a node inherits from its document and ancestors.
+struct NodeContext<'c> {
+    /// The index of the document the node is in.
+    document: usize,
+    /// The timezone the node inherits, if any.
+    zone: Option<Tz>,
+    /// The tags the node inherits.
+    tags: Vec<String>,
+    /// The tag configuration of the document the node is in.
+    tag_config: &'c TagConfig,
+    /// The keyword set used to work out whether the node is done.
+    keywords: &'c KeywordSet,
+}
+
+/// Adds the agenda entries for the given node and all its children. The given path should be that
+/// of the node's ancestors, and it will be restored after this is done (unless this fails on an
+/// invalid timezone).
+fn add_node_entries<K: Keyword + Clone, I: ParseId>(
+    node: &Node<K, I>,
+    context: &NodeContext,
+    path: &mut Vec<String>,
+    options: &AgendaOptions,
+    entries: &mut Vec<AgendaEntry<K>>,
+) -> Result<(), TimezoneError> {
+    let done = node
+        .keyword
+        .as_ref()
+        .is_some_and(|keyword| context.keywords.is_done(keyword));
+    // Property values are plain strings here, so the format doesn't matter
+    let zone = node.timezone(Format::Org).transpose()?.or(context.zone);
+    let tags = context.tag_config.effective(&context.tags, &node.tags);
+    let localise = |timestamp: &Timestamp| match (zone, options.timezone) {
+        (Some(from), Some(to)) => timestamp.clone().into_timezone(&from, &to),
+        _ => timestamp.clone(),
+    };
+    let today_in_span = options.from <= options.today && options.today <= options.to;
+    let mut push = |date, kind, applies, timestamp: &Timestamp| {
+        entries.push(AgendaEntry {
+            date,
+            kind,
+            applies,
+            timestamp: timestamp.clone(),
+            keyword: node.keyword.clone(),
+            title: node.title.clone(),
+            tags: tags.clone(),
+            path: path.clone(),
+            document: context.document,
+        })
+    };
+
+    if let Some(scheduled) = node.planning.scheduled.as_ref().map(localise) {
+        let scheduled = &scheduled;
+        let on_today = add_occurrences(scheduled, AgendaEntryKind::Scheduled, options, &mut push);
+        let days = (options.today - scheduled.start.date).num_days();
+        if !done && today_in_span && !on_today && days > 0 {
+            push(
+                options.today,
+                AgendaEntryKind::ScheduledOverdue { days },
+                TimestampApplies::AllDay,
+                scheduled,
+            );
+        }
+    }
+    if let Some(deadline) = node.planning.deadline.as_ref().map(localise) {
+        let deadline = &deadline;
+        let on_today = add_occurrences(deadline, AgendaEntryKind::Deadline, options, &mut push);
+        if !done && today_in_span && !on_today {
+            let days = (options.today - deadline.start.date).num_days();
+            if days > 0 {
+                push(
+                    options.today,
+                    AgendaEntryKind::DeadlineOverdue { days },
+                    TimestampApplies::AllDay,
+                    deadline,
+                );
+            } else if deadline.is_within_warning_period(options.today, options.warning_days) {
+                // This is the next repeat of the deadline (which we know isn't today)
+                let next = deadline
+                    .get_next_repeat(options.today)
+                    .unwrap_or(deadline.start.date);
+                push(
+                    options.today,
+                    AgendaEntryKind::DeadlineUpcoming {
+                        days: (next - options.today).num_days(),
+                    },
+                    TimestampApplies::AllDay,
+                    deadline,
+                );
+            }
+        }
+    }
+    let body_timestamps = node
+        .body
+        .as_deref()
+        .map(find_timestamps)
+        .unwrap_or_default();
+    for timestamp in node.timestamps.iter().chain(body_timestamps.iter()) {
+        if timestamp.active {
+            let timestamp = localise(timestamp);
+            add_occurrences(&timestamp, AgendaEntryKind::Timestamp, options, &mut push);
+        }
+    }
+
+    let child_context = NodeContext {
+        zone,
+        tags: context.tag_config.passed_down(&context.tags, &node.tags),
+        ..*context
+    };
+    path.push(node.title.clone());
+    for child in node.children() {
+        add_node_entries(child, &child_context, path, options, entries)?;
+    }
+    path.pop();
+
+    Ok(())
+}
+
+/// Adds an entry of the given kind for every date in the agenda's span on which the given
+/// timestamp applies. This returns whether or not any of these were on the current date.
+fn add_occurrences(
+    timestamp: &Timestamp,
+    kind: AgendaEntryKind,
+    options: &AgendaOptions,
+    push: &mut impl FnMut(NaiveDate, AgendaEntryKind, TimestampApplies, &Timestamp),
+) -> bool {
+    let mut on_today = false;
+    for occurrence in timestamp.occurrences(options.from, options.to) {
+        for date in occurrence_dates(&occurrence, options) {
+            on_today |= date == options.today;
+            push(date, kind, occurrence.applies(date), timestamp);
+        }
+    }
+
+    on_today
+}
+
+/// Gets the dates in the agenda's span that the given occurrence covers.
+fn occurrence_dates(
+    occurrence: &Occurrence,
+    options: &AgendaOptions,
+) -> impl Iterator<Item = NaiveDate> {
+    let start = occurrence.start.date.max(options.from);
+    let end = occurrence
+        .end
+        .as_ref()
+        .map(|end| end.date)
+        .unwrap_or(occurrence.start.date)
+        .min(options.to);
+    start.iter_days().take_while(move |date| *date <= end)
+}
+
+/// Gets the time at which something that applies in the given way starts on its date, if it has
+/// one. This is used for sorting entries.
+fn applies_time(applies: TimestampApplies) -> Option<NaiveTime> {
+    match applies {
+        TimestampApplies::Block(start, _) => Some(start),
+        TimestampApplies::Start(start) => Some(start),
+        TimestampApplies::End(end) => Some(end),
+        TimestampApplies::AllDay | TimestampApplies::None => None,
+    }
+}
+
+/// Finds all the valid timestamps (including range timestamps) in the given text. Anything that
+/// looks like it might be a timestamp, but which fails to parse, will be ignored.
+fn find_timestamps(text: &str) -> Vec<Timestamp> {
+    let mut timestamps = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['<', '[']) {
+        let closer = if rest[start..].starts_with('<') {
+            '>'
+        } else {
+            ']'
+        };
+        let end = match rest[start..].find(closer) {
+            Some(end) => start + end + 1,
+            // This is just a stray bracket, but there might be timestamps after it
+            None => {
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        // This might be the first half of a range timestamp
+        let mut candidate_end = end;
+        if rest[end..].starts_with("--<") || rest[end..].starts_with("--[") {
+            let range_closer = if rest[end..].starts_with("--<") {
+                '>'
+            } else {
+                ']'
+            };
+            if let Some(range_end) = rest[end + 3..].find(range_closer) {
+                candidate_end = end + 3 + range_end + 1;
+            }
+        }
+
+        if let Ok(timestamp) = Timestamp::from_str(&rest[start..candidate_end]) {
+            timestamps.push(timestamp);
+            rest = &rest[candidate_end..];
+        } else if let Ok(timestamp) = Timestamp::from_str(&rest[start..end]) {
+            timestamps.push(timestamp);
+            rest = &rest[end..];
+        } else {
+            // Skip just the opening character, there might be a timestamp inside this
+            rest = &rest[start + 1..];
+        }
+    }
+
+    timestamps
+}
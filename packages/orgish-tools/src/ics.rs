@@ -0,0 +1,751 @@
+use crate::DocumentFragment;
+use chrono::{Duration, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use orgish::{
+    error::TimezoneError,
+    keyword::{KeywordSet, Workflow},
+    timestamp::{DateTime, Repeater, RepeaterKind, RepeaterUnit},
+    Document, Format, Keyword, Node, ParseId, Priority, Timestamp,
+};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Exports the nodes in the given documents as an RFC 5545 (iCalendar) calendar. Active timestamps
+/// in headings (including range timestamps) become events, and nodes with `SCHEDULED` or
+/// `DEADLINE` planning become todos, with those as their start and due dates respectively.
+/// Repeaters become recurrence rules.
+///
+/// Each component's UID will be the ID of its node (with a `-n` suffix if the node produces more
+/// than one component, which will be the case if it has several timestamps, or both timestamps
+/// and planning). Nodes without IDs will be given a UID based on a hash of their titles and
+/// outline paths, which will stay the same between exports as long as those don't change. The
+/// extra components of a node also have an `X-ORGISH-ID` property with the UID of its first, so
+/// [`import_ics`] can put the node back together.
+///
+/// Timestamps in nodes with a known timezone (from their `TZ` property, an ancestor's, or their
+/// document's `#+TIMEZONE`) will be written in UTC, and others will be written as floating local
+/// times. `now` is used as the time the calendar was created (`DTSTAMP`), and should be in UTC.
+/// Nodes with done keywords will be exported as completed todos. Which keywords are done is worked
+/// out from each document's keyword set (from `#+TODO`, defaulting to `TODO | DONE`), unless
+/// `keywords` is given, in which case it's used for every document instead.
+///
+/// This will fail if any document's `#+TIMEZONE`, or any node's `TZ` property, isn't a known
+/// timezone, rather than exporting the times it applies to as if they were floating.
+pub fn export_ics<'d, K, I>(
+    documents: impl IntoIterator<Item = &'d Document<K, I>>,
+    now: NaiveDateTime,
+    keywords: Option<&KeywordSet>,
+) -> Result<String, TimezoneError>
+where
+    K: Keyword + Clone + 'd,
+    I: ParseId + Clone + 'd,
+{
+    let mut calendar = IcsWriter::default();
+    calendar.line("BEGIN:VCALENDAR");
+    calendar.line("VERSION:2.0");
+    calendar.line("PRODID:-//orgish//orgish-tools//EN");
+    calendar.line("CALSCALE:GREGORIAN");
+    for document in documents {
+        let zone = document.timezone().transpose()?;
+        // Invalid keyword sets are ignored, as if they weren't given
+        let document_keywords = document
+            .keyword_set()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        let keywords = keywords.unwrap_or(&document_keywords);
+        let mut path = Vec::new();
+        for node in document.root.children() {
+            export_node(node, zone, &mut path, now, keywords, &mut calendar)?;
+        }
+    }
+    calendar.line("END:VCALENDAR");
+
+    Ok(calendar.contents)
+}
+
+/// Exports the given node and all its children into the given calendar. The given path should be
+/// that of the node's ancestors, and the given timezone the one it inherits, if any.
+fn export_node<K: Keyword + Clone, I: ParseId + Clone>(
+    node: &Node<K, I>,
+    zone: Option<Tz>,
+    path: &mut Vec<String>,
+    now: NaiveDateTime,
+    keywords: &KeywordSet,
+    calendar: &mut IcsWriter,
+) -> Result<(), TimezoneError> {
+    // Property values are plain strings here, so the format doesn't matter
+    let zone = node.timezone(Format::Org).transpose()?.or(zone);
+    let base_uid = if node.properties.id.is_some() {
+        node.properties.id.clone().into_string()
+    } else {
+        let hash = stable_hash(path.iter().chain([&node.title]).map(String::as_str));
+        format!("{hash:016x}@orgish")
+    };
+    let mut uids = (0..).map(|idx| {
+        if idx == 0 {
+            base_uid.clone()
+        } else {
+            format!("{base_uid}-{idx}")
+        }
+    });
+
+    let planning = &node.planning;
+    if planning.scheduled.is_some() || planning.deadline.is_some() {
+        calendar.line("BEGIN:VTODO");
+        calendar.common_properties(node, uids.next().unwrap(), &base_uid, now);
+        let scheduled = planning.scheduled.as_ref().map(|ts| &ts.start);
+        let deadline = planning.deadline.as_ref().map(|ts| &ts.start);
+        // These must be of the same type, so we can't have a date and a time
+        if let Some(scheduled) = scheduled {
+            let start = matching_datetime(scheduled, deadline.unwrap_or(scheduled));
+            calendar.datetime("DTSTART", &start, zone);
+        }
+        if let Some(deadline) = deadline {
+            let due = matching_datetime(deadline, scheduled.unwrap_or(deadline));
+            calendar.datetime("DUE", &due, zone);
+        }
+        if let Some(repeater) = planning
+            .scheduled
+            .as_ref()
+            .and_then(|ts| ts.repeater.as_ref())
+            .or(planning
+                .deadline
+                .as_ref()
+                .and_then(|ts| ts.repeater.as_ref()))
+        {
+            calendar.line(&rrule(repeater));
+        }
+        if node
+            .keyword
+            .as_ref()
+            .is_some_and(|keyword| keywords.is_done(keyword))
+        {
+            calendar.line("STATUS:COMPLETED");
+            if let Some(closed) = &planning.closed {
+                calendar.datetime("COMPLETED", &closed.start, zone);
+            }
+        } else {
+            calendar.line("STATUS:NEEDS-ACTION");
+        }
+        calendar.line("END:VTODO");
+    }
+
+    for timestamp in node.timestamps.iter().filter(|ts| ts.active) {
+        calendar.line("BEGIN:VEVENT");
+        calendar.common_properties(node, uids.next().unwrap(), &base_uid, now);
+        calendar.event_times(timestamp, zone);
+        if let Some(repeater) = &timestamp.repeater {
+            calendar.line(&rrule(repeater));
+        }
+        calendar.line("END:VEVENT");
+    }
+
+    path.push(node.title.clone());
+    for child in node.children() {
+        export_node(child, zone, path, now, keywords, calendar)?;
+    }
+    path.pop();
+
+    Ok(())
+}
+
+/// Converts the given repeater into an iCalendar recurrence rule. All kinds of repeaters are
+/// treated the same, as iCalendar has no notion of completion-based recurrence.
+fn rrule(repeater: &Repeater) -> String {
+    let freq = match repeater.unit {
+        RepeaterUnit::Hour => "HOURLY",
+        RepeaterUnit::Day => "DAILY",
+        RepeaterUnit::Week => "WEEKLY",
+        RepeaterUnit::Month => "MONTHLY",
+        RepeaterUnit::Year => "YEARLY",
+    };
+    format!("RRULE:FREQ={freq};INTERVAL={}", repeater.count)
+}
+
+/// Gives the given date and time the time of the reference, if it doesn't have one of its own.
+/// iCalendar requires some pairs of properties to be both dates or both date-times, so, if either
+/// of a pair has a time, they should both be passed through this (with the other as the
+/// reference) to make them both date-times without losing any times.
+fn matching_datetime(datetime: &DateTime, reference: &DateTime) -> DateTime {
+    DateTime {
+        date: datetime.date,
+        time: datetime.time.or(reference.time),
+    }
+}
+
+/// Hashes the given strings with 64-bit FNV-1a. Unlike the hashers in the standard library, this is
+/// guaranteed to give the same results across versions and platforms.
+fn stable_hash<'s>(parts: impl IntoIterator<Item = &'s str>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        // Each part is terminated, so `a/bc` and `ab/c` won't collide
+        for byte in part.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    hash
+}
+
+/// A simple writer for iCalendar files, which handles line endings, folding, and escaping.
+#[derive(Default)]
+struct IcsWriter {
+    contents: String,
+}
+impl IcsWriter {
+    /// Writes the given content line, folding it if it's longer than 75 octets.
+    fn line(&mut self, line: &str) {
+        let mut len = 0;
+        for c in line.chars() {
+            if len + c.len_utf8() > 75 {
+                self.contents.push_str("\r\n ");
+                // The space counts towards the next line
+                len = 1;
+            }
+            self.contents.push(c);
+            len += c.len_utf8();
+        }
+        self.contents.push_str("\r\n");
+    }
+    /// Writes a property with the given text value, escaping it.
+    fn text(&mut self, name: &str, value: &str) {
+        let escaped = value
+            .replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace('\n', "\\n");
+        self.line(&format!("{name}:{escaped}"));
+    }
+    /// Writes a property with the given date or date-time value. Times will be converted to UTC if
+    /// the timezone is known.
+    fn datetime(&mut self, name: &str, datetime: &DateTime, zone: Option<Tz>) {
+        let value = match (datetime.time, zone) {
+            (None, _) => format!(";VALUE=DATE:{}", datetime.date.format("%Y%m%d")),
+            (Some(_), Some(zone)) => match datetime.in_timezone(&zone) {
+                Some(resolved) => format!(":{}", resolved.naive_utc().format("%Y%m%dT%H%M%SZ")),
+                None => format!(":{}", naive(datetime).format("%Y%m%dT%H%M%S")),
+            },
+            (Some(_), None) => format!(":{}", naive(datetime).format("%Y%m%dT%H%M%S")),
+        };
+        self.line(&format!("{name}{value}"));
+    }
+    /// Writes the properties shared by all components for the given node, which should have the
+    /// given UID for its first component.
+    fn common_properties<K: Keyword, I: ParseId>(
+        &mut self,
+        node: &Node<K, I>,
+        uid: String,
+        base_uid: &str,
+        now: NaiveDateTime,
+    ) {
+        self.text("UID", &uid);
+        if uid != base_uid {
+            self.text("X-ORGISH-ID", base_uid);
+        }
+        self.line(&format!("DTSTAMP:{}", now.format("%Y%m%dT%H%M%SZ")));
+        self.text("SUMMARY", &node.title);
+        if let Some(body) = node.body.as_deref().map(str::trim) {
+            if !body.is_empty() {
+                self.text("DESCRIPTION", body);
+            }
+        }
+        if !node.tags.is_empty() {
+            let tags = node
+                .tags
+                .iter()
+                .map(|tag| tag.replace('\\', "\\\\").replace(',', "\\,"))
+                .collect::<Vec<_>>();
+            self.line(&format!("CATEGORIES:{}", tags.join(",")));
+        }
+        // Org's default priorities are A (highest) to C (lowest)
+        let priority = match node.priority.0.as_deref() {
+            Some("A") => Some(1),
+            Some("B") => Some(5),
+            Some("C") => Some(9),
+            _ => None,
+        };
+        if let Some(priority) = priority {
+            self.line(&format!("PRIORITY:{priority}"));
+        }
+    }
+    /// Writes the start and end of an event from the given timestamp. Ends of untimed events are
+    /// exclusive in iCalendar, so they will be moved forward by a day.
+    fn event_times(&mut self, timestamp: &Timestamp, zone: Option<Tz>) {
+        let start = &timestamp.start;
+        let end = timestamp.end.as_ref();
+        self.datetime(
+            "DTSTART",
+            &matching_datetime(start, end.unwrap_or(start)),
+            zone,
+        );
+        if let Some(end) = end {
+            let mut end = matching_datetime(end, start);
+            if end.time.is_none() {
+                end.date += Duration::try_days(1).unwrap();
+            }
+            self.datetime("DTEND", &end, zone);
+        }
+    }
+}
+
+/// Gets the naive date-time for the given date and time (which should have a time).
+fn naive(datetime: &DateTime) -> NaiveDateTime {
+    datetime.date.and_time(datetime.time.unwrap_or_default())
+}
+
+/// The options for importing an iCalendar file.
+#[derive(Clone)]
+pub struct IcsImportOptions<K: Keyword> {
+    /// The keyword to give todos that haven't been completed.
+    pub todo_keyword: K,
+    /// The keyword to give completed todos.
+    pub done_keyword: K,
+    /// The timezone to write times in. If this is set, times given in UTC or with a known `TZID`
+    /// will be converted to it. Otherwise, all times are taken as they're written (which, for UTC
+    /// times, means they'll be in UTC). Floating times are never converted.
+    pub timezone: Option<Tz>,
+}
+
+/// Errors that can occur while importing an iCalendar file.
+#[derive(Error, Debug)]
+pub enum IcsError {
+    #[error("invalid content line '{line}'")]
+    InvalidLine { line: String },
+    #[error("found 'END:{component}' without a matching 'BEGIN'")]
+    UnexpectedEnd { component: String },
+    #[error("'{component}' component was never ended")]
+    UnterminatedComponent { component: String },
+    #[error("'{component}' component has no uid")]
+    MissingUid { component: String },
+    #[error("uid '{uid}' is not a valid identifier")]
+    InvalidUid { uid: String },
+    #[error("event '{uid}' has no start")]
+    MissingStart { uid: String },
+    #[error("invalid date or date-time '{value}' in property '{property}'")]
+    InvalidDateTime { property: String, value: String },
+    #[error("invalid duration '{value}'")]
+    InvalidDuration { value: String },
+}
+
+/// Imports the events and todos in the given RFC 5545 (iCalendar) calendar as level 1 nodes. This
+/// is the inverse of [`export_ics`]: events become nodes with an active timestamp in their
+/// headings, and todos become nodes with a keyword, and `SCHEDULED` and `DEADLINE` planning from
+/// their start and due dates. Recurrence rules that only have a frequency and interval become
+/// repeaters, and any others are ignored, leaving just the first occurrence.
+///
+/// Each node will be given the UID of its component as its ID, so the nodes can be merged into a
+/// document with [`crate::merge_by_uid`] without duplicating those imported before. Components
+/// with an `X-ORGISH-ID` property (which [`export_ics`] gives to the extra components of nodes
+/// that produce several) are instead combined into the node with that ID. For the same
+/// reason, components that modify a single instance of a recurring event (i.e. those with a
+/// `RECURRENCE-ID`) are skipped, as they share the UID of the whole event. If the calendar has
+/// several components with the same UID otherwise, only the last will be imported (in the place
+/// of the first).
+pub fn import_ics<K, I>(
+    ics: &str,
+    options: &IcsImportOptions<K>,
+) -> Result<DocumentFragment<K, I>, IcsError>
+where
+    K: Keyword + Clone,
+    I: ParseId,
+{
+    let mut nodes = Vec::new();
+    // The index in `nodes` of the node imported for each UID, and for each node ID
+    let mut uids = HashMap::new();
+    let mut ids = HashMap::new();
+    // The components we're inside, and the properties of the event or todo we're in, if any
+    let mut components: Vec<String> = Vec::new();
+    let mut properties = Vec::new();
+    for line in unfold(ics) {
+        let line = ContentLine::parse(&line)?;
+        match line.name.as_str() {
+            "BEGIN" => components.push(line.value.to_uppercase()),
+            "END" => {
+                let component = line.value.to_uppercase();
+                if components.pop().as_ref() != Some(&component) {
+                    return Err(IcsError::UnexpectedEnd { component });
+                }
+                if component == "VEVENT" || component == "VTODO" {
+                    let properties = std::mem::take(&mut properties);
+                    if let Some((uid, id, node)) =
+                        import_component(&component, &properties, options)?
+                    {
+                        match (uids.get(&uid), ids.get(&id)) {
+                            (Some(&idx), _) => nodes[idx] = node,
+                            (None, Some(&idx)) => {
+                                combine_components(&mut nodes[idx], node);
+                                uids.insert(uid, idx);
+                            }
+                            (None, None) => {
+                                uids.insert(uid, nodes.len());
+                                ids.insert(id, nodes.len());
+                                nodes.push(node);
+                            }
+                        }
+                    }
+                }
+            }
+            // Properties of nested components (e.g. alarms) are ignored
+            _ => {
+                if matches!(
+                    components.last().map(String::as_str),
+                    Some("VEVENT" | "VTODO")
+                ) {
+                    properties.push(line);
+                }
+            }
+        }
+    }
+    if let Some(component) = components.pop() {
+        return Err(IcsError::UnterminatedComponent { component });
+    }
+
+    Ok(nodes)
+}
+
+/// Creates a node from the properties of the given event or todo component, returning it with the
+/// component's UID and the node's ID (from `X-ORGISH-ID`, or otherwise the UID). This will return
+/// `None` if the component should be skipped.
+#[allow(clippy::type_complexity)]
+fn import_component<K: Keyword + Clone, I: ParseId>(
+    component: &str,
+    properties: &[ContentLine],
+    options: &IcsImportOptions<K>,
+) -> Result<Option<(String, String, Node<K, I>)>, IcsError> {
+    let get = |name: &str| properties.iter().find(|property| property.name == name);
+    if get("RECURRENCE-ID").is_some() {
+        return Ok(None);
+    }
+
+    let uid = get("UID")
+        .map(|uid| unescape(&uid.value))
+        .ok_or_else(|| IcsError::MissingUid {
+            component: component.to_string(),
+        })?;
+    let node_id = get("X-ORGISH-ID")
+        .map(|id| unescape(&id.value))
+        .unwrap_or_else(|| uid.clone());
+    let id = I::parse(&node_id).ok_or_else(|| IcsError::InvalidUid {
+        uid: node_id.clone(),
+    })?;
+    let title = get("SUMMARY")
+        .map(|summary| unescape(&summary.value))
+        .unwrap_or_default();
+    let body = get("DESCRIPTION").map(|description| unescape(&description.value));
+
+    let mut node = Node::new(1, title, body);
+    node.properties.id = id;
+    // Tags can't contain spaces
+    *node.tags = properties
+        .iter()
+        .filter(|property| property.name == "CATEGORIES")
+        .flat_map(|property| split_list(&property.value))
+        .map(|category| category.trim().replace(char::is_whitespace, "_"))
+        .filter(|category| !category.is_empty())
+        .collect();
+    // Org's default priorities are A (highest) to C (lowest), and iCalendar's 1 to 9
+    node.priority = Priority(
+        match get("PRIORITY").and_then(|priority| priority.value.trim().parse::<u8>().ok()) {
+            Some(1..=4) => Some("A".to_string()),
+            Some(5) => Some("B".to_string()),
+            Some(6..=9) => Some("C".to_string()),
+            _ => None,
+        },
+    );
+
+    let zone = options.timezone;
+    let start = get("DTSTART")
+        .map(|start| start.datetime(zone))
+        .transpose()?;
+    let repeater = get("RRULE").and_then(|rrule| rrule_repeater(&rrule.value));
+    let timestamp = |start: DateTime, end: Option<DateTime>, active: bool| Timestamp {
+        start,
+        end,
+        repeater: if active { repeater.clone() } else { None },
+        delay: None,
+        active,
+    };
+
+    if component == "VTODO" {
+        let done =
+            get("STATUS").is_some_and(|status| status.value.eq_ignore_ascii_case("COMPLETED"));
+        node.keyword = Some(if done {
+            options.done_keyword.clone()
+        } else {
+            options.todo_keyword.clone()
+        });
+        node.planning.scheduled = start.map(|start| timestamp(start, None, true));
+        if let Some(due) = get("DUE") {
+            node.planning.deadline = Some(timestamp(due.datetime(zone)?, None, true));
+        }
+        if let Some(completed) = get("COMPLETED").filter(|_| done) {
+            node.planning.closed = Some(timestamp(completed.datetime(zone)?, None, false));
+        }
+    } else {
+        let start = start.ok_or_else(|| IcsError::MissingStart { uid: uid.clone() })?;
+        let end = match (get("DTEND"), get("DURATION")) {
+            (Some(end), _) => Some(end.datetime(zone)?),
+            (None, Some(duration)) => {
+                let duration =
+                    parse_duration(&duration.value).ok_or_else(|| IcsError::InvalidDuration {
+                        value: duration.value.clone(),
+                    })?;
+                let end = naive(&start) + duration;
+                Some(DateTime {
+                    date: end.date(),
+                    time: start.time.map(|_| end.time()),
+                })
+            }
+            (None, None) => None,
+        };
+        let end = end
+            .map(|mut end| {
+                // Ends of untimed events are exclusive in iCalendar
+                if end.time.is_none() {
+                    end.date = end.date.pred_opt().unwrap_or(end.date);
+                }
+                end
+            })
+            .filter(|end| (end.time.is_some() && *end != start) || end.date > start.date);
+        node.timestamps.push(timestamp(start, end, true));
+    }
+
+    Ok(Some((uid, node_id, node)))
+}
+
+/// Combines the given node, imported from one of the extra components of a node exported with
+/// [`export_ics`], into the node imported from its other components. Only the todo has a keyword
+/// and planning, and the events each have one timestamp.
+fn combine_components<K: Keyword, I: ParseId>(node: &mut Node<K, I>, other: Node<K, I>) {
+    node.timestamps.extend(other.timestamps);
+    if other.keyword.is_some() {
+        node.keyword = other.keyword;
+        node.planning = other.planning;
+    }
+}
+
+/// Converts the given recurrence rule into a repeater, if it can be expressed as one. This is only
+/// the case for rules with just a frequency and an interval (and a week start, which doesn't
+/// matter without other parts).
+fn rrule_repeater(rule: &str) -> Option<Repeater> {
+    let mut unit = None;
+    let mut count = 1;
+    for part in rule.trim().split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                unit = Some(match value.to_uppercase().as_str() {
+                    "HOURLY" => RepeaterUnit::Hour,
+                    "DAILY" => RepeaterUnit::Day,
+                    "WEEKLY" => RepeaterUnit::Week,
+                    "MONTHLY" => RepeaterUnit::Month,
+                    "YEARLY" => RepeaterUnit::Year,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => count = value.parse().ok().filter(|count| *count > 0)?,
+            "WKST" => {}
+            // Anything else (e.g. `COUNT`, `UNTIL`, or `BYDAY`) can't be expressed
+            _ => return None,
+        }
+    }
+
+    Some(Repeater {
+        count,
+        unit: unit?,
+        kind: RepeaterKind::Cumulate,
+        habit_max: None,
+    })
+}
+
+/// Parses the given (positive) iCalendar duration, like `P1D` or `PT1H30M`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let value = value.strip_prefix('+').unwrap_or(value).strip_prefix('P')?;
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if number.is_empty() => in_time = true,
+            _ => {
+                let n = number.parse().ok()?;
+                number.clear();
+                duration += match (c, in_time) {
+                    ('W', false) => Duration::try_weeks(n)?,
+                    ('D', false) => Duration::try_days(n)?,
+                    ('H', true) => Duration::try_hours(n)?,
+                    ('M', true) => Duration::try_minutes(n)?,
+                    ('S', true) => Duration::try_seconds(n)?,
+                    _ => return None,
+                };
+            }
+        }
+    }
+
+    number.is_empty().then_some(duration)
+}
+
+/// Unfolds the lines of the given iCalendar file, joining those that were folded at 75 octets.
+/// Blank lines are removed.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        if let Some(continuation) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(continuation);
+                continue;
+            }
+        }
+        if !line.trim().is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+
+    lines
+}
+
+/// Unescapes the given text value.
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+
+    unescaped
+}
+
+/// Splits the given list value on its unescaped commas, unescaping each item.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => items.push(String::new()),
+            '\\' if !escaped => escaped = true,
+            _ => escaped = false,
+        }
+        items.last_mut().unwrap().push(c);
+    }
+
+    items
+        .into_iter()
+        .map(|item| unescape(item.strip_prefix(',').unwrap_or(&item)))
+        .collect()
+}
+
+/// A single parsed content line from an iCalendar file, like `DTSTART;TZID=Europe/London:...`.
+struct ContentLine {
+    /// The name of the property, in uppercase.
+    name: String,
+    /// The parameters of the property, with their names in uppercase and any quotes removed.
+    params: Vec<(String, String)>,
+    /// The raw value of the property.
+    value: String,
+}
+impl ContentLine {
+    /// Parses the given (unfolded) line.
+    fn parse(line: &str) -> Result<Self, IcsError> {
+        // Colons and semicolons inside quoted parameter values don't count
+        let mut in_quotes = false;
+        let mut parts = Vec::new();
+        let mut part_start = 0;
+        let mut value_start = None;
+        for (idx, c) in line.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ';' if !in_quotes => {
+                    parts.push(&line[part_start..idx]);
+                    part_start = idx + 1;
+                }
+                ':' if !in_quotes => {
+                    parts.push(&line[part_start..idx]);
+                    value_start = Some(idx + 1);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let invalid = || IcsError::InvalidLine {
+            line: line.to_string(),
+        };
+        let value_start = value_start.ok_or_else(invalid)?;
+
+        let mut parts = parts.into_iter();
+        let name = parts
+            .next()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(invalid)?;
+        let params = parts
+            .map(|param| {
+                let (key, value) = param.split_once('=').ok_or_else(invalid)?;
+                Ok((key.to_uppercase(), value.replace('"', "")))
+            })
+            .collect::<Result<_, IcsError>>()?;
+
+        Ok(Self {
+            name: name.to_uppercase(),
+            params,
+            value: line[value_start..].to_string(),
+        })
+    }
+    /// Gets the value of the given parameter, if it was set.
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+    /// Parses the value of this property as a date or date-time. Times in UTC, or with a `TZID`
+    /// (which is the name of a known timezone) will be converted to the given timezone, if there
+    /// is one.
+    fn datetime(&self, zone: Option<Tz>) -> Result<DateTime, IcsError> {
+        let invalid = || IcsError::InvalidDateTime {
+            property: self.name.clone(),
+            value: self.value.clone(),
+        };
+        let value = self.value.trim();
+        if self.param("VALUE") == Some("DATE") || value.len() == 8 {
+            let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+            return Ok(DateTime { date, time: None });
+        }
+
+        let (value, utc) = match value.strip_suffix('Z') {
+            Some(value) => (value, true),
+            None => (value, false),
+        };
+        let datetime =
+            NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        // Some calendars use custom timezone definitions, which we treat as floating times
+        let from = if utc {
+            Some(Tz::UTC)
+        } else {
+            self.param("TZID")
+                .and_then(|tzid| tzid.trim_start_matches('/').parse::<Tz>().ok())
+        };
+        let datetime = match (from, zone) {
+            (Some(from), Some(to)) => from
+                .from_local_datetime(&datetime)
+                .earliest()
+                .map(|resolved| resolved.with_timezone(&to).naive_local())
+                .unwrap_or(datetime),
+            _ => datetime,
+        };
+
+        Ok(DateTime {
+            date: datetime.date(),
+            time: Some(datetime.time()),
+        })
+    }
+}
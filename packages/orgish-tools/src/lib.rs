@@ -1,7 +1,15 @@
+mod agenda;
+mod ics;
 mod mark_done;
+mod query;
 mod refile;
+#[cfg(test)]
+mod tests;
 
+pub use agenda::*;
+pub use ics::*;
 pub use mark_done::*;
+pub use query::*;
 pub use refile::*;
 
 use orgish::{Node, StringId};
@@ -1,35 +1,41 @@
 use crate::DocumentFragment;
 use chrono::NaiveDateTime;
-use orgish::{timestamp::DateTime, Keyword, Node, ParseId, Timestamp};
+use orgish::{keyword::Workflow, timestamp::DateTime, Keyword, Node, ParseId, Timestamp};
 
-/// Marks all top-level nodes as done in the given document. This takes a keyword to be interpreted
-/// as `DONE`, meaning it can be used to convert nodes into other states like Org's traditional
-/// `KILL`. The main thing this function does is handles timestamps with repeaters by moving them
-/// to their next iteration.
+/// Marks all top-level nodes as done in the given document. The main thing this function does is
+/// handles timestamps with repeaters by moving them to their next iteration.
 ///
-/// The keywords repesenting the `DONE` state may be different for nodes that repeat and those that
-/// don't. (E.g. repeating nodes may return to `TODO`.)
+/// The keywords to use come from the given workflow (e.g. a [`orgish::keyword::KeywordSet`] read
+/// from the document's `#+TODO`). Nodes that don't repeat will be given the workflow's first done
+/// state (if it has no done states, their keywords won't be changed). Repeating nodes will keep
+/// their current keyword if it's not a done state, as they still need to be done, and will
+/// otherwise be given the workflow's first state that still needs to be done.
 ///
-/// This also takes a date relative to which the timestamps will be updated (i.e. their next repeat
-/// after this date will be inserted, for those which repeat). Non-repeating timestamps in nodes
-/// that have one or more repeating timestamps will be removed outright.
+/// This also takes the time at which the nodes were completed, relative to which the timestamps
+/// will be updated according to the kinds of their repeaters (see
+/// [`Timestamp::into_next_repeat_from`]). Non-repeating timestamps in nodes that have one or more
+/// repeating timestamps will be removed outright.
 ///
-/// For repeating nodes, the `LAST_REPEAT` node will automatically be set if `completion_time` is
-/// provided.
+/// For repeating nodes, the `LAST_REPEAT` property will be set to the completion time if
+/// `set_last_repeat` is `true`.
 pub fn mark_nodes_done<K: Keyword + Clone, I: ParseId + Clone>(
     nodes: DocumentFragment<K, I>,
-    new_keyword_repeating: K,
-    new_keyword_not_repeating: K,
-    completion_time: Option<NaiveDateTime>,
+    workflow: &impl Workflow<K>,
+    completion_time: NaiveDateTime,
+    set_last_repeat: bool,
 ) -> Vec<CompletedNode<K, I>> {
     // Go through all the top-level nodes (any underneath won't be changed, they'll be
     // left entirely alone)
     let mut annotated_nodes = Vec::new();
+    let done_keyword = workflow.first_done();
+    let todo_keyword = workflow.first_todo();
     for mut node in nodes {
         // If the node repeats, we might need to put it in two places
         let mut repeating_node = node.clone();
         // This is all we need to do if it doesn't repeat
-        node.keyword = Some(new_keyword_not_repeating.clone());
+        if let Some(done_keyword) = &done_keyword {
+            node.keyword = Some(done_keyword.clone());
+        }
 
         // If any of the timestamps in the node repeats (deadline, scheduled time, anything), we
         // should keep this node around. All non-repeating timestamps will be axed.
@@ -37,7 +43,7 @@ pub fn mark_nodes_done<K: Keyword + Clone, I: ParseId + Clone>(
         repeating_node.timestamps = repeating_node
             .timestamps
             .into_iter()
-            .map(|ts| ts.into_next_repeat())
+            .map(|ts| ts.into_next_repeat_from(completion_time))
             .filter_map(|ts_opt| ts_opt.ok())
             .collect();
         if !repeating_node.timestamps.is_empty() {
@@ -46,24 +52,21 @@ pub fn mark_nodes_done<K: Keyword + Clone, I: ParseId + Clone>(
         repeating_node.planning.deadline = repeating_node
             .planning
             .deadline
-            .map(|ts| ts.into_next_repeat().ok())
-            .flatten();
+            .and_then(|ts| ts.into_next_repeat_from(completion_time).ok());
         if repeating_node.planning.deadline.is_some() {
             has_repeating_ts = true;
         }
         repeating_node.planning.scheduled = repeating_node
             .planning
             .scheduled
-            .map(|ts| ts.into_next_repeat().ok())
-            .flatten();
+            .and_then(|ts| ts.into_next_repeat_from(completion_time).ok());
         if repeating_node.planning.scheduled.is_some() {
             has_repeating_ts = true;
         }
         repeating_node.planning.closed = repeating_node
             .planning
             .closed
-            .map(|ts| ts.into_next_repeat().ok())
-            .flatten();
+            .and_then(|ts| ts.into_next_repeat_from(completion_time).ok());
         if repeating_node.planning.closed.is_some() {
             has_repeating_ts = true;
         }
@@ -73,9 +76,11 @@ pub fn mark_nodes_done<K: Keyword + Clone, I: ParseId + Clone>(
         //
         // Otherwise, add the node with everything intact, but its keyword changed to `DONE`,
         if has_repeating_ts {
-            repeating_node.keyword = Some(new_keyword_repeating.clone());
-            // If we have a completion time, set `LAST_REPEAT`
-            if let Some(completion_time) = completion_time {
+            repeating_node.keyword = match repeating_node.keyword {
+                Some(keyword) if !workflow.is_done(&keyword) => Some(keyword),
+                keyword => todo_keyword.clone().or(keyword),
+            };
+            if set_last_repeat {
                 repeating_node.properties.insert(
                     "LAST_REPEAT".to_string(),
                     // This is a string timestamp, and won't be parsed by Orgish automatically, so
@@ -87,6 +92,7 @@ pub fn mark_nodes_done<K: Keyword + Clone, I: ParseId + Clone>(
                         },
                         end: None,
                         repeater: None,
+                        delay: None,
                         active: false,
                     }
                     .into_string(),
@@ -105,6 +111,7 @@ pub fn mark_nodes_done<K: Keyword + Clone, I: ParseId + Clone>(
 }
 
 /// A representation of a completed node.
+#[allow(clippy::large_enum_variant)]
 pub enum CompletedNode<K: Keyword, I: ParseId> {
     /// The node is completed and does not repeat.
     Done(Node<K, I>),
@@ -0,0 +1,890 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use orgish::{
+    error::TimestampInputError,
+    keyword::{KeywordSet, Workflow},
+    priority::PriorityModel,
+    tags::TagConfig,
+    Document, Keyword, Node, ParseId, Timestamp,
+};
+use std::cmp::Ordering;
+use thiserror::Error;
+
+/// A parsed Org mode tags/property match query, like `+work-meeting&PRIORITY="A"|TODO="NEXT"`.
+///
+/// The syntax is that of Org's tag searches: a series of alternatives separated by `|`, each of
+/// which is a series of terms that must all match (optionally separated by `&`). Terms can be
+/// prefixed with `+` (the default) or `-` to require or exclude them, and are either tags, which
+/// match the effective tags of a node (see [`Document::effective_tags`]), or property
+/// comparisons like `PROP="value"`, `PROP>=5`, or `DEADLINE<"<+1w>"`. The operators are `=`,
+/// `<>` (or `!=`), `<`, `<=`, `>`, and `>=`.
+///
+/// Comparison values in quotes are compared as strings, unless they're timestamps in angle
+/// brackets (like `"<2024-05-01 Wed>"`, `"<today>"`, `"<now>"`, or `"<+1w>"`), in which case
+/// they're compared as times, and values without quotes are compared as numbers. Properties
+/// which can't be interpreted as the kind of value they're compared to never match, except that
+/// missing properties are taken to be empty strings in string comparisons.
+///
+/// Besides a node's own properties (whose names are case-insensitive), these special properties
+/// are supported: `TODO` (the keyword), `PRIORITY` (the effective priority, using the document's
+/// `#+PRIORITIES`), `LEVEL`, `ITEM` (the title), `ID`, `DEADLINE`, `SCHEDULED`, `CLOSED`, `TAGS`
+/// (the node's own tags, like `:a:b:`), and `ALLTAGS` (its effective tags, in the same format).
+///
+/// The query can also end with a `/` and a match over keywords (e.g. `+work/TODO|NEXT` or
+/// `/-DONE`), which must also match. If this starts with `!`, only nodes with keywords that aren't
+/// done states (according to the document's `#+TODO`) will match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchQuery {
+    /// The alternatives for the tags and properties part.
+    tags: Vec<Vec<Condition>>,
+    /// The alternatives for the keywords part, if there is one.
+    keywords: Option<Vec<Vec<Condition>>>,
+    /// Whether or not nodes must have a keyword that isn't a done state.
+    not_done: bool,
+}
+
+/// A single term in a query, which may be negated.
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    negated: bool,
+    term: Term,
+}
+
+/// The things a query can match on.
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// The node has the given tag, including through inheritance.
+    Tag(String),
+    /// The node has the given keyword.
+    Keyword(String),
+    /// The node has a keyword that isn't a done state (used by [`Query::not_done`]).
+    NotDone,
+    /// The given property compares to the given value in the given way.
+    Property {
+        name: String,
+        op: ComparisonOp,
+        value: Value,
+    },
+    /// The node is scheduled for a date in the given span (inclusive).
+    ScheduledWithin(NaiveDate, NaiveDate),
+    /// The node has a deadline on a date in the given span (inclusive).
+    DeadlineWithin(NaiveDate, NaiveDate),
+}
+
+/// The ways properties can be compared with values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComparisonOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+impl ComparisonOp {
+    /// Returns whether or not the given ordering satisfies this comparison.
+    fn accepts(&self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering == Ordering::Equal,
+            Self::Ne => ordering != Ordering::Equal,
+            Self::Lt => ordering == Ordering::Less,
+            Self::Le => ordering != Ordering::Greater,
+            Self::Gt => ordering == Ordering::Greater,
+            Self::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// The values properties can be compared with.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Number(f64),
+    /// A timestamp, as written between the angle brackets. This is resolved when the query is
+    /// run, as it may be relative to the current time.
+    Time(String),
+}
+
+/// Errors that can occur while parsing a match query.
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("query is empty")]
+    Empty,
+    #[error("unexpected '{found}' at position {position} in query")]
+    UnexpectedChar { found: char, position: usize },
+    #[error("unexpected end of query (expected {expected})")]
+    UnexpectedEnd { expected: &'static str },
+    #[error("regular expressions aren't supported in queries (found at position {position})")]
+    RegexUnsupported { position: usize },
+    #[error("keywords can't be compared to values (found '{keyword}')")]
+    KeywordComparison { keyword: String },
+    #[error("invalid timestamp '{value}' in query")]
+    InvalidTimestamp {
+        value: String,
+        #[source]
+        source: TimestampInputError,
+    },
+}
+
+impl MatchQuery {
+    /// Parses the given query. See [`MatchQuery`] for the syntax.
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        let mut parser = QueryParser {
+            chars: query.char_indices().collect(),
+            idx: 0,
+        };
+        let tags = parser.alternatives(false)?;
+        let (keywords, not_done) = if parser.eat('/') {
+            let not_done = parser.eat('!');
+            let keywords = parser.alternatives(true)?;
+            // `/!` on its own is fine, it just requires a keyword that isn't done
+            let keywords = (!keywords.iter().all(Vec::is_empty)).then_some(keywords);
+            (keywords, not_done)
+        } else {
+            (None, false)
+        };
+        if let Some((position, found)) = parser.peek() {
+            return Err(QueryError::UnexpectedChar { found, position });
+        }
+        if tags.iter().all(Vec::is_empty) && keywords.is_none() && !not_done {
+            return Err(QueryError::Empty);
+        }
+
+        Ok(Self {
+            tags,
+            keywords,
+            not_done,
+        })
+    }
+    /// Returns whether or not the given node matches this query. This needs the effective tags of
+    /// the node, and the configuration of the document it's in.
+    pub fn matches<K: Keyword + Clone, I: ParseId + Clone>(
+        &self,
+        node: &Node<K, I>,
+        tags: &[String],
+        context: &QueryContext,
+    ) -> bool {
+        let matches_all = |conditions: &Vec<Condition>| {
+            conditions
+                .iter()
+                .all(|condition| condition.term.matches(node, tags, context) != condition.negated)
+        };
+        // An empty set of alternatives (i.e. no tags part) matches everything
+        let tags_match = self.tags.iter().any(matches_all);
+        let keywords_match = self
+            .keywords
+            .as_ref()
+            .is_none_or(|keywords| keywords.iter().any(matches_all));
+        let done_match = !self.not_done
+            || node
+                .keyword
+                .as_ref()
+                .is_some_and(|keyword| !context.keywords.is_done(keyword));
+
+        tags_match && keywords_match && done_match
+    }
+}
+
+/// The document-level information needed to run a query on a node.
+#[derive(Debug, Clone)]
+pub struct QueryContext {
+    /// The current time, which relative timestamps in the query are resolved against.
+    pub now: NaiveDateTime,
+    /// The priority model of the document, used for the `PRIORITY` property.
+    pub priorities: PriorityModel,
+    /// The keyword set of the document, used for `/!`.
+    pub keywords: KeywordSet,
+}
+impl QueryContext {
+    /// Creates the context for running a query over the given document at the given time. Any
+    /// invalid priority or keyword definitions in the document are ignored, and the defaults
+    /// used instead.
+    pub fn for_document<K: Keyword, I: ParseId>(
+        document: &Document<K, I>,
+        now: NaiveDateTime,
+    ) -> Self {
+        Self {
+            now,
+            priorities: document
+                .priority_model()
+                .and_then(Result::ok)
+                .unwrap_or_default(),
+            keywords: document
+                .keyword_set()
+                .and_then(Result::ok)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A query over the nodes in a document, which can be run with [`Query::find`].
+///
+/// Queries can be built up from the constructors here (like [`Query::keyword`] and
+/// [`Query::has_tag`]), and combined with [`Query::and`], [`Query::or`], [`Query::descendant_of`],
+/// and negation (with `!`). Any [`MatchQuery`] can be used as a query too (with [`Query::parse`]
+/// or [`From`]), and combined with others in the same way. Either way, they're evaluated in a
+/// single traversal of the document.
+///
+/// ```
+/// use orgish_tools::Query;
+///
+/// let query = Query::keyword("TODO")
+///     .and(Query::has_tag("work"))
+///     .and(!Query::descendant_of(Query::property_eq("ARCHIVE", "t")));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    kind: QueryKind,
+}
+
+/// The ways queries can be put together.
+#[derive(Debug, Clone, PartialEq)]
+enum QueryKind {
+    /// A single condition on the node.
+    Term(Term),
+    /// A parsed tags/property match query.
+    Match(MatchQuery),
+    /// Every one of the queries matches (which is true if there are none).
+    All(Vec<Query>),
+    /// Any one of the queries matches (which is false if there are none).
+    Any(Vec<Query>),
+    /// The query doesn't match.
+    Not(Box<Query>),
+    /// The query matches any one of the node's ancestors.
+    DescendantOf(Box<Query>),
+}
+
+impl Query {
+    /// Parses the given Org mode tags/property match query, like
+    /// `+work-meeting&PRIORITY="A"|TODO="NEXT"`. See [`MatchQuery`] for the syntax.
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        MatchQuery::parse(query).map(Self::from)
+    }
+    /// Matches nodes with the given keyword.
+    pub fn keyword(keyword: impl Into<String>) -> Self {
+        Self::term(Term::Keyword(keyword.into()))
+    }
+    /// Matches nodes with a keyword that isn't a done state, according to the `#+TODO` of their
+    /// document (or `TODO | DONE`, if it doesn't have one).
+    pub fn not_done() -> Self {
+        Self::term(Term::NotDone)
+    }
+    /// Matches nodes with the given tag, including if it's inherited or implied by a tag group
+    /// (see [`Document::effective_tags`]).
+    pub fn has_tag(tag: impl Into<String>) -> Self {
+        Self::term(Term::Tag(tag.into()))
+    }
+    /// Matches nodes where the given property has the given value. This supports the same special
+    /// properties as [`MatchQuery`] (like `PRIORITY` or `ID`), and property names are
+    /// case-insensitive. Note that a missing property is taken to be empty, so comparing with an
+    /// empty value will match nodes that don't have the property.
+    pub fn property_eq(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::term(Term::Property {
+            name: name.into(),
+            op: ComparisonOp::Eq,
+            value: Value::String(value.into()),
+        })
+    }
+    /// Matches nodes which are scheduled for a date between the given dates (inclusive),
+    /// including through a repeater.
+    pub fn scheduled_within(from: NaiveDate, to: NaiveDate) -> Self {
+        Self::term(Term::ScheduledWithin(from, to))
+    }
+    /// Matches nodes which have a deadline on a date between the given dates (inclusive),
+    /// including through a repeater.
+    pub fn deadline_within(from: NaiveDate, to: NaiveDate) -> Self {
+        Self::term(Term::DeadlineWithin(from, to))
+    }
+    /// Matches nodes which match every one of the given queries. If there are none, this will
+    /// match every node.
+    pub fn all(queries: impl IntoIterator<Item = Query>) -> Self {
+        Self {
+            kind: QueryKind::All(queries.into_iter().collect()),
+        }
+    }
+    /// Matches nodes which match any one of the given queries. If there are none, this won't
+    /// match any nodes.
+    pub fn any(queries: impl IntoIterator<Item = Query>) -> Self {
+        Self {
+            kind: QueryKind::Any(queries.into_iter().collect()),
+        }
+    }
+    /// Matches nodes which match both this query and the given one.
+    pub fn and(self, other: Query) -> Self {
+        match self.kind {
+            QueryKind::All(mut queries) => {
+                queries.push(other);
+                Self::all(queries)
+            }
+            kind => Self::all([Self { kind }, other]),
+        }
+    }
+    /// Matches nodes which match either this query or the given one.
+    pub fn or(self, other: Query) -> Self {
+        match self.kind {
+            QueryKind::Any(mut queries) => {
+                queries.push(other);
+                Self::any(queries)
+            }
+            kind => Self::any([Self { kind }, other]),
+        }
+    }
+    /// Matches nodes which have an ancestor (at any level above them) that matches the given
+    /// query.
+    pub fn descendant_of(ancestor: Query) -> Self {
+        Self {
+            kind: QueryKind::DescendantOf(Box::new(ancestor)),
+        }
+    }
+    /// Runs this query over every node in the given document, returning the nodes that match in
+    /// document order. Relative timestamps in the query (like `"<+1w>"`) will be resolved against
+    /// the given time, and the document's `#+TAGS`, `#+PRIORITIES`, and `#+TODO` will be used
+    /// when matching its nodes.
+    pub fn find<'d, K, I>(
+        &self,
+        document: &'d Document<K, I>,
+        now: NaiveDateTime,
+    ) -> Vec<QueryMatch<'d, K, I>>
+    where
+        K: Keyword + Clone + 'd,
+        I: ParseId + Clone + 'd,
+    {
+        run([document], self, now)
+    }
+
+    /// Creates a query from a single term.
+    fn term(term: Term) -> Self {
+        Self {
+            kind: QueryKind::Term(term),
+        }
+    }
+    /// Returns whether or not the given node, which has the given effective tags and ancestors,
+    /// matches this query.
+    fn matches<K: Keyword + Clone, I: ParseId + Clone>(
+        &self,
+        node: &Node<K, I>,
+        tags: &[String],
+        ancestors: &[Ancestor<K, I>],
+        context: &QueryContext,
+    ) -> bool {
+        match &self.kind {
+            QueryKind::Term(term) => term.matches(node, tags, context),
+            QueryKind::Match(query) => query.matches(node, tags, context),
+            QueryKind::All(queries) => queries
+                .iter()
+                .all(|query| query.matches(node, tags, ancestors, context)),
+            QueryKind::Any(queries) => queries
+                .iter()
+                .any(|query| query.matches(node, tags, ancestors, context)),
+            QueryKind::Not(query) => !query.matches(node, tags, ancestors, context),
+            QueryKind::DescendantOf(query) => {
+                ancestors.iter().enumerate().any(|(idx, ancestor)| {
+                    query.matches(ancestor.node, &ancestor.tags, &ancestors[..idx], context)
+                })
+            }
+        }
+    }
+}
+impl From<MatchQuery> for Query {
+    fn from(query: MatchQuery) -> Self {
+        Self {
+            kind: QueryKind::Match(query),
+        }
+    }
+}
+impl std::ops::Not for Query {
+    type Output = Query;
+
+    fn not(self) -> Self::Output {
+        match self.kind {
+            QueryKind::Not(query) => *query,
+            kind => Self {
+                kind: QueryKind::Not(Box::new(Self { kind })),
+            },
+        }
+    }
+}
+
+/// A node which matched a query.
+#[derive(Debug)]
+pub struct QueryMatch<'d, K: Keyword, I: ParseId> {
+    /// The node that matched.
+    pub node: &'d Node<K, I>,
+    /// The effective tags of the node.
+    pub tags: Vec<String>,
+    /// The titles of the ancestors of the node, from the top level down.
+    pub path: Vec<String>,
+    /// The indices of the node's ancestors among their siblings, from the top level down, followed
+    /// by the index of the node itself. Following these through the children of the root node of
+    /// the document will lead back to this node.
+    pub indices: Vec<usize>,
+    /// The index of the document the node is in, in the order the documents were given.
+    pub document: usize,
+}
+
+/// Runs the given query over every node in the given documents, returning the nodes that match in
+/// document order. Relative timestamps in the query (like `"<+1w>"`) will be resolved against the
+/// given time, and each document's `#+TAGS`, `#+PRIORITIES`, and `#+TODO` will be used when
+/// matching its nodes.
+pub fn query<'d, K, I>(
+    documents: impl IntoIterator<Item = &'d Document<K, I>>,
+    query: &MatchQuery,
+    now: NaiveDateTime,
+) -> Vec<QueryMatch<'d, K, I>>
+where
+    K: Keyword + Clone + 'd,
+    I: ParseId + Clone + 'd,
+{
+    run(documents, &Query::from(query.clone()), now)
+}
+
+/// Runs the given query over every node in the given documents, in a single traversal of each.
+fn run<'d, K, I>(
+    documents: impl IntoIterator<Item = &'d Document<K, I>>,
+    query: &Query,
+    now: NaiveDateTime,
+) -> Vec<QueryMatch<'d, K, I>>
+where
+    K: Keyword + Clone + 'd,
+    I: ParseId + Clone + 'd,
+{
+    let mut matches = Vec::new();
+    for (idx, document) in documents.into_iter().enumerate() {
+        let tag_config = document
+            .tag_config()
+            .and_then(Result::ok)
+            .unwrap_or_default();
+        let mut traversal = Traversal {
+            query,
+            context: QueryContext::for_document(document, now),
+            inherited: tag_config.passed_down(&[], &document.root.tags),
+            tag_config,
+            document: idx,
+            ancestors: Vec::new(),
+            indices: Vec::new(),
+            matches: &mut matches,
+        };
+        for (child_idx, node) in document.root.children().iter().enumerate() {
+            traversal.visit(node, child_idx);
+        }
+    }
+
+    matches
+}
+
+/// An ancestor of the node a query is being run on.
+struct Ancestor<'d, K: Keyword, I: ParseId> {
+    node: &'d Node<K, I>,
+    /// The effective tags of the ancestor.
+    tags: Vec<String>,
+    /// The tags the ancestor passes down to its children.
+    passed_down: Vec<String>,
+}
+
+/// The state of a query being run over a single document.
+struct Traversal<'d, 'q, K: Keyword, I: ParseId> {
+    query: &'q Query,
+    context: QueryContext,
+    tag_config: TagConfig,
+    /// The tags the document passes down to its top-level nodes.
+    inherited: Vec<String>,
+    /// The index of the document.
+    document: usize,
+    /// The ancestors of the current node, from the top level down.
+    ancestors: Vec<Ancestor<'d, K, I>>,
+    /// The indices of the ancestors of the current node.
+    indices: Vec<usize>,
+    matches: &'q mut Vec<QueryMatch<'d, K, I>>,
+}
+impl<'d, K: Keyword + Clone, I: ParseId + Clone> Traversal<'d, '_, K, I> {
+    /// Runs the query over the given node, which is at the given index among its siblings, and
+    /// all its children.
+    fn visit(&mut self, node: &'d Node<K, I>, idx: usize) {
+        let inherited = self
+            .ancestors
+            .last()
+            .map(|parent| &parent.passed_down)
+            .unwrap_or(&self.inherited);
+        let tags = self.tag_config.effective(inherited, &node.tags);
+        let passed_down = self.tag_config.passed_down(inherited, &node.tags);
+        self.indices.push(idx);
+        if self
+            .query
+            .matches(node, &tags, &self.ancestors, &self.context)
+        {
+            self.matches.push(QueryMatch {
+                node,
+                tags: tags.clone(),
+                path: self
+                    .ancestors
+                    .iter()
+                    .map(|ancestor| ancestor.node.title.clone())
+                    .collect(),
+                indices: self.indices.clone(),
+                document: self.document,
+            });
+        }
+
+        self.ancestors.push(Ancestor {
+            node,
+            tags,
+            passed_down,
+        });
+        for (child_idx, child) in node.children().iter().enumerate() {
+            self.visit(child, child_idx);
+        }
+        self.ancestors.pop();
+        self.indices.pop();
+    }
+}
+
+impl Term {
+    /// Returns whether or not this term matches the given node.
+    fn matches<K: Keyword + Clone, I: ParseId + Clone>(
+        &self,
+        node: &Node<K, I>,
+        tags: &[String],
+        context: &QueryContext,
+    ) -> bool {
+        let within = |timestamp: &Option<Timestamp>, from: &NaiveDate, to: &NaiveDate| {
+            timestamp
+                .as_ref()
+                .is_some_and(|timestamp| timestamp.occurrences(*from, *to).next().is_some())
+        };
+        match self {
+            Term::Tag(tag) => tags.contains(tag),
+            Term::Keyword(keyword) => node
+                .keyword
+                .as_ref()
+                .is_some_and(|k| k.clone().into_string() == *keyword),
+            Term::NotDone => node
+                .keyword
+                .as_ref()
+                .is_some_and(|keyword| !context.keywords.is_done(keyword)),
+            Term::ScheduledWithin(from, to) => within(&node.planning.scheduled, from, to),
+            Term::DeadlineWithin(from, to) => within(&node.planning.deadline, from, to),
+            Term::Property { name, op, value } => {
+                let property = property_value(node, name, tags, context);
+                let ordering = match value {
+                    Value::String(value) => Some(
+                        property
+                            .map(|property| property.into_text())
+                            .unwrap_or_default()
+                            .as_str()
+                            .cmp(value.as_str()),
+                    ),
+                    Value::Number(value) => property
+                        .and_then(|property| property.into_text().trim().parse::<f64>().ok())
+                        .and_then(|property| property.partial_cmp(value)),
+                    Value::Time(value) => {
+                        let time = resolve_time(value, context.now);
+                        property
+                            .and_then(PropertyValue::into_time)
+                            .zip(time)
+                            .map(|(property, time)| property.cmp(&time))
+                    }
+                };
+                ordering.is_some_and(|ordering| op.accepts(ordering))
+            }
+        }
+    }
+}
+
+/// The value of a property of a node, which might be a timestamp.
+enum PropertyValue {
+    Text(String),
+    Timestamp(Timestamp),
+}
+impl PropertyValue {
+    /// Gets the text of this value.
+    fn into_text(self) -> String {
+        match self {
+            Self::Text(text) => text,
+            Self::Timestamp(timestamp) => timestamp.into_string(),
+        }
+    }
+    /// Gets the start time of this value, if it's a timestamp (or text that parses as one).
+    /// Untimed timestamps start at midnight.
+    fn into_time(self) -> Option<NaiveDateTime> {
+        let timestamp = match self {
+            Self::Text(text) => Timestamp::from_str(text.trim()).ok()?,
+            Self::Timestamp(timestamp) => timestamp,
+        };
+        Some(
+            timestamp
+                .start
+                .date
+                .and_time(timestamp.start.time.unwrap_or(NaiveTime::MIN)),
+        )
+    }
+}
+
+/// Gets the value of the given property of a node, including the special properties.
+fn property_value<K: Keyword + Clone, I: ParseId + Clone>(
+    node: &Node<K, I>,
+    name: &str,
+    tags: &[String],
+    context: &QueryContext,
+) -> Option<PropertyValue> {
+    let tag_string = |tags: &[String]| {
+        if tags.is_empty() {
+            String::new()
+        } else {
+            format!(":{}:", tags.join(":"))
+        }
+    };
+    let text = match name.to_uppercase().as_str() {
+        "TODO" => node.keyword.clone().map(Keyword::into_string),
+        "PRIORITY" => Some(context.priorities.effective(&node.priority).into_string()),
+        "LEVEL" => Some(node.level().to_string()),
+        "ITEM" => Some(node.title.clone()),
+        "ID" => node
+            .properties
+            .id
+            .is_some()
+            .then(|| node.properties.id.clone().into_string()),
+        "DEADLINE" => return node.planning.deadline.clone().map(PropertyValue::Timestamp),
+        "SCHEDULED" => {
+            return node
+                .planning
+                .scheduled
+                .clone()
+                .map(PropertyValue::Timestamp)
+        }
+        "CLOSED" => return node.planning.closed.clone().map(PropertyValue::Timestamp),
+        "TAGS" => Some(tag_string(&node.tags)),
+        "ALLTAGS" => Some(tag_string(tags)),
+        _ => node
+            .properties
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone()),
+    };
+
+    text.map(PropertyValue::Text)
+}
+
+/// Resolves the given timestamp from a query (without its angle brackets) to a time. This may be
+/// `now`, or anything [`parse_time`] accepts (relative to the current date).
+fn resolve_time(value: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    if value.trim().eq_ignore_ascii_case("now") {
+        return Some(now);
+    }
+    let timestamp = parse_time(value, now.date()).ok()?;
+    Some(
+        timestamp
+            .start
+            .date
+            .and_time(timestamp.start.time.unwrap_or(NaiveTime::MIN)),
+    )
+}
+
+/// Parses the given timestamp from a query (without its angle brackets). This may be a full Org
+/// timestamp (like `2024-05-01 Wed`), or anything [`Timestamp::from_user_input`] accepts (relative
+/// to the given date).
+fn parse_time(value: &str, today: NaiveDate) -> Result<Timestamp, TimestampInputError> {
+    Timestamp::from_str(&format!("<{}>", value.trim()))
+        .or_else(|_| Timestamp::from_user_input(value, today))
+}
+
+/// A simple recursive descent parser for queries.
+struct QueryParser {
+    chars: Vec<(usize, char)>,
+    idx: usize,
+}
+impl QueryParser {
+    /// Gets the next character and its position, without consuming it.
+    fn peek(&self) -> Option<(usize, char)> {
+        self.chars.get(self.idx).copied()
+    }
+    /// Consumes the next character if it's the given one, returning whether or not it was.
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek().is_some_and(|(_, next)| next == c) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+    /// Parses a series of alternatives separated by `|`, stopping at a `/` or the end of the
+    /// query. If `keywords` is `true`, the terms are parsed as keywords rather than tags.
+    fn alternatives(&mut self, keywords: bool) -> Result<Vec<Vec<Condition>>, QueryError> {
+        let mut alternatives = vec![Vec::new()];
+        while let Some((position, c)) = self.peek() {
+            match c {
+                '/' => break,
+                '|' => {
+                    self.idx += 1;
+                    alternatives.push(Vec::new());
+                }
+                '&' => self.idx += 1,
+                '{' => return Err(QueryError::RegexUnsupported { position }),
+                _ => {
+                    let negated = if self.eat('-') {
+                        true
+                    } else {
+                        self.eat('+');
+                        false
+                    };
+                    let term = self.term(keywords)?;
+                    alternatives
+                        .last_mut()
+                        .unwrap()
+                        .push(Condition { negated, term });
+                }
+            }
+        }
+
+        Ok(alternatives)
+    }
+    /// Parses a single tag, keyword, or property comparison.
+    fn term(&mut self, keywords: bool) -> Result<Term, QueryError> {
+        let mut name = String::new();
+        while let Some((_, c)) = self.peek() {
+            if c.is_alphanumeric() || "_@#%".contains(c) {
+                name.push(c);
+                self.idx += 1;
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            return Err(match self.peek() {
+                Some((position, '{')) => QueryError::RegexUnsupported { position },
+                Some((position, found)) => QueryError::UnexpectedChar { found, position },
+                None => QueryError::UnexpectedEnd {
+                    expected: "a tag or property",
+                },
+            });
+        }
+
+        let op = self.op()?;
+        match (op, keywords) {
+            (None, false) => Ok(Term::Tag(name)),
+            (None, true) => Ok(Term::Keyword(name)),
+            (Some(_), true) => Err(QueryError::KeywordComparison { keyword: name }),
+            (Some(op), false) => Ok(Term::Property {
+                name,
+                op,
+                value: self.value()?,
+            }),
+        }
+    }
+    /// Parses a comparison operator, if there is one.
+    fn op(&mut self) -> Result<Option<ComparisonOp>, QueryError> {
+        let op = match self.peek().map(|(_, c)| c) {
+            Some('=') => {
+                self.idx += 1;
+                // Org accepts `==` too
+                self.eat('=');
+                ComparisonOp::Eq
+            }
+            Some('!') => {
+                self.idx += 1;
+                if !self.eat('=') {
+                    return Err(match self.peek() {
+                        Some((position, found)) => QueryError::UnexpectedChar { found, position },
+                        None => QueryError::UnexpectedEnd { expected: "'='" },
+                    });
+                }
+                ComparisonOp::Ne
+            }
+            Some('<') => {
+                self.idx += 1;
+                if self.eat('>') {
+                    ComparisonOp::Ne
+                } else if self.eat('=') {
+                    ComparisonOp::Le
+                } else {
+                    ComparisonOp::Lt
+                }
+            }
+            Some('>') => {
+                self.idx += 1;
+                if self.eat('=') {
+                    ComparisonOp::Ge
+                } else {
+                    ComparisonOp::Gt
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(op))
+    }
+    /// Parses a value to compare a property with.
+    fn value(&mut self) -> Result<Value, QueryError> {
+        match self.peek() {
+            Some((_, '"')) => {
+                self.idx += 1;
+                let mut value = String::new();
+                loop {
+                    match self.peek() {
+                        Some((_, '"')) => {
+                            self.idx += 1;
+                            break;
+                        }
+                        Some((_, '\\')) => {
+                            self.idx += 1;
+                            if let Some((_, c)) = self.peek() {
+                                value.push(c);
+                                self.idx += 1;
+                            }
+                        }
+                        Some((_, c)) => {
+                            value.push(c);
+                            self.idx += 1;
+                        }
+                        None => {
+                            return Err(QueryError::UnexpectedEnd {
+                                expected: "a closing '\"'",
+                            })
+                        }
+                    }
+                }
+
+                match value
+                    .strip_prefix('<')
+                    .and_then(|value| value.strip_suffix('>'))
+                {
+                    Some(time) => {
+                        // Check the timestamp now, so errors are caught early (the date doesn't
+                        // matter for this)
+                        if !time.trim().eq_ignore_ascii_case("now") {
+                            parse_time(time, NaiveDate::default()).map_err(|source| {
+                                QueryError::InvalidTimestamp {
+                                    value: value.clone(),
+                                    source,
+                                }
+                            })?;
+                        }
+                        Ok(Value::Time(time.to_string()))
+                    }
+                    None => Ok(Value::String(value)),
+                }
+            }
+            Some((position, '{')) => Err(QueryError::RegexUnsupported { position }),
+            Some((position, found)) => {
+                let mut number = String::new();
+                while let Some((_, c)) = self.peek() {
+                    if c.is_ascii_digit() || (c == '.' || c == '-' || c == '+') {
+                        // Signs are only allowed at the start
+                        if (c == '-' || c == '+') && !number.is_empty() {
+                            break;
+                        }
+                        number.push(c);
+                        self.idx += 1;
+                    } else {
+                        break;
+                    }
+                }
+                number
+                    .parse()
+                    .map(Value::Number)
+                    .map_err(|_| QueryError::UnexpectedChar { found, position })
+            }
+            None => Err(QueryError::UnexpectedEnd {
+                expected: "a value",
+            }),
+        }
+    }
+}
@@ -100,3 +100,87 @@ pub fn refile<K: Keyword, I: ParseId>(
         Some(())
     }
 }
+
+/// Merges the given document fragment into the file at the specified location, in the same way as
+/// [`merge_by_uid`]. The location is given in the same way as for [`refile_to_file`], and any
+/// nodes that aren't already in the file will be refiled there. Everything in the file other than
+/// the nodes merged or added will be written back exactly as it was.
+pub fn merge_to_file<K: Keyword, I: ParseId + Clone>(
+    nodes: DocumentFragment<K, I>,
+    target: String,
+    format: Format,
+) -> Result<(), anyhow::Error> {
+    let mut parts = target.splitn(2, "::");
+    let target_path = parts.next().unwrap();
+    let target_heading = parts.next();
+
+    let target_contents =
+        std::fs::read_to_string(target_path).with_context(|| "failed to read from merge target")?;
+    // Parsed losslessly, so only the nodes that were merged or added will be rewritten
+    let mut target_doc = Document::<K, I>::from_str_lossless(&target_contents, format)
+        .with_context(|| "failed to parse merge target into document")?;
+
+    merge_by_uid(nodes, target_heading, &mut target_doc)
+        .ok_or(anyhow!("merge target not found in document"))?;
+    let updated_doc = target_doc.into_string(format);
+
+    std::fs::write(target_path, updated_doc)
+        .with_context(|| "failed to write target document updated from merge")?;
+    Ok(())
+}
+
+/// Merges the given nodes into the given parsed document by their IDs. Any node with the same ID
+/// as one already in the document (anywhere in it) will replace that node's title, body,
+/// priority, tags, planning, and timestamps, and its keyword, if it has one. The existing node
+/// keeps its place, its children, and its other properties. All other nodes are refiled under the
+/// given target heading, as with [`refile`].
+///
+/// This makes it possible to repeatedly import nodes from elsewhere (e.g. with
+/// [`crate::import_ics`]) without creating duplicates. Like [`refile`], this will return `None` if
+/// the target heading couldn't be found, in which case the existing nodes may still have been
+/// updated.
+pub fn merge_by_uid<K: Keyword, I: ParseId + Clone>(
+    nodes: DocumentFragment<K, I>,
+    target_heading: Option<&str>,
+    target_doc: &mut Document<K, I>,
+) -> Option<()> {
+    fn find_by_id<'n, K: Keyword, I: ParseId + Clone>(
+        node: &'n mut Node<K, I>,
+        id: &str,
+    ) -> Option<&'n mut Node<K, I>> {
+        if node.properties.id.is_some() && node.properties.id.clone().into_string() == id {
+            return Some(node);
+        }
+        node.unchecked_mut_children()
+            .iter_mut()
+            .find_map(|child| find_by_id(child, id))
+    }
+
+    let mut new_nodes = Vec::new();
+    for node in nodes {
+        let existing = if node.properties.id.is_some() {
+            find_by_id(
+                &mut target_doc.root,
+                &node.properties.id.clone().into_string(),
+            )
+        } else {
+            None
+        };
+
+        if let Some(existing) = existing {
+            existing.title = node.title;
+            existing.body = node.body;
+            existing.priority = node.priority;
+            existing.tags = node.tags;
+            existing.planning = node.planning;
+            existing.timestamps = node.timestamps;
+            if node.keyword.is_some() {
+                existing.keyword = node.keyword;
+            }
+        } else {
+            new_nodes.push(node);
+        }
+    }
+
+    refile(new_nodes, target_heading, target_doc)
+}
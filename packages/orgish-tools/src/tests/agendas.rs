@@ -0,0 +1,295 @@
+use super::*;
+use chrono::NaiveTime;
+use orgish::error::TimezoneError;
+use orgish::keyword::KeywordSet;
+use orgish::timestamp::{Repeater, RepeaterKind, RepeaterUnit, TimestampApplies};
+
+const TEXT: &str = r#"#+FILETAGS: :home:
+* TODO Scheduled :work:
+SCHEDULED: <2024-05-06 Mon 09:00>
+** TODO Child
+SCHEDULED: <2024-05-06 Mon>
+* TODO Old
+SCHEDULED: <2024-05-01 Wed>
+* DONE Old done
+SCHEDULED: <2024-05-01 Wed> DEADLINE: <2024-05-02 Thu>
+* DONE Finished
+SCHEDULED: <2024-05-10 Fri>
+* TODO Due soon
+DEADLINE: <2024-05-15 Wed -10d>
+* TODO Due later
+DEADLINE: <2024-06-15 Sat>
+* TODO Overdue deadline
+DEADLINE: <2024-05-03 Fri>
+* TODO Due today
+DEADLINE: <2024-05-07 Tue>
+* Meeting <2024-05-07 Tue 14:00-15:00>
+* Trip <2024-05-11 Sat>--<2024-05-13 Mon>
+* Plain
+Body with <2024-05-08 Wed> and an inactive [2024-05-09 Thu]"#;
+
+fn options() -> AgendaOptions {
+    AgendaOptions {
+        from: date(2024, 5, 6),
+        to: date(2024, 5, 12),
+        today: date(2024, 5, 7),
+        warning_days: 14,
+        timezone: None,
+    }
+}
+
+/// Summarises the given entries as their dates, kinds, and titles.
+fn summarise(entries: &[AgendaEntry<TestKeyword>]) -> Vec<(u32, AgendaEntryKind, &str)> {
+    use chrono::Datelike;
+
+    entries
+        .iter()
+        .map(|entry| (entry.date.day(), entry.kind, entry.title.as_str()))
+        .collect()
+}
+
+#[test]
+fn agenda_should_include_scheduled_deadline_and_plain_entries() {
+    use AgendaEntryKind::*;
+
+    let document = Document::<TestKeyword>::from_str(TEXT, Format::Org).unwrap();
+    let entries = agenda([&document], &options(), None).unwrap();
+    assert_eq!(
+        summarise(&entries),
+        vec![
+            // Timed entries come first
+            (6, Scheduled, "Scheduled"),
+            (6, Scheduled, "Child"),
+            (7, Timestamp, "Meeting"),
+            (7, ScheduledOverdue { days: 1 }, "Scheduled"),
+            (7, ScheduledOverdue { days: 1 }, "Child"),
+            (7, ScheduledOverdue { days: 6 }, "Old"),
+            (7, DeadlineUpcoming { days: 8 }, "Due soon"),
+            (7, DeadlineOverdue { days: 4 }, "Overdue deadline"),
+            (7, Deadline, "Due today"),
+            (8, Timestamp, "Plain"),
+            (10, Scheduled, "Finished"),
+            // Ranges appear on every date they cover in the span
+            (11, Timestamp, "Trip"),
+            (12, Timestamp, "Trip"),
+        ]
+    );
+
+    let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+    assert_eq!(entries[0].applies, TimestampApplies::Start(time(9)));
+    assert_eq!(
+        entries[2].applies,
+        TimestampApplies::Block(time(14), time(15))
+    );
+    assert_eq!(entries[3].applies, TimestampApplies::AllDay);
+
+    // Tags are inherited from ancestors and the document
+    assert_eq!(entries[1].tags, vec!["home", "work"]);
+    assert_eq!(entries[1].path, vec!["Scheduled"]);
+    assert_eq!(entries[1].keyword, Some(TestKeyword("TODO".to_string())));
+    assert_eq!(entries[1].document, 0);
+}
+
+#[test]
+fn agenda_should_only_warn_within_warning_period() {
+    let document = Document::<TestKeyword>::from_str(TEXT, Format::Org).unwrap();
+    let upcoming = |today, warning_days| {
+        let options = AgendaOptions {
+            from: today,
+            today,
+            warning_days,
+            ..options()
+        };
+        agenda([&document], &options, None)
+            .unwrap()
+            .into_iter()
+            .filter_map(|entry| match entry.kind {
+                AgendaEntryKind::DeadlineUpcoming { days } => Some((entry.title, days)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // The deadline's own warning period takes precedence over the default
+    assert_eq!(
+        upcoming(date(2024, 5, 5), 0),
+        vec![("Due soon".to_string(), 10)]
+    );
+    assert!(upcoming(date(2024, 5, 4), 30)
+        .iter()
+        .all(|(title, _)| title != "Due soon"));
+    // Otherwise, the default is used
+    assert_eq!(
+        upcoming(date(2024, 5, 6), 40),
+        vec![
+            ("Due soon".to_string(), 9),
+            ("Due later".to_string(), 40),
+            ("Due today".to_string(), 1)
+        ]
+    );
+}
+
+#[test]
+fn agenda_should_not_mark_done_entries_overdue() {
+    let document = Document::<TestKeyword>::from_str(TEXT, Format::Org).unwrap();
+    let options = AgendaOptions {
+        today: date(2024, 5, 10),
+        ..options()
+    };
+    let entries = agenda([&document], &options, None).unwrap();
+
+    // Done entries still appear on their own dates, but never as overdue
+    assert!(entries
+        .iter()
+        .any(|entry| entry.title == "Finished" && entry.kind == AgendaEntryKind::Scheduled));
+    assert!(entries.iter().all(|entry| entry.title != "Old done"));
+    // Without a done keyword, everything is overdue
+    let keywords = KeywordSet {
+        todo: vec!["TODO".to_string(), "DONE".to_string()],
+        done: Vec::new(),
+    };
+    let entries = agenda([&document], &options, Some(&keywords)).unwrap();
+    assert!(entries.iter().any(|entry| entry.title == "Old done"
+        && entry.kind == AgendaEntryKind::DeadlineOverdue { days: 8 }));
+}
+
+#[test]
+fn agenda_should_use_each_documents_keywords() {
+    let custom = Document::<TestKeyword>::from_str(
+        "#+TODO: TODO | FINISHED\n* FINISHED Custom\nDEADLINE: <2024-05-01 Wed>\n* DONE Not done here\nDEADLINE: <2024-05-01 Wed>",
+        Format::Org,
+    )
+    .unwrap();
+    let default = Document::<TestKeyword>::from_str(
+        "* FINISHED Not done here either\nDEADLINE: <2024-05-01 Wed>",
+        Format::Org,
+    )
+    .unwrap();
+    let titles = |entries: &[AgendaEntry<TestKeyword>]| {
+        entries
+            .iter()
+            .map(|entry| entry.title.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let entries = agenda([&custom, &default], &options(), None).unwrap();
+    assert_eq!(
+        titles(&entries),
+        vec!["Not done here", "Not done here either"]
+    );
+    // An explicit keyword set overrides those of the documents
+    let keywords = KeywordSet::from_str("TODO | DONE FINISHED").unwrap();
+    let entries = agenda([&custom, &default], &options(), Some(&keywords)).unwrap();
+    assert!(entries.is_empty());
+}
+
+#[test]
+fn agenda_should_sort_by_date_then_time_then_document() {
+    let first = Document::<TestKeyword>::from_str(
+        "* First untimed\n<2024-05-06 Mon>\n* First late\n<2024-05-06 Mon 18:00>",
+        Format::Org,
+    )
+    .unwrap();
+    let second = Document::<TestKeyword>::from_str(
+        "* Second untimed\n<2024-05-06 Mon>\n* Second early\n<2024-05-06 Mon 08:00>\n* Second tomorrow\n<2024-05-07 Tue 07:00>",
+        Format::Org,
+    )
+    .unwrap();
+    let entries = agenda([&first, &second], &options(), None).unwrap();
+    let order = entries
+        .iter()
+        .map(|entry| (entry.title.as_str(), entry.document))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        order,
+        vec![
+            ("Second early", 1),
+            ("First late", 0),
+            ("First untimed", 0),
+            ("Second untimed", 1),
+            ("Second tomorrow", 1),
+        ]
+    );
+}
+
+#[test]
+fn agenda_should_find_timestamps_after_stray_brackets() {
+    let document = Document::<TestKeyword>::from_str(
+        "* Call\nSee [the link for details\n<2024-05-08 Wed 10:00-11:00>\n* Compare\n1 < 2, but <2024-05-09 Thu> [and 3 > 2",
+        Format::Org,
+    )
+    .unwrap();
+    let entries = agenda([&document], &options(), None).unwrap();
+    assert_eq!(
+        summarise(&entries),
+        vec![
+            (8, AgendaEntryKind::Timestamp, "Call"),
+            (9, AgendaEntryKind::Timestamp, "Compare"),
+        ]
+    );
+}
+
+#[test]
+fn agenda_should_handle_zero_repeaters() {
+    // These can't be parsed...
+    assert!(Document::<TestKeyword>::from_str(
+        "* TODO Task\nSCHEDULED: <2024-05-01 Wed +0d>",
+        Format::Org
+    )
+    .is_err());
+
+    // ...but they can still be constructed, and they shouldn't repeat
+    let mut document =
+        Document::<TestKeyword>::from_str("* TODO Task\nSCHEDULED: <2024-05-06 Mon>", Format::Org)
+            .unwrap();
+    let node = &mut document.root.unchecked_mut_children()[0];
+    node.planning.scheduled.as_mut().unwrap().repeater = Some(Repeater {
+        count: 0,
+        unit: RepeaterUnit::Day,
+        kind: RepeaterKind::Cumulate,
+        habit_max: None,
+    });
+    let entries = agenda([&document], &options(), None).unwrap();
+    assert_eq!(
+        summarise(&entries),
+        vec![
+            (6, AgendaEntryKind::Scheduled, "Task"),
+            (7, AgendaEntryKind::ScheduledOverdue { days: 1 }, "Task"),
+        ]
+    );
+}
+
+#[test]
+fn agenda_should_convert_timezones_and_reject_unknown_ones() {
+    let options = AgendaOptions {
+        timezone: Some(chrono_tz::Tz::UTC),
+        ..options()
+    };
+    let document = Document::<TestKeyword>::from_str(
+        "#+TIMEZONE: Europe/Berlin\n* Meeting <2024-05-07 Tue 14:00>\n* Call\n:PROPERTIES:\n:TZ: America/New_York\n:END:\n** Child <2024-05-07 Tue 09:00>",
+        Format::Org,
+    )
+    .unwrap();
+    let entries = agenda([&document], &options, None).unwrap();
+    let starts = entries
+        .iter()
+        .map(|entry| entry.timestamp.start.time.unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(starts, vec!["12:00:00", "13:00:00"]);
+
+    let document = Document::<TestKeyword>::from_str(
+        "#+TIMEZONE: Mars/Olympus_Mons\n* Meeting <2024-05-07 Tue 14:00>",
+        Format::Org,
+    )
+    .unwrap();
+    assert!(matches!(
+        agenda([&document], &options, None),
+        Err(TimezoneError::UnknownTimezone { name }) if name == "Mars/Olympus_Mons"
+    ));
+    let document = Document::<TestKeyword>::from_str(
+        "* Call\n:PROPERTIES:\n:TZ: Nowhere\n:END:\n** Child <2024-05-07 Tue 09:00>",
+        Format::Org,
+    )
+    .unwrap();
+    assert!(agenda([&document], &options, None).is_err());
+}
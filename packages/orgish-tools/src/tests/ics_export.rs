@@ -0,0 +1,237 @@
+use super::*;
+
+/// Exports the given Org document, returning the lines of the calendar (still folded).
+fn export(text: &str) -> Vec<String> {
+    let document = Document::<TestKeyword>::from_str(text, Format::Org).unwrap();
+    let now = date(2024, 5, 1).and_hms_opt(8, 30, 0).unwrap();
+    let calendar = export_ics([&document], now, None).unwrap();
+    assert!(calendar.ends_with("\r\n"));
+
+    calendar
+        .strip_suffix("\r\n")
+        .unwrap()
+        .split("\r\n")
+        .map(str::to_string)
+        .collect()
+}
+
+/// Gets the lines of the first component of the given type in the given calendar, without its
+/// `UID` and `DTSTAMP`.
+fn component(lines: &[String], name: &str) -> Vec<String> {
+    let start = lines
+        .iter()
+        .position(|line| *line == format!("BEGIN:{name}"))
+        .unwrap();
+    lines[start..]
+        .iter()
+        .take_while(|line| **line != format!("END:{name}"))
+        .skip(1)
+        .filter(|line| !line.starts_with("UID:") && !line.starts_with("DTSTAMP:"))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn export_should_write_calendar_structure() {
+    let lines = export("* Meeting <2024-05-07 Tue 14:00-15:00>");
+    assert_eq!(
+        lines,
+        vec![
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "PRODID:-//orgish//orgish-tools//EN",
+            "CALSCALE:GREGORIAN",
+            "BEGIN:VEVENT",
+            "UID:16e33be91279d1ae@orgish",
+            "DTSTAMP:20240501T083000Z",
+            "SUMMARY:Meeting",
+            "DTSTART:20240507T140000",
+            "DTEND:20240507T150000",
+            "END:VEVENT",
+            "END:VCALENDAR",
+        ]
+    );
+}
+
+#[test]
+fn export_should_give_stable_uids() {
+    let lines = export("* Task\nSCHEDULED: <2024-05-01 Wed>\n* Parent\n** Task <2024-05-01 Wed>");
+    let uids = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("UID:"))
+        .collect::<Vec<_>>();
+    // These are FNV-1a hashes of the outline paths, so they should never change
+    assert_eq!(
+        uids,
+        vec!["c797ca9076cbbcc4@orgish", "c48fdcff691973ca@orgish"]
+    );
+
+    // IDs are used where they exist, with suffixes for extra components
+    let lines = export(
+        "* Task <2024-05-02 Thu>\nSCHEDULED: <2024-05-01 Wed>\n:PROPERTIES:\n:ID: task-1\n:END:",
+    );
+    let uids = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("UID:"))
+        .collect::<Vec<_>>();
+    assert_eq!(uids, vec!["task-1", "task-1-1"]);
+    // And the extra components point back to the node
+    let ids = lines
+        .iter()
+        .filter_map(|line| line.strip_prefix("X-ORGISH-ID:"))
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["task-1"]);
+}
+
+#[test]
+fn export_should_fold_long_lines() {
+    let title = "Some very long title with some multibyte characters like é and ü ".repeat(3);
+    let lines = export(&format!("* {}<2024-05-07 Tue>", title));
+    let summary_idx = lines
+        .iter()
+        .position(|line| line.starts_with("SUMMARY:"))
+        .unwrap();
+    let folded = lines[summary_idx..]
+        .iter()
+        .take_while(|line| line.starts_with("SUMMARY:") || line.starts_with(' '))
+        .collect::<Vec<_>>();
+    assert_eq!(folded.len(), 3);
+    for line in &folded {
+        assert!(line.len() <= 75);
+    }
+    // Every line but the last should be full (or one short, if a character didn't fit)
+    assert!(folded[..2].iter().all(|line| line.len() >= 74));
+
+    let unfolded = folded
+        .iter()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<String>();
+    assert_eq!(unfolded, format!("SUMMARY:{}", title.trim()));
+}
+
+#[test]
+fn export_should_escape_text() {
+    let lines = export(
+        "* Lunch, then a walk; maybe \\ not <2024-05-07 Tue> :food:\nFirst line\nSecond, with a comma",
+    );
+    assert_eq!(
+        component(&lines, "VEVENT"),
+        vec![
+            "SUMMARY:Lunch\\, then a walk\\; maybe \\\\ not",
+            "DESCRIPTION:First line\\nSecond\\, with a comma",
+            "CATEGORIES:food",
+            "DTSTART;VALUE=DATE:20240507",
+        ]
+    );
+}
+
+#[test]
+fn export_should_make_untimed_range_ends_exclusive() {
+    let lines = export("* Trip <2024-05-01 Wed>--<2024-05-03 Fri>");
+    assert_eq!(
+        component(&lines, "VEVENT"),
+        vec![
+            "SUMMARY:Trip",
+            "DTSTART;VALUE=DATE:20240501",
+            "DTEND;VALUE=DATE:20240504",
+        ]
+    );
+
+    // Timed ends are already exact, and a time on either end makes both date-times
+    let lines = export("* Trip <2024-05-01 Wed>--<2024-05-03 Fri 18:00>");
+    assert_eq!(
+        component(&lines, "VEVENT"),
+        vec![
+            "SUMMARY:Trip",
+            "DTSTART:20240501T180000",
+            "DTEND:20240503T180000",
+        ]
+    );
+}
+
+#[test]
+fn export_should_write_repeaters_as_rules() {
+    let lines = export(
+        "* Standup <2024-05-01 Wed 09:00 +2w>\n* TODO Bins\nSCHEDULED: <2024-05-01 Wed .+1m>\n* Hourly <2024-05-01 Wed 09:00 ++3h>",
+    );
+    let rules = lines
+        .iter()
+        .filter(|line| line.starts_with("RRULE:"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        vec![
+            "RRULE:FREQ=WEEKLY;INTERVAL=2",
+            "RRULE:FREQ=MONTHLY;INTERVAL=1",
+            "RRULE:FREQ=HOURLY;INTERVAL=3",
+        ]
+    );
+}
+
+#[test]
+fn export_should_convert_to_utc_with_timezones() {
+    let lines = export(
+        "#+TIMEZONE: Europe/Berlin\n* Meeting <2024-05-07 Tue 14:00>\n* Call\n:PROPERTIES:\n:TZ: America/New_York\n:END:\n** Child <2024-01-07 Sun 09:00>\n** Untimed <2024-01-07 Sun>",
+    );
+    let starts = lines
+        .iter()
+        .filter(|line| line.starts_with("DTSTART"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        starts,
+        vec![
+            "DTSTART:20240507T120000Z",
+            "DTSTART:20240107T140000Z",
+            "DTSTART;VALUE=DATE:20240107",
+        ]
+    );
+
+    let document = Document::<TestKeyword>::from_str(
+        "* Call\n:PROPERTIES:\n:TZ: Nowhere\n:END:\n** Child <2024-01-07 Sun 09:00>",
+        Format::Org,
+    )
+    .unwrap();
+    let now = date(2024, 5, 1).and_hms_opt(8, 30, 0).unwrap();
+    assert!(matches!(
+        export_ics([&document], now, None),
+        Err(orgish::error::TimezoneError::UnknownTimezone { name }) if name == "Nowhere"
+    ));
+}
+
+#[test]
+fn export_should_write_todos() {
+    let lines = export(
+        "* DONE [#A] Report\nCLOSED: [2024-05-02 Thu 10:00] DEADLINE: <2024-05-03 Fri>\n* TODO Task\nSCHEDULED: <2024-05-01 Wed> DEADLINE: <2024-05-04 Sat 12:00>",
+    );
+    assert_eq!(
+        component(&lines, "VTODO"),
+        vec![
+            "SUMMARY:Report",
+            "PRIORITY:1",
+            "DUE;VALUE=DATE:20240503",
+            "STATUS:COMPLETED",
+            "COMPLETED:20240502T100000",
+        ]
+    );
+
+    // An untimed start takes the time of a timed deadline, so neither loses anything
+    let todos = lines
+        .split(|line| line == "END:VTODO")
+        .nth(1)
+        .unwrap()
+        .iter()
+        .filter(|line| line.starts_with("DTSTART") || line.starts_with("DUE"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        todos,
+        vec!["DTSTART:20240501T120000", "DUE:20240504T120000"]
+    );
+}
+
+#[test]
+fn export_should_use_the_documents_done_keywords() {
+    let lines = export("#+TODO: TODO | FINISHED\n* FINISHED Task\nDEADLINE: <2024-05-03 Fri>");
+    assert!(lines.iter().any(|line| line == "STATUS:COMPLETED"));
+    let lines = export("#+TODO: TODO | FINISHED\n* DONE Task\nDEADLINE: <2024-05-03 Fri>");
+    assert!(lines.iter().any(|line| line == "STATUS:NEEDS-ACTION"));
+}
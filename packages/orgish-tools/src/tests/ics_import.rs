@@ -0,0 +1,298 @@
+use super::*;
+use orgish::StringId;
+
+fn options(timezone: Option<chrono_tz::Tz>) -> IcsImportOptions<TestKeyword> {
+    IcsImportOptions {
+        todo_keyword: TestKeyword("TODO".to_string()),
+        done_keyword: TestKeyword("DONE".to_string()),
+        timezone,
+    }
+}
+
+/// Imports the given components (which will be wrapped in a calendar), writing each node to a
+/// string.
+fn import(components: &str, timezone: Option<chrono_tz::Tz>) -> Vec<String> {
+    let calendar = format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}\r\nEND:VCALENDAR\r\n",
+        components.replace('\n', "\r\n")
+    );
+    import_ics::<_, StringId>(&calendar, &options(timezone))
+        .unwrap()
+        .into_iter()
+        .map(|node| node.into_string(Format::Org))
+        .collect()
+}
+
+#[test]
+fn import_should_unfold_and_unescape() {
+    let nodes = import(
+        "BEGIN:VEVENT\nUID:event-1\nDTSTART;VALUE=DATE:20240501\nSUMMARY:Lunch\\, then a walk\\; maybe \\\\ n\n ot\nDESCRIPTION:First line\\nSecond\\, wi\n\tth a comma\nEND:VEVENT",
+        None,
+    );
+    assert_eq!(
+        nodes,
+        vec![
+            "* Lunch, then a walk; maybe \\ not <2024-05-01 Wed>\n:PROPERTIES:\n:ID: event-1\n:END:\nFirst line\nSecond, with a comma"
+        ]
+    );
+}
+
+#[test]
+fn import_should_read_category_lists() {
+    let nodes = import(
+        "BEGIN:VEVENT\nUID:event-1\nDTSTART;VALUE=DATE:20240501\nSUMMARY:Party\nCATEGORIES:work,big project\nCATEGORIES:garden\\, front,\nEND:VEVENT",
+        None,
+    );
+    assert_eq!(
+        nodes,
+        vec!["* Party <2024-05-01 Wed> :work:big_project:garden,_front:\n:PROPERTIES:\n:ID: event-1\n:END:"]
+    );
+}
+
+#[test]
+fn import_should_handle_durations() {
+    let nodes = import(
+        "BEGIN:VEVENT\nUID:event-1\nDTSTART:20240501T090000\nDURATION:PT1H30M\nSUMMARY:Meeting\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-2\nDTSTART;VALUE=DATE:20240501\nDURATION:P2D\nSUMMARY:Trip\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-3\nDTSTART:20240501T220000\nDURATION:P1DT4H\nSUMMARY:Night shift\nEND:VEVENT",
+        None,
+    );
+    let headings = nodes
+        .iter()
+        .map(|node| node.lines().next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headings,
+        vec![
+            "* Meeting <2024-05-01 Wed 09:00-10:30>",
+            // Untimed durations are exclusive, like `DTEND`
+            "* Trip <2024-05-01 Wed>--<2024-05-02 Thu>",
+            "* Night shift <2024-05-01 Wed 22:00>--<2024-05-03 Fri 02:00>",
+        ]
+    );
+
+    let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event-1\r\nDTSTART:20240501T090000\r\nDURATION:1H\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    assert!(matches!(
+        import_ics::<_, StringId>(calendar, &options(None)),
+        Err(IcsError::InvalidDuration { .. })
+    ));
+}
+
+#[test]
+fn import_should_convert_timezones() {
+    let components = "BEGIN:VEVENT\nUID:event-1\nDTSTART:20240501T120000Z\nSUMMARY:UTC\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-2\nDTSTART;TZID=America/New_York:20240501T080000\nSUMMARY:New York\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-3\nDTSTART;TZID=Custom Zone:20240501T080000\nSUMMARY:Custom\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-4\nDTSTART:20240501T080000\nSUMMARY:Floating\nEND:VEVENT";
+    let headings = |timezone| {
+        import(components, timezone)
+            .iter()
+            .map(|node| node.lines().next().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        headings(Some(chrono_tz::Europe::Berlin)),
+        vec![
+            "* UTC <2024-05-01 Wed 14:00>",
+            "* New York <2024-05-01 Wed 14:00>",
+            // Unknown and floating times are never converted
+            "* Custom <2024-05-01 Wed 08:00>",
+            "* Floating <2024-05-01 Wed 08:00>",
+        ]
+    );
+    // Without a timezone, everything is taken as written
+    assert_eq!(
+        headings(None),
+        vec![
+            "* UTC <2024-05-01 Wed 12:00>",
+            "* New York <2024-05-01 Wed 08:00>",
+            "* Custom <2024-05-01 Wed 08:00>",
+            "* Floating <2024-05-01 Wed 08:00>",
+        ]
+    );
+}
+
+#[test]
+fn import_should_skip_recurrence_overrides() {
+    let nodes = import(
+        "BEGIN:VEVENT\nUID:event-1\nDTSTART:20240501T090000\nRRULE:FREQ=WEEKLY\nSUMMARY:Standup\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-1\nRECURRENCE-ID:20240508T090000\nDTSTART:20240508T100000\nSUMMARY:Late standup\nEND:VEVENT",
+        None,
+    );
+    assert_eq!(
+        nodes,
+        vec!["* Standup <2024-05-01 Wed 09:00 +1w>\n:PROPERTIES:\n:ID: event-1\n:END:"]
+    );
+}
+
+#[test]
+fn import_should_drop_unsupported_rules() {
+    let nodes = import(
+        "BEGIN:VEVENT\nUID:event-1\nDTSTART;VALUE=DATE:20240501\nRRULE:FREQ=DAILY;INTERVAL=2;WKST=MO\nSUMMARY:Simple\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-2\nDTSTART;VALUE=DATE:20240501\nRRULE:FREQ=WEEKLY;BYDAY=MO,WE\nSUMMARY:By day\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-3\nDTSTART;VALUE=DATE:20240501\nRRULE:FREQ=MONTHLY;COUNT=3\nSUMMARY:Counted\nEND:VEVENT",
+        None,
+    );
+    let headings = nodes
+        .iter()
+        .map(|node| node.lines().next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headings,
+        vec![
+            "* Simple <2024-05-01 Wed +2d>",
+            // Only the first occurrence is left
+            "* By day <2024-05-01 Wed>",
+            "* Counted <2024-05-01 Wed>",
+        ]
+    );
+}
+
+#[test]
+fn import_should_read_todos() {
+    let nodes = import(
+        "BEGIN:VTODO\nUID:todo-1\nSUMMARY:Report\nPRIORITY:1\nDUE;VALUE=DATE:20240503\nSTATUS:COMPLETED\nCOMPLETED:20240502T100000\nEND:VTODO\nBEGIN:VTODO\nUID:todo-2\nSUMMARY:Task\nDTSTART:20240501T090000\nDUE:20240504T120000\nSTATUS:NEEDS-ACTION\nBEGIN:VALARM\nACTION:DISPLAY\nDESCRIPTION:Ignored\nEND:VALARM\nEND:VTODO",
+        None,
+    );
+    assert_eq!(
+        nodes,
+        vec![
+            "* DONE [#A] Report\nDEADLINE: <2024-05-03 Fri>\nCLOSED: [2024-05-02 Thu 10:00]\n:PROPERTIES:\n:ID: todo-1\n:END:",
+            "* TODO Task\nDEADLINE: <2024-05-04 Sat 12:00>\nSCHEDULED: <2024-05-01 Wed 09:00>\n:PROPERTIES:\n:ID: todo-2\n:END:",
+        ]
+    );
+}
+
+#[test]
+fn import_should_deduplicate_uids() {
+    let nodes = import(
+        "BEGIN:VEVENT\nUID:event-1\nDTSTART;VALUE=DATE:20240501\nSUMMARY:First\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-2\nDTSTART;VALUE=DATE:20240502\nSUMMARY:Other\nEND:VEVENT\nBEGIN:VEVENT\nUID:event-1\nDTSTART;VALUE=DATE:20240503\nSUMMARY:Second\nEND:VEVENT",
+        None,
+    );
+    let headings = nodes
+        .iter()
+        .map(|node| node.lines().next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headings,
+        vec!["* Second <2024-05-03 Fri>", "* Other <2024-05-02 Thu>"]
+    );
+}
+
+#[test]
+fn import_should_reject_invalid_calendars() {
+    let import = |calendar: &str| import_ics::<_, StringId>(calendar, &options(None));
+    assert!(matches!(
+        import("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART:20240501T090000\r\nEND:VEVENT\r\nEND:VCALENDAR"),
+        Err(IcsError::MissingUid { .. })
+    ));
+    assert!(matches!(
+        import("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event-1\r\nEND:VEVENT\r\nEND:VCALENDAR"),
+        Err(IcsError::MissingStart { .. })
+    ));
+    assert!(matches!(
+        import("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event-1\r\nDTSTART:tomorrow\r\nEND:VEVENT\r\nEND:VCALENDAR"),
+        Err(IcsError::InvalidDateTime { .. })
+    ));
+    assert!(matches!(
+        import("BEGIN:VCALENDAR\r\nEND:VEVENT"),
+        Err(IcsError::UnexpectedEnd { .. })
+    ));
+    assert!(matches!(
+        import("BEGIN:VCALENDAR\r\nBEGIN:VEVENT"),
+        Err(IcsError::UnterminatedComponent { .. })
+    ));
+    assert!(matches!(
+        import("BEGIN:VCALENDAR\r\nnot a content line\r\nEND:VCALENDAR"),
+        Err(IcsError::InvalidLine { .. })
+    ));
+}
+
+#[test]
+fn export_then_import_should_round_trip() {
+    let text = r#"#+TIMEZONE: Europe/Berlin
+* Meeting <2024-05-07 Tue 14:00-15:00> :work:
+:PROPERTIES:
+:ID: meeting
+:END:
+Agenda, notes; and more
+* Trip <2024-05-10 Fri>--<2024-05-12 Sun>
+* TODO [#B] Report
+SCHEDULED: <2024-05-01 Wed +1w> DEADLINE: <2024-05-03 Fri>"#;
+    let document = Document::<TestKeyword, StringId>::from_str(text, Format::Org).unwrap();
+    let now = date(2024, 5, 1).and_hms_opt(8, 30, 0).unwrap();
+    let calendar = export_ics([&document], now, None).unwrap();
+
+    let nodes = import_ics::<_, StringId>(&calendar, &options(Some(chrono_tz::Europe::Berlin)))
+        .unwrap()
+        .into_iter()
+        .map(|node| node.into_string(Format::Org))
+        .collect::<Vec<_>>();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(
+        nodes[0],
+        "* Meeting <2024-05-07 Tue 14:00-15:00> :work:\n:PROPERTIES:\n:ID: meeting\n:END:\nAgenda, notes; and more"
+    );
+    // Nodes without IDs get their generated UIDs, and repeaters apply to the whole todo
+    assert_eq!(
+        nodes[1],
+        "* Trip <2024-05-10 Fri>--<2024-05-12 Sun>\n:PROPERTIES:\n:ID: e39e090d5e14f89e@orgish\n:END:"
+    );
+    assert_eq!(
+        nodes[2],
+        "* TODO [#B] Report\nDEADLINE: <2024-05-03 Fri +1w>\nSCHEDULED: <2024-05-01 Wed +1w>\n:PROPERTIES:\n:ID: 884c20ca269a1751@orgish\n:END:"
+    );
+}
+
+#[test]
+fn export_then_import_should_keep_nodes_with_several_components_together() {
+    let text = "* TODO Conference <2024-05-07 Tue>--<2024-05-08 Wed> <2024-05-14 Tue 10:00>\nDEADLINE: <2024-05-03 Fri>\n:PROPERTIES:\n:ID: conference\n:END:\n* Talk <2024-05-09 Thu> <2024-05-10 Fri>";
+    let document = Document::<TestKeyword, StringId>::from_str(text, Format::Org).unwrap();
+    let now = date(2024, 5, 1).and_hms_opt(8, 30, 0).unwrap();
+    let calendar = export_ics([&document], now, None).unwrap();
+
+    let nodes = import_ics::<_, StringId>(&calendar, &options(None))
+        .unwrap()
+        .into_iter()
+        .map(|node| node.into_string(Format::Org))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        nodes,
+        vec![
+            "* TODO Conference <2024-05-07 Tue>--<2024-05-08 Wed> <2024-05-14 Tue 10:00>\nDEADLINE: <2024-05-03 Fri>\n:PROPERTIES:\n:ID: conference\n:END:",
+            "* Talk <2024-05-09 Thu> <2024-05-10 Fri>\n:PROPERTIES:\n:ID: 6a094d90424b263d@orgish\n:END:",
+        ]
+    );
+}
+
+#[test]
+fn merging_twice_should_update_rather_than_duplicate() {
+    let calendar = |summary: &str| {
+        format!("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event-1\r\nDTSTART;VALUE=DATE:20240501\r\nSUMMARY:{summary}\r\nEND:VEVENT\r\nBEGIN:VTODO\r\nUID:todo-1\r\nSUMMARY:Task\r\nSTATUS:NEEDS-ACTION\r\nDUE;VALUE=DATE:20240502\r\nEND:VTODO\r\nEND:VCALENDAR\r\n")
+    };
+    let mut document =
+        Document::<TestKeyword, StringId>::from_str("* Calendar\n* Other", Format::Org).unwrap();
+
+    let nodes = import_ics(&calendar("Party"), &options(None)).unwrap();
+    merge_by_uid(nodes, Some("Calendar"), &mut document).unwrap();
+    // The second time around, the event has changed, and the todo has been done locally (but the
+    // calendar's keyword still takes precedence)
+    document.root.unchecked_mut_children()[0].unchecked_mut_children()[1].keyword =
+        Some(TestKeyword("DONE".to_string()));
+    let nodes = import_ics(&calendar("Big party"), &options(None)).unwrap();
+    merge_by_uid(nodes, Some("Calendar"), &mut document).unwrap();
+
+    assert_eq!(
+        document.into_string(Format::Org),
+        "* Calendar\n** Big party <2024-05-01 Wed>\n:PROPERTIES:\n:ID: event-1\n:END:\n** TODO Task\nDEADLINE: <2024-05-02 Thu>\n:PROPERTIES:\n:ID: todo-1\n:END:\n* Other"
+    );
+}
+
+#[test]
+fn merging_should_keep_the_rest_of_a_lossless_document() {
+    let text = "#+title:   Plans\n* Calendar\n** Old party   <2024-05-01 Wed>\n:PROPERTIES:\n:ID:      event-1\n:END:\n* Other    :tag:\nSCHEDULED: <2024-05-03 Fri>\n  Some   spaced body\n";
+    let calendar = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:event-1\r\nDTSTART;VALUE=DATE:20240501\r\nSUMMARY:Party\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nUID:event-2\r\nDTSTART;VALUE=DATE:20240502\r\nSUMMARY:New\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    let mut document =
+        Document::<TestKeyword, StringId>::from_str_lossless(text, Format::Org).unwrap();
+
+    let nodes = import_ics(calendar, &options(None)).unwrap();
+    merge_by_uid(nodes, Some("Calendar"), &mut document).unwrap();
+
+    assert_eq!(
+        document.into_string(Format::Org),
+        "#+title:   Plans\n* Calendar\n** Party <2024-05-01 Wed>\n:PROPERTIES:\n:ID:      event-1\n:END:\n** New <2024-05-02 Thu>\n:PROPERTIES:\n:ID: event-2\n:END:\n* Other    :tag:\nSCHEDULED: <2024-05-03 Fri>\n  Some   spaced body\n"
+    );
+}
@@ -0,0 +1,236 @@
+use super::*;
+use chrono::NaiveDateTime;
+
+const TEXT: &str = r#"#+TODO: TODO NEXT | DONE
+#+TAGS: [ work : meeting call ] home
+#+PRIORITIES: A E C
+#+FILETAGS: :notes:
+* TODO [#A] Plan :work:
+DEADLINE: <2024-05-03 Fri>
+:PROPERTIES:
+:EFFORT: 2
+:OWNER: Alice Smith
+:END:
+** NEXT Call Bob :call:
+SCHEDULED: <2024-05-02 Thu>
+** DONE [#D] Standup :meeting:
+CLOSED: [2024-04-30 Tue 10:00]
+* TODO Groceries :home:
+DEADLINE: <2024-05-20 Mon>
+:PROPERTIES:
+:EFFORT: 0.5
+:END:
+* Phone bank :call:
+* Notes"#;
+
+fn now() -> NaiveDateTime {
+    date(2024, 5, 1).and_hms_opt(8, 0, 0).unwrap()
+}
+
+/// Runs the given query over the test document, returning the titles of the matching nodes.
+fn titles(text: &str) -> Vec<String> {
+    let document = Document::<TestKeyword>::from_str(TEXT, Format::Org).unwrap();
+    let match_query = MatchQuery::parse(text).unwrap();
+    query([&document], &match_query, now())
+        .into_iter()
+        .map(|m| m.node.title.clone())
+        .collect()
+}
+
+#[test]
+fn match_queries_should_accept_full_timestamps() {
+    assert_eq!(titles(r#"DEADLINE<"<2024-05-10 Fri>""#), ["Plan"]);
+    assert_eq!(titles(r#"SCHEDULED="<2024-05-02 Thu>""#), ["Call Bob"]);
+    // User input is still accepted as well
+    assert_eq!(titles(r#"DEADLINE>"<2024-05-10>""#), ["Groceries"]);
+}
+
+/// Parses the given query, expecting it to fail.
+fn error(text: &str) -> QueryError {
+    MatchQuery::parse(text).unwrap_err()
+}
+
+#[test]
+fn match_queries_should_parse_tag_syntax() {
+    // `+` is the default, and `&` is optional
+    let parsed = MatchQuery::parse("+work-meeting").unwrap();
+    assert_eq!(parsed, MatchQuery::parse("work&-meeting").unwrap());
+    assert_eq!(parsed, MatchQuery::parse("+work&-meeting").unwrap());
+    assert_ne!(parsed, MatchQuery::parse("work|-meeting").unwrap());
+    assert_eq!(titles("+work-meeting"), ["Plan", "Call Bob", "Phone bank"]);
+
+    assert_eq!(titles("meeting|home"), ["Standup", "Groceries"]);
+    assert_eq!(titles("work&home"), Vec::<String>::new());
+    assert_eq!(titles("-work"), ["Groceries", "Notes"]);
+}
+
+#[test]
+fn match_queries_should_parse_keyword_syntax() {
+    assert_eq!(titles("/TODO|NEXT"), ["Plan", "Call Bob", "Groceries"]);
+    // Nodes without keywords don't have `DONE`
+    assert_eq!(
+        titles("/-DONE"),
+        ["Plan", "Call Bob", "Groceries", "Phone bank", "Notes"]
+    );
+    assert_eq!(titles("work/TODO"), ["Plan"]);
+
+    // `/!` can be used on its own, or with more keywords
+    assert_eq!(titles("/!"), ["Plan", "Call Bob", "Groceries"]);
+    assert_eq!(titles("work/!"), ["Plan", "Call Bob"]);
+    assert_eq!(titles("/!NEXT"), ["Call Bob"]);
+}
+
+#[test]
+fn match_queries_should_parse_operators() {
+    assert_eq!(
+        MatchQuery::parse(r#"TODO<>"TODO""#).unwrap(),
+        MatchQuery::parse(r#"TODO!="TODO""#).unwrap()
+    );
+    assert_eq!(
+        MatchQuery::parse(r#"TODO="TODO""#).unwrap(),
+        MatchQuery::parse(r#"TODO=="TODO""#).unwrap()
+    );
+    // Missing properties are empty, so they aren't equal to anything else
+    assert_eq!(
+        titles(r#"TODO<>"TODO""#),
+        ["Call Bob", "Standup", "Phone bank", "Notes"]
+    );
+    assert_eq!(titles("EFFORT>=2"), ["Plan"]);
+    assert_eq!(titles("EFFORT<=2"), ["Plan", "Groceries"]);
+}
+
+#[test]
+fn match_queries_should_compare_quoted_and_unquoted_values() {
+    // Unquoted values are numbers, which properties that aren't numbers never match
+    assert_eq!(titles("EFFORT<1"), ["Groceries"]);
+    assert_eq!(titles("EFFORT<>1"), ["Plan", "Groceries"]);
+    assert_eq!(titles("LEVEL=2"), ["Call Bob", "Standup"]);
+    assert_eq!(titles("EFFORT>-1"), ["Plan", "Groceries"]);
+    assert!(matches!(
+        error("TODO=NEXT"),
+        QueryError::UnexpectedChar {
+            found: 'N',
+            position: 5
+        }
+    ));
+
+    // Quoted values are strings, so `"0.5"` and missing properties (which are `""`) are less
+    // than `"1"`
+    assert_eq!(titles(r#"EFFORT="2""#), ["Plan"]);
+    assert_eq!(
+        titles(r#"EFFORT<"1""#),
+        ["Call Bob", "Standup", "Groceries", "Phone bank", "Notes"]
+    );
+    assert_eq!(titles(r#"owner="Alice Smith""#), ["Plan"]);
+    assert_eq!(titles(r#"OWNER="Alice \"Smith\"""#), Vec::<String>::new());
+}
+
+#[test]
+fn match_queries_should_report_error_positions() {
+    assert!(matches!(error(""), QueryError::Empty));
+    assert!(matches!(error("|"), QueryError::Empty));
+    assert!(matches!(
+        error("work&{meet}"),
+        QueryError::RegexUnsupported { position: 5 }
+    ));
+    assert!(matches!(
+        error("ITEM={Plan}"),
+        QueryError::RegexUnsupported { position: 5 }
+    ));
+    assert!(matches!(
+        error("work home"),
+        QueryError::UnexpectedChar {
+            found: ' ',
+            position: 4
+        }
+    ));
+    assert!(matches!(
+        error("TODO!\"NEXT\""),
+        QueryError::UnexpectedChar {
+            found: '"',
+            position: 5
+        }
+    ));
+    // Positions are in bytes
+    assert!(matches!(
+        error("café)"),
+        QueryError::UnexpectedChar {
+            found: ')',
+            position: 5
+        }
+    ));
+    assert!(matches!(
+        error("work/TODO/DONE"),
+        QueryError::UnexpectedChar {
+            found: '/',
+            position: 9
+        }
+    ));
+
+    assert!(matches!(
+        error("EFFORT="),
+        QueryError::UnexpectedEnd {
+            expected: "a value"
+        }
+    ));
+    assert!(matches!(
+        error("OWNER=\"Alice"),
+        QueryError::UnexpectedEnd {
+            expected: "a closing '\"'"
+        }
+    ));
+    assert!(matches!(
+        error("/TODO=\"x\""),
+        QueryError::KeywordComparison { keyword } if keyword == "TODO"
+    ));
+    assert!(matches!(
+        error(r#"DEADLINE<"<sometime>""#),
+        QueryError::InvalidTimestamp { value, .. } if value == "<sometime>"
+    ));
+}
+
+#[test]
+fn match_queries_should_use_effective_tags() {
+    // `notes` is inherited from the document, and `work` from `Plan` or the `call` group
+    assert_eq!(titles("notes").len(), 6);
+    assert_eq!(
+        titles("work"),
+        ["Plan", "Call Bob", "Standup", "Phone bank"]
+    );
+    assert_eq!(
+        titles("work-TAGS=\":work:\""),
+        ["Call Bob", "Standup", "Phone bank"]
+    );
+
+    assert_eq!(titles(r#"TAGS=":call:""#), ["Call Bob", "Phone bank"]);
+    assert_eq!(titles(r#"ALLTAGS=":notes:call:work:""#), ["Phone bank"]);
+    assert_eq!(titles(r#"ALLTAGS=":notes:""#), ["Notes"]);
+}
+
+#[test]
+fn match_queries_should_use_special_properties() {
+    // `D` is in the range of the document's priorities, and `C` is the default
+    assert_eq!(titles(r#"PRIORITY="D""#), ["Standup"]);
+    assert_eq!(
+        titles(r#"PRIORITY="C""#),
+        ["Call Bob", "Groceries", "Phone bank", "Notes"]
+    );
+    assert_eq!(titles(r#"PRIORITY<"C""#), ["Plan"]);
+
+    assert_eq!(titles("LEVEL>1"), ["Call Bob", "Standup"]);
+    assert_eq!(titles(r#"TODO="NEXT""#), ["Call Bob"]);
+    assert_eq!(titles(r#"ITEM="Notes""#), ["Notes"]);
+}
+
+#[test]
+fn match_queries_should_compare_relative_times() {
+    // Nodes without the timestamp never match
+    assert_eq!(titles(r#"DEADLINE<"<+1w>""#), ["Plan"]);
+    assert_eq!(titles(r#"DEADLINE>="<today>""#), ["Plan", "Groceries"]);
+    assert_eq!(titles(r#"SCHEDULED>"<now>""#), ["Call Bob"]);
+    assert_eq!(titles(r#"CLOSED<"<now>""#), ["Standup"]);
+    assert_eq!(
+        titles(r#"DEADLINE<"<+1w>"&TODO="TODO"-work"#),
+        Vec::<String>::new()
+    );
+}
@@ -0,0 +1,32 @@
+mod agendas;
+mod ics_export;
+mod ics_import;
+mod match_queries;
+mod queries;
+
+pub use super::*;
+pub use orgish::{Document, Format, Keyword};
+
+/// A keyword type that treats any uppercase word as a keyword, like the one the command line uses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestKeyword(pub String);
+impl Keyword for TestKeyword {
+    fn from_str(keyword: &str) -> Option<Self> {
+        if keyword.chars().all(|c| c.is_uppercase()) {
+            Some(Self(keyword.to_string()))
+        } else {
+            None
+        }
+    }
+    fn into_string(self) -> String {
+        self.0
+    }
+    fn other(keyword: String) -> Self {
+        Self(keyword)
+    }
+}
+
+/// Shorthand for constructing a date that's known to be valid.
+pub fn date(year: i32, month: u32, day: u32) -> chrono::NaiveDate {
+    chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
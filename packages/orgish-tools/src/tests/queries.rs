@@ -0,0 +1,214 @@
+use super::*;
+use chrono::NaiveDateTime;
+use orgish::timestamp::{Repeater, RepeaterKind, RepeaterUnit};
+
+const TEXT: &str = r#"* TODO Write report :work:
+SCHEDULED: <2024-05-02 Thu>
+:PROPERTIES:
+:ID: report
+:END:
+** TODO Draft
+DEADLINE: <2024-05-03 Fri>
+** DONE Outline
+*** TODO Sources
+* Archive
+:PROPERTIES:
+:ARCHIVE: t
+:END:
+** TODO Old task :work:
+SCHEDULED: <2024-04-01 Mon +1w>
+* Inbox
+DEADLINE: <2024-06-01 Sat>"#;
+
+fn now() -> NaiveDateTime {
+    date(2024, 5, 1).and_hms_opt(8, 0, 0).unwrap()
+}
+
+fn document() -> Document<TestKeyword> {
+    Document::from_str(TEXT, Format::Org).unwrap()
+}
+
+/// Runs the given query over the given document, returning the titles of the matching nodes.
+fn titles(document: &Document<TestKeyword>, query: &Query) -> Vec<String> {
+    query
+        .find(document, now())
+        .into_iter()
+        .map(|m| m.node.title.clone())
+        .collect()
+}
+
+#[test]
+fn queries_should_match_terms() {
+    let document = document();
+    assert_eq!(
+        titles(&document, &Query::keyword("TODO")),
+        ["Write report", "Draft", "Sources", "Old task"]
+    );
+    assert_eq!(titles(&document, &Query::keyword("DONE")), ["Outline"]);
+    assert_eq!(titles(&document, &Query::not_done()).len(), 4);
+    // Tags are inherited
+    assert_eq!(
+        titles(&document, &Query::has_tag("work")),
+        ["Write report", "Draft", "Outline", "Sources", "Old task"]
+    );
+    assert_eq!(
+        titles(&document, &Query::property_eq("archive", "t")),
+        ["Archive"]
+    );
+    assert_eq!(
+        titles(&document, &Query::property_eq("ID", "report")),
+        ["Write report"]
+    );
+}
+
+#[test]
+fn queries_should_match_planning_within_spans() {
+    let document = document();
+    // The repeater puts `Old task` on the 29th and the 6th
+    assert_eq!(
+        titles(
+            &document,
+            &Query::scheduled_within(date(2024, 5, 1), date(2024, 5, 7))
+        ),
+        ["Write report", "Old task"]
+    );
+    assert_eq!(
+        titles(
+            &document,
+            &Query::scheduled_within(date(2024, 4, 2), date(2024, 4, 7))
+        ),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        titles(
+            &document,
+            &Query::deadline_within(date(2024, 5, 3), date(2024, 6, 1))
+        ),
+        ["Draft", "Inbox"]
+    );
+    assert_eq!(
+        titles(
+            &document,
+            &Query::deadline_within(date(2024, 5, 4), date(2024, 5, 31))
+        ),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn queries_should_not_hang_on_zero_repeaters() {
+    // These can't be parsed, but they can be constructed by hand, in which case they occur once
+    let mut document = document();
+    let scheduled = document.root.unchecked_mut_children()[0]
+        .planning
+        .scheduled
+        .as_mut()
+        .unwrap();
+    scheduled.repeater = Some(Repeater {
+        count: 0,
+        unit: RepeaterUnit::Day,
+        kind: RepeaterKind::Cumulate,
+        habit_max: None,
+    });
+
+    assert_eq!(
+        titles(
+            &document,
+            &Query::scheduled_within(date(2024, 5, 1), date(2024, 5, 31))
+        ),
+        ["Write report", "Old task"]
+    );
+    assert_eq!(
+        titles(
+            &document,
+            &Query::scheduled_within(date(2024, 5, 3), date(2024, 5, 5))
+        ),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn queries_should_combine() {
+    let document = document();
+    let active_work = Query::keyword("TODO")
+        .and(Query::has_tag("work"))
+        .and(!Query::descendant_of(Query::property_eq("ARCHIVE", "t")));
+    assert_eq!(
+        titles(&document, &active_work),
+        ["Write report", "Draft", "Sources"]
+    );
+    assert_eq!(
+        titles(
+            &document,
+            &Query::keyword("DONE").or(Query::property_eq("ARCHIVE", "t"))
+        ),
+        ["Outline", "Archive"]
+    );
+    assert_eq!(
+        titles(&document, &!Query::has_tag("work")),
+        ["Archive", "Inbox"]
+    );
+    assert_eq!(!!Query::has_tag("work"), Query::has_tag("work"));
+
+    assert_eq!(titles(&document, &Query::all([])).len(), 7);
+    assert_eq!(titles(&document, &Query::any([])), Vec::<String>::new());
+}
+
+#[test]
+fn queries_should_match_descendants() {
+    let document = document();
+    // Only strict descendants match
+    assert_eq!(
+        titles(&document, &Query::descendant_of(Query::has_tag("work"))),
+        ["Draft", "Outline", "Sources"]
+    );
+    assert_eq!(
+        titles(&document, &Query::descendant_of(Query::keyword("DONE"))),
+        ["Sources"]
+    );
+    // Ancestors are matched with their own ancestors
+    assert_eq!(
+        titles(
+            &document,
+            &Query::descendant_of(Query::descendant_of(Query::keyword("TODO")))
+        ),
+        ["Sources"]
+    );
+}
+
+#[test]
+fn queries_should_combine_with_match_queries() {
+    let document = document();
+    let parsed = Query::parse("work/TODO").unwrap();
+    assert_eq!(parsed, Query::from(MatchQuery::parse("work/TODO").unwrap()));
+    assert_eq!(
+        titles(
+            &document,
+            &parsed.and(!Query::descendant_of(Query::property_eq("ARCHIVE", "t")))
+        ),
+        ["Write report", "Draft", "Sources"]
+    );
+}
+
+#[test]
+fn query_matches_should_lead_back_to_nodes() {
+    let document = document();
+    let matches = Query::keyword("TODO").find(&document, now());
+    let indices = matches
+        .iter()
+        .map(|m| m.indices.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        indices,
+        vec![vec![0], vec![0, 0], vec![0, 1, 0], vec![1, 0]]
+    );
+    assert_eq!(matches[2].path, ["Write report", "Outline"]);
+
+    for m in &matches {
+        let mut node = &document.root;
+        for idx in &m.indices {
+            node = &node.children()[*idx];
+        }
+        assert!(std::ptr::eq(node, m.node));
+    }
+}
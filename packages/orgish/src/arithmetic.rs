@@ -0,0 +1,87 @@
+//! Logic for moving timestamps through time and measuring how long they last.
+
+use crate::timestamp::DateTime;
+use crate::Timestamp;
+use chrono::{Duration, Months, NaiveTime};
+
+impl DateTime {
+    /// Moves this date and time by the given duration. Untimed dates are treated as being at
+    /// midnight, and so will move to whichever date that midnight moves to.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the result is out of the range of dates supported by `chrono`.
+    fn shift(&mut self, duration: Duration) {
+        let shifted = self.date.and_time(self.time.unwrap_or(NaiveTime::MIN)) + duration;
+        self.date = shifted.date();
+        if self.time.is_some() {
+            self.time = Some(shifted.time());
+        }
+    }
+    /// Moves this date by the given number of months (which may be negative), keeping the time.
+    /// If the day of the month doesn't exist in the new month, the last day of that month is used.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the result is out of the range of dates supported by `chrono`.
+    fn shift_months(&mut self, months: i32) {
+        let shifted = if months >= 0 {
+            self.date.checked_add_months(Months::new(months as u32))
+        } else {
+            self.date
+                .checked_sub_months(Months::new(months.unsigned_abs()))
+        };
+        self.date = shifted.expect("date out of range");
+    }
+}
+
+impl Timestamp {
+    /// Moves this timestamp by the given duration, moving its start and end (if it has one) by the
+    /// same amount. The repeater and delay are kept. Untimed dates are treated as being at
+    /// midnight, so, for example, moving an untimed timestamp back by an hour will move it to the
+    /// previous day.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the result is out of the range of dates supported by `chrono`.
+    pub fn shift(&mut self, duration: Duration) {
+        self.start.shift(duration);
+        if let Some(end) = &mut self.end {
+            end.shift(duration);
+        }
+    }
+    /// Moves this timestamp by the given number of days (which may be negative). See
+    /// [`Self::shift`] for the details.
+    pub fn shift_days(&mut self, days: i64) {
+        self.shift(Duration::try_days(days).expect("day count out of range"));
+    }
+    /// Moves this timestamp by the given number of months (which may be negative), keeping its
+    /// times, repeater, and delay. The start and end are moved separately, and, if either's day
+    /// of the month doesn't exist in its new month, the last day of that month will be used.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the result is out of the range of dates supported by `chrono`.
+    pub fn shift_months(&mut self, months: i32) {
+        self.start.shift_months(months);
+        if let Some(end) = &mut self.end {
+            end.shift_months(months);
+        }
+    }
+    /// Gets how long this timestamp lasts, if it has an end. An untimed start is taken as the
+    /// start of its day, and an untimed end as the end of its day, so `<2024-05-01>--<2024-05-03>`
+    /// lasts three days.
+    pub fn duration(&self) -> Option<Duration> {
+        let end = self.end.as_ref()?;
+        let start = self
+            .start
+            .date
+            .and_time(self.start.time.unwrap_or(NaiveTime::MIN));
+        let end = match end.time {
+            Some(time) => end.date.and_time(time),
+            None => end.date.and_time(NaiveTime::MIN) + Duration::try_days(1).unwrap(),
+        };
+
+        Some(end - start)
+    }
+}
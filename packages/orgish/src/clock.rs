@@ -0,0 +1,98 @@
+//! Logic for clocking time against nodes, which is recorded in their logbooks.
+
+use crate::drawer::{Clock, LogEntry};
+use crate::error::ClockError;
+use crate::{Keyword, Node, ParseId, ParseString};
+use chrono::{Duration, NaiveDateTime, Timelike};
+
+impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
+    /// Starts a clock on this node at the given time, adding it to the start of the node's
+    /// logbook (which will be created if necessary). Clocks only have minute precision, so any
+    /// seconds will be discarded.
+    ///
+    /// This will fail if the node already has a clock running.
+    pub fn clock_in(&mut self, now: NaiveDateTime) -> Result<(), ClockError> {
+        if let Some(clock) = self.open_clock() {
+            return Err(ClockError::AlreadyClockedIn { start: clock.start });
+        }
+
+        self.drawers.logbook_mut().insert(
+            0,
+            LogEntry::Clock(Clock {
+                start: truncate_to_minute(now),
+                end: None,
+            }),
+        );
+        Ok(())
+    }
+    /// Stops the clock running on this node at the given time, returning how long it ran for.
+    /// Like [`Self::clock_in`], any seconds will be discarded.
+    ///
+    /// This will fail if there's no clock running, or if it was started after the given time.
+    pub fn clock_out(&mut self, now: NaiveDateTime) -> Result<Duration, ClockError> {
+        let now = truncate_to_minute(now);
+        if self.open_clock().is_none() {
+            return Err(ClockError::NotClockedIn);
+        }
+        let clock = self
+            .drawers
+            .logbook_mut()
+            .iter_mut()
+            .find_map(|entry| match entry {
+                LogEntry::Clock(clock) if clock.end.is_none() => Some(clock),
+                _ => None,
+            })
+            // We just checked there's an open clock
+            .unwrap();
+        if now < clock.start {
+            return Err(ClockError::EndBeforeStart {
+                start: clock.start,
+                end: now,
+            });
+        }
+
+        clock.end = Some(now);
+        Ok(now - clock.start)
+    }
+    /// Gets the clock currently running on this node, if there is one.
+    pub fn open_clock(&self) -> Option<&Clock> {
+        self.clocks().find(|clock| clock.end.is_none())
+    }
+    /// Gets all the clocks recorded directly on this node (not any of its children).
+    pub fn clocks(&self) -> impl Iterator<Item = &Clock> {
+        self.drawers
+            .logbook()
+            .into_iter()
+            .flat_map(|logbook| logbook.iter())
+            .filter_map(|entry| match entry {
+                LogEntry::Clock(clock) => Some(clock),
+                _ => None,
+            })
+    }
+    /// Sums the time clocked on this node and all its descendants between the given times (where
+    /// `from` is inclusive and `to` is exclusive). Clocks that only partially overlap this range
+    /// will only have the overlapping part counted, and clocks that are still running won't be
+    /// counted at all.
+    pub fn clocked_time(&self, from: NaiveDateTime, to: NaiveDateTime) -> Duration {
+        let own_time = self
+            .clocks()
+            .filter_map(|clock| {
+                let start = clock.start.max(from);
+                let end = clock.end?.min(to);
+                (end > start).then(|| end - start)
+            })
+            .fold(Duration::zero(), |acc, duration| acc + duration);
+
+        self.children
+            .iter()
+            .fold(own_time, |acc, child| acc + child.clocked_time(from, to))
+    }
+}
+
+/// Removes the seconds (and anything smaller) from the given time.
+fn truncate_to_minute(time: NaiveDateTime) -> NaiveDateTime {
+    time.with_second(0)
+        .and_then(|time| time.with_nanosecond(0))
+        // Zero is always a valid second and nanosecond
+        .unwrap()
+}
@@ -0,0 +1,416 @@
+//! Named drawers other than the property drawer (e.g. `:LOGBOOK:`), which come directly after the
+//! properties of a node. The `LOGBOOK` drawer is parsed into typed entries, while all others are
+//! kept as text.
+//!
+//! In Markdown, drawers are written as HTML comments, with the name of the drawer directly after
+//! the comment opener (e.g. `<!--LOGBOOK`), just like the property drawer.
+
+use crate::{
+    error::{ClockError, ParseError},
+    Format, ParseString, Timestamp,
+};
+use chrono::{Duration, NaiveDateTime};
+
+/// The drawers of a node, in the order they appear in.
+#[derive(Debug, Clone)]
+pub struct Drawers<S: ParseString> {
+    inner: Vec<Drawer<S>>,
+}
+impl<S: ParseString> Default for Drawers<S> {
+    fn default() -> Self {
+        Self { inner: Vec::new() }
+    }
+}
+impl<S: ParseString> Drawers<S> {
+    /// Gets the drawer with the given name, if there is one. Drawer names are case-insensitive.
+    pub fn get(&self, name: &str) -> Option<&Drawer<S>> {
+        self.inner
+            .iter()
+            .find(|drawer| drawer.name.eq_ignore_ascii_case(name))
+    }
+    /// Gets the logbook of this node, if there is one.
+    pub fn logbook(&self) -> Option<&Logbook> {
+        self.inner.iter().find_map(|drawer| match &drawer.contents {
+            DrawerContents::Logbook(logbook) => Some(logbook),
+            DrawerContents::Text(_) => None,
+        })
+    }
+    /// Gets a mutable reference to the logbook of this node, creating an empty one before any
+    /// other drawers if there isn't one already (as Org mode does).
+    pub fn logbook_mut(&mut self) -> &mut Logbook {
+        let idx = match self
+            .inner
+            .iter()
+            .position(|drawer| matches!(drawer.contents, DrawerContents::Logbook(_)))
+        {
+            Some(idx) => idx,
+            None => {
+                self.inner.insert(
+                    0,
+                    Drawer {
+                        name: "LOGBOOK".to_string(),
+                        contents: DrawerContents::Logbook(Logbook::default()),
+                    },
+                );
+                0
+            }
+        };
+
+        match &mut self.inner[idx].contents {
+            DrawerContents::Logbook(logbook) => logbook,
+            // We just found or created it
+            DrawerContents::Text(_) => unreachable!(),
+        }
+    }
+    /// Adds a drawer with the given name and lines of contents to these drawers. This is the
+    /// general drawer parsing logic.
+    pub(crate) fn add_drawer(
+        &mut self,
+        name: &str,
+        lines: &[&str],
+        format: Format,
+    ) -> Result<(), ParseError> {
+        let contents = if name.eq_ignore_ascii_case("LOGBOOK") {
+            DrawerContents::Logbook(Logbook::from_lines(lines))
+        } else {
+            DrawerContents::Text(S::from_str(lines.join("\n"), format).map_err(|source| {
+                ParseError::ParseStringFailed {
+                    source: Box::new(source),
+                }
+            })?)
+        };
+        self.inner.push(Drawer {
+            name: name.to_string(),
+            contents,
+        });
+
+        Ok(())
+    }
+    /// Converts these drawers into their string representation in the given format.
+    pub fn into_string(self, format: Format) -> String {
+        self.inner
+            .into_iter()
+            .map(|drawer| drawer.into_string(format))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+// Drawers are most easily manipulated as a list
+impl<S: ParseString> std::ops::Deref for Drawers<S> {
+    type Target = Vec<Drawer<S>>;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+impl<S: ParseString> std::ops::DerefMut for Drawers<S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+/// A single named drawer.
+#[derive(Debug, Clone)]
+pub struct Drawer<S: ParseString> {
+    /// The name of the drawer, as it was written (e.g. `LOGBOOK`).
+    pub name: String,
+    /// What's inside the drawer.
+    pub contents: DrawerContents<S>,
+}
+impl<S: ParseString> Drawer<S> {
+    /// Converts this drawer into its string representation in the given format, including its
+    /// opening and closing lines.
+    pub fn into_string(self, format: Format) -> String {
+        let contents = match self.contents {
+            DrawerContents::Logbook(logbook) => logbook.into_string(),
+            DrawerContents::Text(text) => text.to_string(format),
+        };
+        let mut drawer_str = format.get_drawer_opener(&self.name);
+        if !contents.is_empty() {
+            drawer_str.push('\n');
+            drawer_str.push_str(&contents);
+        }
+        drawer_str.push('\n');
+        drawer_str.push_str(format.get_drawer_closer());
+
+        drawer_str
+    }
+}
+
+/// The contents of a drawer.
+#[derive(Debug, Clone)]
+pub enum DrawerContents<S: ParseString> {
+    /// The contents of a `LOGBOOK` drawer, which are parsed into typed entries.
+    Logbook(Logbook),
+    /// The contents of any other drawer, which are kept as text.
+    Text(S),
+}
+
+/// The entries in a `LOGBOOK` drawer. Org mode adds new entries at the start, so these will
+/// usually be in reverse chronological order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Logbook {
+    inner: Vec<LogEntry>,
+}
+impl Logbook {
+    /// Parses a logbook from the lines inside its drawer.
+    fn from_lines(lines: &[&str]) -> Self {
+        Self {
+            inner: Self::group_lines(lines)
+                .into_iter()
+                .map(|group| LogEntry::from_lines(&group))
+                .collect(),
+        }
+    }
+    /// Groups the lines inside a logbook drawer into the lines of each entry. Entries start with
+    /// either `CLOCK:` or a list item, and may continue onto further lines (e.g. for notes).
+    pub(crate) fn group_lines<'l>(lines: &[&'l str]) -> Vec<Vec<&'l str>> {
+        let mut groups: Vec<Vec<&str>> = Vec::new();
+        for line in lines {
+            let trimmed = line.trim_start();
+            let starts_entry = trimmed.starts_with("CLOCK:") || trimmed.starts_with("- ");
+            match groups.last_mut() {
+                Some(group) if !starts_entry => group.push(line),
+                _ => groups.push(vec![line]),
+            }
+        }
+
+        groups
+    }
+    /// Converts this logbook into its string representation (without the drawer delimiters).
+    pub fn into_string(self) -> String {
+        self.inner
+            .into_iter()
+            .map(|entry| entry.into_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+impl std::ops::Deref for Logbook {
+    type Target = Vec<LogEntry>;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+impl std::ops::DerefMut for Logbook {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+/// A single entry in a logbook.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogEntry {
+    /// A clock entry (e.g. `CLOCK: [2023-01-01 Sun 10:00]--[2023-01-01 Sun 11:30] =>  1:30`).
+    Clock(Clock),
+    /// A record of a change in the keyword of a node (e.g.
+    /// `- State "DONE"       from "TODO"       [2023-01-01 Sun 10:00]`).
+    StateChange {
+        /// The keyword the node was changed to.
+        to: String,
+        /// The keyword the node was changed from, if it had one.
+        from: Option<String>,
+        /// When the change happened.
+        time: NaiveDateTime,
+        /// Any note attached to the change, with its indentation removed.
+        note: Option<String>,
+    },
+    /// A note attached to the node (e.g. `- Note taken on [2023-01-01 Sun 10:00] \\`, followed by
+    /// the note on the next lines).
+    Note {
+        /// When the note was taken.
+        time: NaiveDateTime,
+        /// The note itself, with its indentation removed.
+        text: String,
+    },
+    /// Any other entry (e.g. `- Rescheduled from ...`), kept exactly as it was written, including
+    /// any lines after the first.
+    Other(String),
+}
+impl LogEntry {
+    /// Parses a logbook entry from the given lines, falling back to [`Self::Other`] if it isn't
+    /// recognised.
+    pub(crate) fn from_lines(lines: &[&str]) -> Self {
+        let first = lines[0].trim();
+        let parsed = if first.starts_with("CLOCK:") {
+            if lines.len() == 1 {
+                Clock::from_str(first).ok().map(Self::Clock)
+            } else {
+                None
+            }
+        } else if let Some(state) = first.strip_prefix("- State ") {
+            Self::parse_state_change(state, &lines[1..])
+        } else if let Some(note) = first.strip_prefix("- Note taken on ") {
+            let (time, has_note) = parse_time_and_continuation(note);
+            match (time, note_from_lines(&lines[1..], has_note)) {
+                (Some(time), Some(Some(text))) => Some(Self::Note { time, text }),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        parsed.unwrap_or_else(|| Self::Other(lines.join("\n")))
+    }
+    /// Parses the rest of a state change entry after `- State `.
+    fn parse_state_change(state: &str, continuation: &[&str]) -> Option<Self> {
+        let (to, rest) = parse_quoted(state)?;
+        let rest = rest.trim_start().strip_prefix("from")?.trim_start();
+        let (from, rest) = match parse_quoted(rest) {
+            Some((from, rest)) => (Some(from), rest),
+            None => (None, rest),
+        };
+        let (time, has_note) = parse_time_and_continuation(rest);
+
+        Some(Self::StateChange {
+            to,
+            from,
+            time: time?,
+            note: note_from_lines(continuation, has_note)?,
+        })
+    }
+    /// Converts this entry into its string representation, as Org mode would write it.
+    pub fn into_string(self) -> String {
+        match self {
+            Self::Clock(clock) => clock.into_string(),
+            Self::StateChange {
+                to,
+                from,
+                time,
+                note,
+            } => {
+                let to = format!("\"{to}\"");
+                let from = from.map(|from| format!("\"{from}\"")).unwrap_or_default();
+                let entry = format!("- State {to:<12} from {from:<12} {}", format_time(time));
+                with_note(entry, note)
+            }
+            Self::Note { time, text } => {
+                with_note(format!("- Note taken on {}", format_time(time)), Some(text))
+            }
+            Self::Other(text) => text,
+        }
+    }
+}
+
+/// A clock entry in a logbook, recording a period of time spent on a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clock {
+    /// When the clock was started.
+    pub start: NaiveDateTime,
+    /// When the clock was stopped, if it has been.
+    pub end: Option<NaiveDateTime>,
+}
+impl Clock {
+    /// Parses a clock from a `CLOCK:` line (e.g.
+    /// `CLOCK: [2023-01-01 Sun 10:00]--[2023-01-01 Sun 11:30] =>  1:30`), or just the timestamp
+    /// part of one. The duration is ignored, as it can be calculated from the start and end.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(clock: &str) -> Result<Self, ClockError> {
+        let clock = clock.trim();
+        let timestamp = clock.strip_prefix("CLOCK:").unwrap_or(clock);
+        let timestamp = timestamp
+            .split_once("=>")
+            .map_or(timestamp, |(range, _)| range);
+        let timestamp = Timestamp::from_str(timestamp)?;
+
+        let invalid = || ClockError::InvalidClock {
+            clock: clock.to_string(),
+        };
+        if timestamp.active || timestamp.repeater.is_some() {
+            return Err(invalid());
+        }
+        let start = timestamp
+            .start
+            .date
+            .and_time(timestamp.start.time.ok_or_else(invalid)?);
+        let end = match timestamp.end {
+            Some(end) => Some(end.date.and_time(end.time.ok_or_else(invalid)?)),
+            None => None,
+        };
+
+        Ok(Self { start, end })
+    }
+    /// Gets how long this clock ran for, if it has been stopped.
+    pub fn duration(&self) -> Option<Duration> {
+        self.end.map(|end| end - self.start)
+    }
+    /// Converts this clock into its string representation, including the `CLOCK:` prefix and, if
+    /// the clock has been stopped, its duration.
+    pub fn into_string(self) -> String {
+        match self.end {
+            Some(end) => {
+                let minutes = (end - self.start).num_minutes();
+                format!(
+                    "CLOCK: {}--{} => {:>2}:{:02}",
+                    format_time(self.start),
+                    format_time(end),
+                    minutes / 60,
+                    minutes % 60
+                )
+            }
+            None => format!("CLOCK: {}", format_time(self.start)),
+        }
+    }
+}
+
+/// Formats the given time as an inactive timestamp (e.g. `[2023-01-01 Sun 10:00]`).
+fn format_time(time: NaiveDateTime) -> String {
+    format!("[{}]", time.format("%Y-%m-%d %a %H:%M"))
+}
+
+/// Parses a quoted string (e.g. `"DONE"`) from the start of the given text, returning it and the
+/// rest of the text.
+fn parse_quoted(text: &str) -> Option<(String, &str)> {
+    let (quoted, rest) = text.strip_prefix('"')?.split_once('"')?;
+    Some((quoted.to_string(), rest))
+}
+
+/// Parses an inactive timestamp with a time (e.g. `[2023-01-01 Sun 10:00]`) from the given text,
+/// which may be followed by `\\` to indicate that a note follows on the next lines.
+fn parse_time_and_continuation(text: &str) -> (Option<NaiveDateTime>, bool) {
+    let text = text.trim();
+    let (text, has_note) = match text.strip_suffix("\\\\") {
+        Some(text) => (text.trim_end(), true),
+        None => (text, false),
+    };
+    let time = Timestamp::from_str(text).ok().and_then(|timestamp| {
+        if timestamp.active || timestamp.end.is_some() || timestamp.repeater.is_some() {
+            None
+        } else {
+            Some(timestamp.start.date.and_time(timestamp.start.time?))
+        }
+    });
+
+    (time, has_note)
+}
+
+/// Extracts a note from the lines after the first line of an entry. This returns `None` if the
+/// lines don't match whether or not a note was expected (in which case the entry should be kept
+/// as it was), and `Some(None)` if there's no note.
+fn note_from_lines(lines: &[&str], has_note: bool) -> Option<Option<String>> {
+    match (has_note, lines.is_empty()) {
+        (true, false) => Some(Some(
+            lines
+                .iter()
+                .map(|line| line.trim())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )),
+        (false, true) => Some(None),
+        _ => None,
+    }
+}
+
+/// Adds the given note to the first line of an entry, if there is one.
+fn with_note(entry: String, note: Option<String>) -> String {
+    match note {
+        Some(note) => {
+            let note = note
+                .lines()
+                .map(|line| format!("  {line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("{entry} \\\\\n{note}")
+        }
+        None => entry,
+    }
+}
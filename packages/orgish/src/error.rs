@@ -41,6 +41,58 @@ pub enum ParseError {
     RootTitleNotString,
     #[error("found `tags` attribute on the document root that wasn't an array of strings")]
     RootTagsNotStringVec,
+    // Like `PlanningRepeat`, this is only ever reported as a warning
+    #[error("found property drawer that was never closed")]
+    UnclosedProperties,
+    // This is also only a warning, the heading is added to the nearest node above it
+    #[error("found heading at level {level} with no heading at the level above it (nearest was at level {parent_level})")]
+    SkippedLevel { parent_level: u8, level: u8 },
+}
+
+/// A problem found while parsing a document with
+/// [`Document::from_str_with_diagnostics`](crate::Document::from_str_with_diagnostics). Unlike a
+/// bare [`ParseError`], this records where in the document the problem was found.
+#[derive(Debug)]
+pub struct Diagnostic {
+    /// The line the problem was found on. This is one-indexed, as an editor would display it.
+    pub line: usize,
+    /// The column at which the offending text starts, in characters. This is also one-indexed.
+    pub column: usize,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// The text that caused the problem (e.g. a whole property line, or just an invalid timestamp).
+    pub text: String,
+    /// The underlying error.
+    pub error: ParseError,
+}
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.error
+        )
+    }
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Something could not be parsed, and the offending text was either dropped or parsed as
+    /// something else (e.g. an invalid planning line will become part of the body). Parsing the
+    /// document with [`Document::from_str`](crate::Document::from_str) would fail.
+    Error,
+    /// Something was parsed, but probably not as intended (e.g. a planning keyword was repeated,
+    /// and only the last instance was kept). These would not cause a strict parse to fail.
+    Warning,
+}
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
 }
 
 /// Errors that can occur specifically while parsing timestamps.
@@ -66,12 +118,32 @@ pub enum TimestampParseError {
     InvalidDateComponents { year: i32, month: u32, day: u32 },
     #[error("found range timestamp `<..>--<..>` with ranges inside")]
     RangeInRange { timestamp: String },
+    #[error("found range timestamp `<..>--<..>` with a different repeater or delay on its end: '{timestamp}'")]
+    RangeEndModifiers { timestamp: String },
     #[error("found unexpected character '{c}' in timestamp")]
     BadCharacter { c: char },
     #[error("found a day name in a timestamp that was more than three characters (had '{current}', but then found '{next_c}')")]
     DayNameTooLong { current: String, next_c: char },
-    #[error("foud invalid repeater unit '{c}' in timestamp (expected d/w/m/y)")]
+    #[error("found invalid repeater unit '{c}' in timestamp (expected h/d/w/m/y)")]
     BadRepeaterUnit { c: char },
+    #[error("found invalid repeater mark '{mark}' in timestamp (expected +/++/.+)")]
+    BadRepeaterMark { mark: String },
+    #[error(
+        "found invalid repeater count '{count}' in timestamp (expected a number before the unit)"
+    )]
+    InvalidRepeaterCount { count: String },
+    #[error("found repeater with a count of zero in timestamp (it would never repeat)")]
+    ZeroRepeaterCount,
+    #[error(
+        "found repeater '{repeater}' without a unit in timestamp (expected h/d/w/m/y after it)"
+    )]
+    MissingRepeaterUnit { repeater: String },
+    #[error("found invalid delay count '{count}' in timestamp")]
+    InvalidDelayCount { count: String },
+    #[error("found delay with a count of zero in timestamp")]
+    ZeroDelayCount,
+    #[error("found delay '{count}' without a unit in timestamp (expected h/d/w/m/y after it)")]
+    MissingDelayUnit { count: String },
     #[error("found invalid time in timestamp: '{time_str}'")]
     InvalidTime {
         time_str: String,
@@ -79,3 +151,115 @@ pub enum TimestampParseError {
         source: chrono::ParseError,
     },
 }
+
+/// Errors that can occur while parsing user input into a timestamp.
+#[derive(Debug, Error)]
+pub enum TimestampInputError {
+    #[error("no timestamp was given")]
+    Empty,
+    #[error("failed to parse timestamp")]
+    TimestampParseFailed {
+        #[from]
+        source: TimestampParseError,
+    },
+    #[error("couldn't understand '{token}' in timestamp input")]
+    UnknownToken { token: String },
+    #[error(
+        "found '{token}' in timestamp input, but that part of the timestamp was already given"
+    )]
+    Duplicate { token: String },
+    #[error("found '{token}' in timestamp input, but repeaters and delays must come after a date")]
+    NoDate { token: String },
+    #[error("repeaters and delays must be at least one unit long (found '{token}')")]
+    ZeroCount { token: String },
+    #[error("expected a day of the week after 'next', found '{token}'")]
+    ExpectedWeekday { token: String },
+    #[error("invalid time '{time}' in timestamp input")]
+    InvalidTime {
+        time: String,
+        #[source]
+        source: chrono::ParseError,
+    },
+    #[error("time range '{token}' in timestamp input ends before it starts")]
+    EndBeforeStart { token: String },
+    #[error("dates relative to today can't be given in hours (found '{token}')")]
+    RelativeHours { token: String },
+    #[error("date described by '{token}' is out of range")]
+    OutOfRange { token: String },
+}
+
+/// Errors that can occur while reading a keyword set.
+#[derive(Debug, Error)]
+pub enum KeywordSetError {
+    #[error("no keywords found in keyword set definition '{definition}'")]
+    NoKeywords { definition: String },
+    #[error("keyword set attribute must be a string")]
+    NotString,
+}
+
+/// Errors that can occur while parsing priorities and priority models.
+#[derive(Debug, Error)]
+pub enum PriorityError {
+    #[error(
+        "invalid priority '{priority}' (expected an uppercase letter or a number from 0 to 64)"
+    )]
+    InvalidPriority { priority: String },
+    #[error("invalid priority model '{definition}' (expected the highest, lowest, and default priorities)")]
+    InvalidDefinition { definition: String },
+    #[error("priority model must use either letters or numbers, not both")]
+    MixedKinds,
+    #[error("invalid priority range: highest '{highest}' must be above lowest '{lowest}', with default '{default}' between them")]
+    InvalidRange {
+        highest: String,
+        lowest: String,
+        default: String,
+    },
+    #[error("priorities attribute must be a string")]
+    NotString,
+}
+
+/// Errors that can occur while reading a tag configuration.
+#[derive(Debug, Error)]
+pub enum TagConfigError {
+    #[error("tag group was never closed in tag definition '{definition}'")]
+    UnclosedGroup { definition: String },
+    #[error("tag groups can't be nested (in tag definition '{definition}')")]
+    NestedGroup { definition: String },
+    #[error(
+        "found the end of a tag group that was never started in tag definition '{definition}'"
+    )]
+    UnexpectedClose { definition: String },
+    #[error("tag configuration attribute must be a string")]
+    NotString,
+}
+
+/// Errors that can occur while resolving the timezone of a node or document.
+#[cfg(feature = "timezones")]
+#[derive(Debug, Error)]
+pub enum TimezoneError {
+    #[error("unknown timezone '{name}'")]
+    UnknownTimezone { name: String },
+    #[error("timezone attribute must be a string")]
+    NotString,
+}
+
+/// Errors that can occur while parsing or working with clocks.
+#[derive(Debug, Error)]
+pub enum ClockError {
+    #[error("failed to parse timestamp in clock")]
+    TimestampParseFailed {
+        #[from]
+        source: TimestampParseError,
+    },
+    #[error("clocks must be inactive timestamps with times and no repeaters, found '{clock}'")]
+    InvalidClock { clock: String },
+    #[error("node is already clocked in (since {start})")]
+    AlreadyClockedIn { start: chrono::NaiveDateTime },
+    #[error("node is not clocked in")]
+    NotClockedIn,
+    #[error("attempted to clock out at {end}, which is before the clock was started ({start})")]
+    EndBeforeStart {
+        start: chrono::NaiveDateTime,
+        end: chrono::NaiveDateTime,
+    },
+}
@@ -32,4 +32,34 @@ impl Format {
             Self::Org => ":END:",
         }
     }
+    /// Gets the string used to open a drawer with the given name in this format.
+    pub(crate) fn get_drawer_opener(&self, name: &str) -> String {
+        match &self {
+            Self::Markdown => format!("<!--{name}"),
+            Self::Org => format!(":{name}:"),
+        }
+    }
+    /// Gets the string used to close drawers in this format (which is the same as for property
+    /// drawers).
+    pub(crate) fn get_drawer_closer(&self) -> &'static str {
+        self.get_properties_closer()
+    }
+    /// If the given (trimmed) line opens a drawer other than the property drawer, gets the name
+    /// of that drawer.
+    pub(crate) fn get_drawer_name<'l>(&self, line: &'l str) -> Option<&'l str> {
+        let name = match &self {
+            Self::Markdown => line.strip_prefix("<!--"),
+            Self::Org => line
+                .strip_prefix(':')
+                .and_then(|line| line.strip_suffix(':')),
+        }?;
+        let is_valid = !name.is_empty()
+            && !name.eq_ignore_ascii_case("END")
+            && !name.eq_ignore_ascii_case("PROPERTIES")
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+
+        is_valid.then_some(name)
+    }
 }
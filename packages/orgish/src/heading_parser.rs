@@ -109,7 +109,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
                                     // We have something that might be a keyword, but no priority after it, so we'll
                                     // parse it as part of the title; this means we have to backtrack by *two*
                                     // words (one extra for the space)
-                                    i = i - (curr.chars().count() + potential_kw.chars().count() + 2);
+                                    i -= curr.chars().count() + potential_kw.chars().count() + 2;
                                     loc = NodeParseLocation::Title;
                                 },
                             }
@@ -141,9 +141,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
                             let mut was_valid = true;
                             // We start this from just after the first `:` to avoid accumulating an empty tag.
                             // We critically avoid the final space!
-                            for j in (i + 1)..(chars.len() - 1) {
-                                let c = chars[j];
-
+                            for &c in &chars[(i + 1)..(chars.len() - 1)] {
                                 // Make sure this character is valid in a tag (see https://orgmode.org/manual/Tags.html)
                                 if c == ':' {
                                     // We know this is non-empty because of the `is_tag_starter` check
@@ -170,15 +168,23 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
                                 curr.push(c);
                             }
                         } else if is_timestamp_starter {
+                            let start = i;
                             let mut timestamp = String::new();
-                            for j in i..chars.len() {
-                                let c = chars[j];
-
+                            let mut in_range = false;
+                            for (j, &c) in chars.iter().enumerate().skip(i) {
                                 // Stop when the timestamp ends, but otherwise leave parsing up to the
                                 // dedicated timestamp parser
                                 if c == '>' {
                                     // We still want that final closing tag
                                     timestamp.push(c);
+                                    // If this is the first half of a range timestamp, keep going
+                                    // until the end of the second half
+                                    if !in_range
+                                        && chars.get(j + 1..j + 4) == Some(&['-', '-', '<'])
+                                    {
+                                        in_range = true;
+                                        continue;
+                                    }
                                     // Jump forward after the timestamp
                                     i = j + 1;
                                     break;
@@ -192,6 +198,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
                                 // It wasn't a valid timestamp, abort all this and keep parsing (the
                                 // innter loop will be discarded)
                                 curr.push(c); // This pushes `<`
+                                i = start;
                             }
                         } else {
                             curr.push(c);
@@ -260,10 +267,7 @@ enum KeywordStatus {
 impl KeywordStatus {
     /// Returns whether or not an ambiguous keyword has been found.
     fn is_ambiguous(&self) -> bool {
-        match &self {
-            Self::Ambiguous(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Ambiguous(_))
     }
 }
 
@@ -10,6 +10,10 @@ use serde::Serialize;
 
 impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
     /// Converts this document into a string.
+    ///
+    /// If this document was parsed with [`Self::from_str_lossless`], and it's being written in the
+    /// same format, anything that hasn't been changed since parsing will be written exactly as it
+    /// originally was.
     pub fn into_string(mut self, format: Format) -> String {
         // Implant the title and tags back into the attributes (we ned to provide the format in
         // case there were no attributes before and we need to create some, in which case we may as
@@ -17,6 +21,13 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
         self.attributes
             .set_title(self.root.title.to_string(format), format);
         self.attributes.set_tags(self.root.tags.to_vec(), format);
+        if self.root.spans.is_some() {
+            if let Some(source) = self.root.source.take() {
+                if source.format() == format {
+                    return self.into_lossless_string(&source);
+                }
+            }
+        }
         // This won't include the attributes
         let root_str = self.root.into_string(format);
         // Put the attributes in the appropriate place depending on the format. Note that the
@@ -59,9 +70,23 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
     /// If called for the root node (i.e. a node with level `0`), this function will
     /// not produce a heading, only the body contents (by recursively calling this
     /// method on the rest of the node tree).
+    ///
+    /// Nodes parsed with [`Document::from_str_lossless`] will be written in the same way as their
+    /// source text wherever they haven't been changed, provided they're written in the same format
+    /// they were parsed from (this doesn't apply to the root node, which is handled by
+    /// [`Document::into_string`]).
     // Implementation: this is only possible if the representation of each node is *totally*
     // self-contained, a property that must be preserved by the parser.
-    pub fn into_string(self, format: Format) -> String {
+    pub fn into_string(mut self, format: Format) -> String {
+        // Nodes that were parsed losslessly will reuse as much of their source text as they can
+        if self.level > 0 && self.spans.is_some() {
+            if let Some(source) = self.source.take() {
+                if source.format() == format {
+                    return self.into_lossless_string(&source);
+                }
+            }
+        }
+
         let mut node_parts = Vec::new();
         // Alias closure for pushing things that aren't empty (otherwise we get too many newlines)
         let mut push_part = |part: String| {
@@ -69,49 +94,15 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
                 node_parts.push(part);
             }
         };
-        let with_space_after = |thing: &str| {
-            if thing.is_empty() {
-                String::new()
-            } else {
-                format!("{thing} ")
-            }
-        };
-        let with_space_before = |thing: &str| {
-            if thing.is_empty() {
-                String::new()
-            } else {
-                format!(" {thing}")
-            }
-        };
         // Handling the root node is quite special (keep in mind this will occur in the context of
         // the document parsing itself!)
         if self.level > 0 {
-            let stars = format
-                .heading_char()
-                .to_string()
-                .repeat(self.level as usize);
-            let tags_str = with_space_before(&self.tags.into_string());
-            let title = self.title.to_string(format);
-            let keyword =
-                with_space_after(&self.keyword.map(|k| k.into_string()).unwrap_or_default());
-            let priority = with_space_after(&self.priority.into_string());
-            let timestamps = with_space_before(
-                &self
-                    .timestamps
-                    .into_iter()
-                    .map(|t| t.into_string())
-                    .collect::<Vec<_>>()
-                    .join(" "),
-            );
-
-            let heading = format!("{stars} {keyword}{priority}{title}{timestamps}{tags_str}")
-                .trim()
-                .to_string();
-            push_part(heading);
+            push_part(self.take_heading_string(format));
             // Add the planning info (https://orgmode.org/worg/org-syntax.html#Property_Drawers
             // makes clear that nothing else comes before properties)
             push_part(self.planning.into_string());
             push_part(self.properties.into_string(format));
+            push_part(self.drawers.into_string(format));
         } else {
             // For the root, we only care about properties (the title and tags will be handled at
             // the document-level, implanting from the attributes)
@@ -132,6 +123,50 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
 
         node_parts.join("\n")
     }
+    /// Converts the heading line of this node into a string, taking the keyword, priority, tags,
+    /// and timestamps out of the node in the process. This should not be called on the root node.
+    pub(crate) fn take_heading_string(&mut self, format: Format) -> String {
+        let with_space_after = |thing: &str| {
+            if thing.is_empty() {
+                String::new()
+            } else {
+                format!("{thing} ")
+            }
+        };
+        let with_space_before = |thing: &str| {
+            if thing.is_empty() {
+                String::new()
+            } else {
+                format!(" {thing}")
+            }
+        };
+
+        let stars = format
+            .heading_char()
+            .to_string()
+            .repeat(self.level as usize);
+        let tags_str = with_space_before(&std::mem::take(&mut self.tags).into_string());
+        let title = self.title.to_string(format);
+        let keyword = with_space_after(
+            &self
+                .keyword
+                .take()
+                .map(|k| k.into_string())
+                .unwrap_or_default(),
+        );
+        let priority = with_space_after(&std::mem::take(&mut self.priority).into_string());
+        let timestamps = with_space_before(
+            &std::mem::take(&mut self.timestamps)
+                .into_iter()
+                .map(|t| t.into_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+
+        format!("{stars} {keyword}{priority}{title}{timestamps}{tags_str}")
+            .trim()
+            .to_string()
+    }
 }
 
 impl Priority {
@@ -146,17 +181,13 @@ impl Priority {
 }
 
 impl Planning {
-    /// Converts these planning items into their string representation.
+    /// Converts these planning items into their string representation, with each item on its own
+    /// line.
     pub fn into_string(self) -> String {
         let mut planning_items = Vec::new();
-
         let mut add_item = |prop: Option<Timestamp>, name: &'static str| {
             if let Some(timestamp) = prop {
-                let mut item = name.to_string();
-                item.push_str(": ");
-                item.push_str(&timestamp.into_string());
-
-                planning_items.push(item);
+                planning_items.push(format!("{name}: {}", timestamp.into_string()));
             }
         };
 
@@ -166,6 +197,22 @@ impl Planning {
 
         planning_items.join("\n")
     }
+    /// Converts these planning items into a single line of text, in the canonical form Org mode
+    /// itself uses (e.g. `CLOSED: [2023-01-02 Mon 10:00] DEADLINE: <2023-01-01 Sun>`).
+    pub fn into_single_line_string(self) -> String {
+        let mut planning_items = Vec::new();
+        let mut add_item = |prop: Option<Timestamp>, name: &'static str| {
+            if let Some(timestamp) = prop {
+                planning_items.push(format!("{name}: {}", timestamp.into_string()));
+            }
+        };
+
+        add_item(self.closed, "CLOSED");
+        add_item(self.deadline, "DEADLINE");
+        add_item(self.scheduled, "SCHEDULED");
+
+        planning_items.join(" ")
+    }
 }
 
 impl<I: ParseId, S: ParseString> Properties<I, S> {
@@ -185,11 +232,7 @@ impl<I: ParseId, S: ParseString> Properties<I, S> {
         // implementation of the ID parser)
         if self.id.is_some() {
             properties_str.push('\n');
-            properties_str.push_str(match format {
-                Format::Markdown => "ID: ",
-                Format::Org => ":ID: ",
-            });
-            properties_str.push_str(&self.id.into_string());
+            properties_str.push_str(&property_line("ID", &self.id.into_string(), format));
         }
         // Now do the regular properties (in alphabetical order, for testing consistency)
         let mut keys = self.inner.keys().collect::<Vec<_>>();
@@ -198,13 +241,7 @@ impl<I: ParseId, S: ParseString> Properties<I, S> {
             let v = self.inner.get(k).unwrap();
 
             properties_str.push('\n');
-            // The key-leading colon is Org-only
-            if format == Format::Org {
-                properties_str.push(':');
-            }
-            properties_str.push_str(&k);
-            properties_str.push_str(": ");
-            properties_str.push_str(&v.to_string(format));
+            properties_str.push_str(&property_line(k, &v.to_string(format), format));
         }
         properties_str.push('\n');
         properties_str.push_str(format.get_properties_closer());
@@ -213,6 +250,15 @@ impl<I: ParseId, S: ParseString> Properties<I, S> {
     }
 }
 
+/// Creates a single line in a property drawer for the given key and value.
+pub(crate) fn property_line(key: &str, value: &str, format: Format) -> String {
+    match format {
+        // The key-leading colon is Org-only
+        Format::Org => format!(":{key}: {value}"),
+        Format::Markdown => format!("{key}: {value}"),
+    }
+}
+
 impl Tags {
     /// Converts these tags into their string representation (i.e. `:tag1:tag2:tag3:`).
     pub fn into_string(self) -> String {
@@ -222,7 +268,7 @@ impl Tags {
             let mut tags_str = ":".to_string();
             for tag in self.inner {
                 tags_str.push_str(&tag);
-                tags_str.push_str(":");
+                tags_str.push(':');
             }
 
             tags_str
@@ -322,7 +368,7 @@ impl Attributes {
     /// from Org to Markdown, YAML frontmatter will be returned. If converting from YAML/TOML
     /// Markdown to Org, any non-string properties will be serialised to strings and inserted as
     /// single-line values.
-    fn into_string(self, format: Format) -> String {
+    pub(crate) fn into_string(self, format: Format) -> String {
         match format {
             Format::Markdown => match self {
                 Self::MarkdownYaml(map) => {
@@ -1,4 +1,13 @@
-//! A typed implementation of keywords with support for wildcards.
+//! A typed implementation of keywords with support for wildcards, and the workflows they move
+//! through.
+
+use crate::error::KeywordSetError;
+use crate::{Attributes, Document, ParseId, ParseString};
+
+/// Derives [`Keyword`] for an `enum` of keywords, along with [`WorkflowKeyword`] if any of them
+/// are marked as done states. See the documentation of `orgish-derive` for details.
+#[cfg(feature = "derive")]
+pub use orgish_derive::Keyword;
 
 /// A trait that should be implemented by all user-defined `enum`s used
 /// to encode the keywords used in documents.
@@ -21,3 +30,191 @@ pub trait Keyword: Sized {
     /// miss valid priorities).
     fn other(keyword: String) -> Self;
 }
+
+/// A sequence of keyword states that nodes move through, such as `TODO` to `NEXT` to `DONE`.
+/// States are split into those which still need to be done, and those which are done, which
+/// allows working out what marking a node as done (or undoing that) should do.
+///
+/// This can be implemented by anything which knows the states, whether it's a runtime
+/// [`KeywordSet`] read from a document's `#+TODO`, or a keyword type which defines its own states
+/// (see [`WorkflowKeyword`] and [`TypedWorkflow`]). Keywords are compared by their string forms.
+pub trait Workflow<K: Keyword + Clone> {
+    /// Gets all the states in this workflow, in order. All the states which still need to be done
+    /// should come before all the done states.
+    fn states(&self) -> Vec<K>;
+    /// Returns whether or not the given keyword is a done state.
+    fn is_done(&self, keyword: &K) -> bool;
+
+    /// Gets the first state in this workflow which still needs to be done, if there is one.
+    fn first_todo(&self) -> Option<K> {
+        self.states().into_iter().find(|state| !self.is_done(state))
+    }
+    /// Gets the first done state in this workflow, if there is one.
+    fn first_done(&self) -> Option<K> {
+        self.states().into_iter().find(|state| self.is_done(state))
+    }
+    /// Gets the state after the given one, cycling as Org mode does: no keyword moves to the first
+    /// state, and the last state moves to no keyword. Keywords not in this workflow also move to
+    /// the first state.
+    fn next(&self, keyword: Option<&K>) -> Option<K> {
+        let states = self.states();
+        match keyword.and_then(|keyword| position(&states, keyword)) {
+            Some(idx) => states.into_iter().nth(idx + 1),
+            None => states.into_iter().next(),
+        }
+    }
+    /// Gets the state before the given one, cycling in the opposite direction to [`Self::next`]:
+    /// no keyword moves to the last state, and the first state moves to no keyword. Keywords not
+    /// in this workflow also move to the last state.
+    fn previous(&self, keyword: Option<&K>) -> Option<K> {
+        let states = self.states();
+        match keyword.and_then(|keyword| position(&states, keyword)) {
+            Some(idx) => idx
+                .checked_sub(1)
+                .and_then(|idx| states.into_iter().nth(idx)),
+            None => states.into_iter().last(),
+        }
+    }
+}
+
+/// Finds the index of the given keyword in the given list of states, comparing them as strings.
+fn position<K: Keyword + Clone>(states: &[K], keyword: &K) -> Option<usize> {
+    let keyword = keyword.clone().into_string();
+    states
+        .iter()
+        .position(|state| state.clone().into_string() == keyword)
+}
+
+/// A companion trait to [`Keyword`] for keyword types which define their own workflow at
+/// compile-time. Use [`TypedWorkflow`] to get a [`Workflow`] from this.
+pub trait WorkflowKeyword: Keyword + Clone {
+    /// Gets all the states of this keyword, in order. All the states which still need to be done
+    /// should come before all the done states.
+    fn states() -> Vec<Self>;
+    /// Returns whether or not this keyword is a done state.
+    fn is_done(&self) -> bool;
+}
+
+/// The [`Workflow`] defined by a keyword type which implements [`WorkflowKeyword`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypedWorkflow;
+impl<K: WorkflowKeyword> Workflow<K> for TypedWorkflow {
+    fn states(&self) -> Vec<K> {
+        K::states()
+    }
+    fn is_done(&self, keyword: &K) -> bool {
+        keyword.is_done()
+    }
+}
+
+/// A set of keyword states defined at runtime, as with Org mode's `#+TODO: TODO NEXT | DONE KILL`
+/// attribute. This works with any keyword type, creating keywords from their strings when needed
+/// (unknown ones will be created with [`Keyword::other`]).
+///
+/// The default set is Org mode's default of `TODO | DONE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordSet {
+    /// The states which still need to be done, in order.
+    pub todo: Vec<String>,
+    /// The done states, in order.
+    pub done: Vec<String>,
+}
+impl Default for KeywordSet {
+    fn default() -> Self {
+        Self {
+            todo: vec!["TODO".to_string()],
+            done: vec!["DONE".to_string()],
+        }
+    }
+}
+impl KeywordSet {
+    /// Parses a keyword set from the given definition, in the format of Org's `#+TODO` (e.g.
+    /// `TODO NEXT(n) | DONE(d!) KILL`). The keywords after the `|` are done states, and, if there
+    /// isn't one, the last keyword is the only done state. Fast-access keys and logging settings in
+    /// parentheses are ignored.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(definition: &str) -> Result<Self, KeywordSetError> {
+        let mut todo = Vec::new();
+        let mut done = Vec::new();
+        let mut seen_bar = false;
+        for word in definition.split_whitespace() {
+            if word == "|" {
+                seen_bar = true;
+                continue;
+            }
+            let keyword = match word.find('(') {
+                Some(idx) if word.ends_with(')') => &word[..idx],
+                _ => word,
+            };
+            if seen_bar {
+                done.push(keyword.to_string());
+            } else {
+                todo.push(keyword.to_string());
+            }
+        }
+        if !seen_bar {
+            done.extend(todo.pop());
+        }
+
+        if todo.is_empty() && done.is_empty() {
+            Err(KeywordSetError::NoKeywords {
+                definition: definition.to_string(),
+            })
+        } else {
+            Ok(Self { todo, done })
+        }
+    }
+    /// Gets the keyword set defined in the given document attributes, if there is one. In Org
+    /// mode, this is defined by the `#+TODO` attribute (or its aliases `#+SEQ_TODO` and
+    /// `#+TYP_TODO`), and in Markdown by the `todo` key in the frontmatter, in the same format.
+    ///
+    /// Note that only one definition can be read, so documents with several `#+TODO` lines should
+    /// combine them into one.
+    pub fn from_attributes(attributes: &Attributes) -> Option<Result<Self, KeywordSetError>> {
+        let definition = match attributes {
+            Attributes::Org(map) => Ok(["todo", "seq_todo", "typ_todo"]
+                .iter()
+                .find_map(|key| map.get(*key))?
+                .as_str()),
+            Attributes::MarkdownYaml(map) => {
+                map.get("todo")?.as_str().ok_or(KeywordSetError::NotString)
+            }
+            Attributes::MarkdownToml(map) => {
+                map.get("todo")?.as_str().ok_or(KeywordSetError::NotString)
+            }
+            Attributes::None => return None,
+        };
+        Some(definition.and_then(Self::from_str))
+    }
+    /// Returns whether or not the given string is one of the keywords in this set.
+    pub fn contains(&self, keyword: &str) -> bool {
+        self.todo
+            .iter()
+            .chain(self.done.iter())
+            .any(|k| k == keyword)
+    }
+    /// Returns whether or not the given string is a done keyword in this set.
+    pub fn is_done_keyword(&self, keyword: &str) -> bool {
+        self.done.iter().any(|k| k == keyword)
+    }
+}
+impl<K: Keyword + Clone> Workflow<K> for KeywordSet {
+    fn states(&self) -> Vec<K> {
+        self.todo
+            .iter()
+            .chain(self.done.iter())
+            .map(|keyword| K::from_str(keyword).unwrap_or_else(|| K::other(keyword.clone())))
+            .collect()
+    }
+    fn is_done(&self, keyword: &K) -> bool {
+        self.is_done_keyword(&keyword.clone().into_string())
+    }
+}
+
+impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
+    /// Gets the keyword set defined by this document's attributes, if there is one. See
+    /// [`KeywordSet::from_attributes`] for details.
+    pub fn keyword_set(&self) -> Option<Result<KeywordSet, KeywordSetError>> {
+        KeywordSet::from_attributes(&self.attributes)
+    }
+}
@@ -13,30 +13,46 @@
 //! can be delimited within a heading through some special character sequence (e.g. `PROPERTIES:`
 //! or `+++`).
 
+mod arithmetic;
+mod clock;
+pub mod drawer;
 /// Errors to do with parsing and handling the representations of documents.
 pub mod error;
 mod format;
 mod heading_parser;
 mod into_format;
 pub mod keyword;
+mod lossless;
 mod parse_id;
 mod parse_string;
 mod parser;
+pub mod priority;
+mod span;
+pub mod tags;
 pub mod timestamp;
+mod timezone;
+pub mod traverse;
+mod user_input;
 
 // Using this structure for ease of storing utility functions
 #[cfg(test)]
 pub mod tests;
+// Lets the derive macros, which refer to `::orgish`, be used in the tests
+#[cfg(test)]
+extern crate self as orgish;
 
 pub use self::keyword::Keyword;
+pub use drawer::Drawers;
 pub use format::*;
 pub use parse_id::*;
 pub use parse_string::ParseString;
+pub use span::{NodeSpans, Span};
 pub use timestamp::Timestamp;
 
 use error::ParseError;
 use indexmap::IndexMap;
-use std::{collections::HashMap, convert::identity};
+use lossless::Source;
+use std::{collections::HashMap, convert::identity, sync::Arc};
 
 /// A document in some format. The document's properties and root body will be captured in the root node.
 /// This does *not* save the document's format details, and conversion into another format is
@@ -87,6 +103,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                 priority: node.priority,
                 tags: node.tags,
                 planning: node.planning,
+                drawers: node.drawers,
                 properties: Properties {
                     id: new_id,
                     inner: props.inner,
@@ -94,6 +111,8 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                 keyword: node.keyword,
                 body: node.body,
                 timestamps: node.timestamps,
+                spans: node.spans,
+                source: node.source,
                 children: node
                     .children
                     .into_iter()
@@ -130,10 +149,13 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                 priority: node.priority,
                 tags: node.tags,
                 planning: node.planning,
+                drawers: node.drawers,
                 properties: node.properties,
                 keyword: new_keyword,
                 body: node.body,
                 timestamps: node.timestamps,
+                spans: node.spans,
+                source: node.source,
                 children: node
                     .children
                     .into_iter()
@@ -147,18 +169,20 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
             attributes: self.attributes,
         }
     }
-    /// Gets the last node in the tree at a certain level. This is used in the parser to get the correct
-    /// parent for the next node at `level + 1`. This will return `None` if there are no nodes of the given
-    /// level at the latest point in the tree.
-    fn get_last_node_at_level(&mut self, level: u8) -> Option<&mut Node<K, I, S>> {
-        let mut curr_level = 0;
+    /// Gets the node a new heading at the given level should be added to, which is the last node in
+    /// the tree with a lower level. Usually, this will be at `level - 1`, but headings can skip
+    /// levels, in which case this will be the nearest node above that (or the root).
+    fn get_parent_for_level(&mut self, level: u8) -> &mut Node<K, I, S> {
         let mut curr_parent = &mut self.root;
-        while curr_level < level {
-            curr_parent = curr_parent.children.last_mut()?;
-            curr_level += 1;
+        while curr_parent
+            .children
+            .last()
+            .is_some_and(|child| child.level < level)
+        {
+            curr_parent = curr_parent.children.last_mut().unwrap();
         }
 
-        Some(curr_parent)
+        curr_parent
     }
 }
 
@@ -224,7 +248,7 @@ impl Attributes {
                         .map(|s| s.to_string())
                         .collect()
                 })
-                .unwrap_or_else(|| Vec::new())),
+                .unwrap_or_default()),
             // A non-string title is an error because otherwise we'd overwrite it with nothing wehn
             // writing back to a string
             Self::MarkdownYaml(map) => map
@@ -235,7 +259,7 @@ impl Attributes {
                         .ok_or(ParseError::RootTagsNotStringVec)
                         // ...if that worked, try to convert every element to a string
                         .map(|seq| {
-                            seq.into_iter()
+                            seq.iter()
                                 .map(|val| {
                                     val.as_str()
                                         .map(|s| s.to_string())
@@ -255,7 +279,7 @@ impl Attributes {
                     val.as_array()
                         .ok_or(ParseError::RootTagsNotStringVec)
                         .map(|arr| {
-                            arr.into_iter()
+                            arr.iter()
                                 .map(|val| {
                                     val.as_str()
                                         .map(|s| s.to_string())
@@ -296,7 +320,8 @@ pub struct Node<K: Keyword, I: ParseId = StringId, S: ParseString = String> {
     pub priority: Priority,
     /// Any tags the node has. Tag inheritance is *not* automatically implemented by this parser, and, as such,
     /// this contains only the tags defined directly on this node, not any that might exist in parent headings
-    /// or the root node.
+    /// or the root node. See [`Document::effective_tags`] and [`tags::TagConfig`] for working out the tags
+    /// which apply to a node through inheritance.
     ///
     /// Tags on documents will not appear in the root node, but in top-level attributes.
     pub tags: Tags,
@@ -305,6 +330,10 @@ pub struct Node<K: Keyword, I: ParseId = StringId, S: ParseString = String> {
     pub planning: Planning,
     /// The properties of this node. Textually, these come directly after the planning information.
     pub properties: Properties<I, S>,
+    /// Any other drawers (e.g. `:LOGBOOK:`) that come directly after the properties. Drawers that
+    /// appear later on in the body of the node will be left as part of the body, and the root
+    /// node will never have any drawers.
+    pub drawers: Drawers<S>,
     /// The keyword for the node. This will be identified if it comes before a priority, or if it is the starting
     /// word of a title and matches one of the list of todo keywords given during parsing.
     pub keyword: Option<K>,
@@ -331,6 +360,12 @@ pub struct Node<K: Keyword, I: ParseId = StringId, S: ParseString = String> {
     /// operation, they will never be less than this node's level. As such, this property is private and
     /// manipulated through a series of methods.
     children: Vec<Node<K, I, S>>,
+    /// Where this node and its components were in the source text it was parsed from. This will be
+    /// `None` for nodes that weren't created by the document parser.
+    spans: Option<NodeSpans>,
+    /// The source text of the document this node was parsed from, if it was parsed losslessly.
+    /// This is shared between all the nodes of the document.
+    source: Option<Arc<Source>>,
 }
 // Manual `Default` impl to avoid requiring a default keyword
 impl<K: Keyword, I: ParseId, S: ParseString> Default for Node<K, I, S> {
@@ -342,10 +377,13 @@ impl<K: Keyword, I: ParseId, S: ParseString> Default for Node<K, I, S> {
             tags: Tags::default(),
             planning: Planning::default(),
             properties: Properties::default(),
+            drawers: Drawers::default(),
             keyword: None,
             body: None,
             timestamps: Vec::new(),
             children: Vec::new(),
+            spans: None,
+            source: None,
         }
     }
 }
@@ -362,9 +400,12 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
             tags: Tags::default(),
             planning: Planning::default(),
             properties: Properties::default(),
+            drawers: Drawers::default(),
             children: Vec::new(),
             keyword: None,
             timestamps: Vec::new(),
+            spans: None,
+            source: None,
         }
     }
     /// Gets an immutable reference to the children of this node.
@@ -374,7 +415,9 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
     /// Gets a mutable reference to the children of this node.
     ///
     /// This should be used with extreme care, as it may lead to invalid tree structures if the
-    /// requirement that children do not have levels lower than their parent is not upheld!
+    /// requirement that children do not have levels lower than their parent is not upheld! To
+    /// modify the nodes throughout a tree without this risk, see [`Self::depth_first_mut`] and
+    /// [`Self::walk_mut`].
     pub fn unchecked_mut_children(&mut self) -> &mut Vec<Self> {
         &mut self.children
     }
@@ -388,8 +431,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
     /// This is used in some secondary parsing behaviours related to higher-order functions, but
     /// generally `.children()` or `.into_children()` should be preferred.
     pub fn take_children(&mut self) -> Vec<Self> {
-        let children = std::mem::take(&mut self.children);
-        children
+        std::mem::take(&mut self.children)
     }
     /// Sets the children of this node to the given vector, without checking them. Undefined behaviour
     /// from the parser's perspective (not actual UB, just very bad things that are undefined because we
@@ -418,6 +460,16 @@ impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
             })
         }
     }
+    /// Gets the positions of this node and its components in the source text it was parsed from.
+    /// This is useful for editor integrations that need to jump to, or patch, specific parts of a
+    /// document.
+    ///
+    /// This will be `None` for nodes that were constructed manually, or parsed individually with
+    /// [`Self::from_heading_str`]. Note that these spans are *not* updated when the node is
+    /// modified.
+    pub fn spans(&self) -> Option<&NodeSpans> {
+        self.spans.as_ref()
+    }
     /// Gets the level of this node (immutably).
     pub fn level(&self) -> u8 {
         self.level
@@ -460,37 +512,82 @@ impl Planning {
     /// Adds the given line of planning to this set of planning items. This will return `None`
     /// if the given line is not a planning line, and `Some(Err(_))` if an error occurred while
     /// parsing (especially the timestamp).
+    ///
+    /// A single line may contain any combination of planning items, as Org mode writes them (e.g.
+    /// `DEADLINE: <2023-01-01 Sun> SCHEDULED: <2022-12-25 Sun>`). If any of them fail to parse,
+    /// none of them will be added.
     pub fn add_line(&mut self, line: &str) -> Option<Result<(), ParseError>> {
-        // Only split into two parts (timestamp may contain colons)
-        let parts = line.splitn(2, ':').collect::<Vec<_>>();
-        // Format: `TITLE: <timestamp>`
-        if parts.len() != 2 {
+        self.add_line_checked(line).map(|res| res.map(|_| ()))
+    }
+    /// The same as [`Self::add_line`], except this will report whether or not the given line
+    /// overwrote a planning item that had already been set (which the parser reports as a
+    /// warning).
+    pub(crate) fn add_line_checked(&mut self, line: &str) -> Option<Result<bool, ParseError>> {
+        // Format: `KEYWORD: <timestamp>`, possibly repeated (timestamps may contain colons, so we
+        // split on the keywords instead)
+        let line = line.trim();
+        let (mut keyword, start, mut value_start) = find_planning_keyword(line)?;
+        if start != 0 {
             return None;
-        };
-
-        let key = parts[0].trim();
-        let timestamp = parts[1].trim();
-
-        // This abstracts over which property of `self` we're setting
-        let update_self = |prop: &mut Option<Timestamp>| -> Option<Result<(), ParseError>> {
-            Some(match Timestamp::from_str(timestamp) {
-                Ok(timestamp) => {
-                    *prop = Some(timestamp);
-                    Ok(())
+        }
+        let mut items = Vec::new();
+        loop {
+            let rest = &line[value_start..];
+            match find_planning_keyword(rest) {
+                Some((next_keyword, next_start, next_value_start)) => {
+                    items.push((keyword, rest[..next_start].trim()));
+                    keyword = next_keyword;
+                    value_start += next_value_start;
                 }
-                Err(err) => Err(err.into()),
-            })
-        };
+                None => {
+                    items.push((keyword, rest.trim()));
+                    break;
+                }
+            }
+        }
 
-        match key {
-            "DEADLINE" => update_self(&mut self.deadline),
-            "SCHEDULED" => update_self(&mut self.scheduled),
-            "CLOSED" => update_self(&mut self.closed),
-            _ => None,
+        // Parse everything before we set anything, so errors don't leave us half-updated
+        let mut parsed = Vec::new();
+        for (keyword, timestamp) in items {
+            match Timestamp::from_str(timestamp) {
+                Ok(timestamp) => parsed.push((keyword, timestamp)),
+                Err(err) => return Some(Err(err.into())),
+            }
         }
+        let mut repeated = false;
+        for (keyword, timestamp) in parsed {
+            let prop = match keyword {
+                "DEADLINE" => &mut self.deadline,
+                "SCHEDULED" => &mut self.scheduled,
+                _ => &mut self.closed,
+            };
+            repeated |= prop.is_some();
+            *prop = Some(timestamp);
+        }
+
+        Some(Ok(repeated))
     }
 }
 
+/// Finds the first planning keyword (followed by a colon) in the given text, returning the
+/// keyword, the byte offset it starts at, and the byte offset its value starts at.
+fn find_planning_keyword(text: &str) -> Option<(&'static str, usize, usize)> {
+    ["DEADLINE", "SCHEDULED", "CLOSED"]
+        .into_iter()
+        .filter_map(|keyword| {
+            text.match_indices(keyword).find_map(|(start, _)| {
+                let after = &text[start + keyword.len()..];
+                let spacing = after.len() - after.trim_start().len();
+                after.trim_start().starts_with(':').then_some((
+                    keyword,
+                    start,
+                    start + keyword.len() + spacing + 1,
+                ))
+            })
+        })
+        .min_by_key(|(_, start, _)| *start)
+}
+
 /// Properties of some entry in a document. This will typically apply to a heading, but it can equally
 /// apply to an entire document.
 ///
@@ -521,7 +618,7 @@ impl<I: ParseId, S: ParseString> Properties<I, S> {
 
         // If this is an ID, parse it according to the given logic
         if key == "ID" {
-            if let Some(id) = I::parse(&value) {
+            if let Some(id) = I::parse(value) {
                 self.id = id;
             } else {
                 return Err(ParseError::IdParseFailed {
@@ -0,0 +1,612 @@
+//! Logic for writing documents parsed with [`Document::from_str_lossless`] back to strings, reusing
+//! the source text of anything that hasn't changed since parsing.
+//!
+//! The approach is the same for every component of a node: the current value is written out as
+//! usual, and the original text is parsed again and written out in the same way. If the two are
+//! the same, nothing has changed, and the original text can be used instead.
+
+use crate::drawer::{Drawer, DrawerContents, LogEntry, Logbook};
+use crate::into_format::property_line;
+use crate::{
+    Attributes, Document, Drawers, Format, Keyword, Node, ParseId, ParseString, Planning,
+    Properties, Span,
+};
+use indexmap::IndexMap;
+use std::{fmt, ops::Range, sync::Arc};
+
+/// The source text of a document parsed losslessly, shared between all of its nodes.
+pub(crate) struct Source {
+    text: String,
+    format: Format,
+    layout: RootLayout,
+    /// The line terminator used in the source text.
+    newline: &'static str,
+}
+impl Source {
+    /// Creates a new record of the given source text.
+    pub(crate) fn new(text: &str, format: Format, layout: RootLayout) -> Self {
+        Self {
+            text: text.to_string(),
+            format,
+            layout,
+            newline: if text.contains("\r\n") { "\r\n" } else { "\n" },
+        }
+    }
+    /// Gets the format the source text was parsed as.
+    pub(crate) fn format(&self) -> Format {
+        self.format
+    }
+    /// Gets the text covered by the given span.
+    fn slice(&self, span: &Span) -> &str {
+        &self.text[span.bytes.clone()]
+    }
+}
+// The whole source text would make the debug output of every node unreadable
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Source")
+            .field("format", &self.format)
+            .field("len", &self.text.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Details of the layout of the root node of a document that aren't covered by its spans. All
+/// lines are zero-indexed.
+#[derive(Default)]
+pub(crate) struct RootLayout {
+    /// The lines of any Org attributes (e.g. `#+title`), which may be interspersed with the body.
+    pub(crate) attribute_lines: Vec<usize>,
+    /// The lines of the Markdown frontmatter block, including its delimiters.
+    pub(crate) frontmatter: Option<Range<usize>>,
+    /// The line of the first heading in the document, if there is one.
+    pub(crate) first_heading: Option<usize>,
+}
+
+impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
+    /// Writes this document to a string, reusing whatever parts of the given source text haven't
+    /// changed. The title and tags must already have been implanted in the attributes.
+    pub(crate) fn into_lossless_string(self, source: &Source) -> String {
+        let Self {
+            mut root,
+            attributes,
+        } = self;
+        // Guaranteed by the caller
+        let spans = root.spans.take().unwrap();
+        let format = source.format;
+        let layout = &source.layout;
+        let lines = source.text.lines().collect::<Vec<_>>();
+        let root_end = layout.first_heading.unwrap_or(lines.len());
+        let in_span =
+            |span: &Option<Span>, i: usize| span.as_ref().is_some_and(|s| s.lines.contains(&i));
+
+        // Work out the new attributes: Org attributes are handled line-by-line, but frontmatter
+        // is either kept or rewritten wholesale
+        let (mut attribute_lines, mut new_attribute_lines, frontmatter) = match format {
+            Format::Org => {
+                let original_lines = layout
+                    .attribute_lines
+                    .iter()
+                    .map(|i| lines[*i])
+                    .collect::<Vec<_>>();
+                let (attribute_lines, new_attribute_lines) =
+                    org_attribute_lines(&original_lines, attributes);
+                (attribute_lines, new_attribute_lines, String::new())
+            }
+            Format::Markdown => {
+                let original = layout
+                    .frontmatter
+                    .as_ref()
+                    .map(|frontmatter_lines| lines[frontmatter_lines.clone()].join(source.newline));
+                let frontmatter = original_if_unchanged(
+                    original.as_deref(),
+                    attributes.into_string(format),
+                    canonical_frontmatter,
+                );
+                (Vec::new(), Vec::new(), frontmatter)
+            }
+        };
+        let properties = match &spans.properties {
+            Some(span) => {
+                root.properties
+                    .into_lossless_string(source.slice(span), format, source.newline)
+            }
+            None => root.properties.into_string(format),
+        };
+        // The body of an Org root can be interspersed with attributes, so we have to work out
+        // which lines were actually part of it. If it hasn't changed, we'll leave it as `None`,
+        // otherwise this will be `Some(new_body)`.
+        let original_body = spans.body.as_ref().map(|span| {
+            span.lines
+                .clone()
+                .filter(|i| !layout.attribute_lines.contains(i))
+                .map(|i| lines[i])
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
+        let body = root.body.take().map(|body| body.to_string(format));
+        let body_unchanged = match (&original_body, &body) {
+            (Some(original), Some(current)) => {
+                canonical_body::<S>(original, format).as_ref() == Some(current)
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        let mut new_body = if body_unchanged { None } else { body };
+
+        let mut parts = Vec::new();
+        // Where we should put properties or attributes that weren't in the source (the new parts
+        // will be placed in the same positions they would be by the regular writer)
+        let mut after_frontmatter = 0;
+        let mut after_properties = 0;
+        for (i, line) in lines[..root_end].iter().enumerate() {
+            if let Some(frontmatter_lines) = &layout.frontmatter {
+                if frontmatter_lines.contains(&i) {
+                    if i == frontmatter_lines.start && !frontmatter.is_empty() {
+                        parts.push(frontmatter.clone());
+                    }
+                    after_frontmatter = parts.len();
+                    continue;
+                }
+            }
+
+            if in_span(&spans.properties, i) {
+                if spans.properties.as_ref().unwrap().lines.start == i && !properties.is_empty() {
+                    parts.push(properties.clone());
+                }
+                after_properties = parts.len();
+            } else if let Some(idx) = layout.attribute_lines.iter().position(|l| *l == i) {
+                if let Some(line) = attribute_lines[idx].take() {
+                    parts.push(line);
+                }
+                // Any new attributes go after the last existing one
+                if idx == layout.attribute_lines.len() - 1 {
+                    parts.append(&mut new_attribute_lines);
+                }
+            } else if in_span(&spans.body, i) {
+                if body_unchanged {
+                    parts.push(line.to_string());
+                } else if let Some(body) = new_body.take() {
+                    // Written in place of the first line of the old body
+                    parts.push(body);
+                }
+            } else {
+                // This is something the parser skipped over, like blank lines before properties
+                parts.push(line.to_string());
+            }
+        }
+        // Now add anything that wasn't in the source at all
+        if let Some(body) = new_body {
+            parts.push(body);
+        }
+        if !new_attribute_lines.is_empty() {
+            parts.splice(after_properties..after_properties, new_attribute_lines);
+        }
+        if spans.properties.is_none() && !properties.is_empty() {
+            match format {
+                Format::Org => parts.insert(0, properties),
+                Format::Markdown => parts.insert(after_frontmatter, properties),
+            }
+        }
+        if layout.frontmatter.is_none() && !frontmatter.is_empty() {
+            parts.insert(0, frontmatter);
+        }
+
+        for child in root.children {
+            parts.push(child.into_string(format));
+        }
+
+        let mut document_str = parts.join(source.newline);
+        if source.text.ends_with('\n') {
+            document_str.push_str(source.newline);
+        }
+        document_str
+    }
+}
+
+impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
+    /// Gives this node and all its descendants a reference to the given source text, so that they
+    /// can be written back losslessly.
+    pub(crate) fn set_source(&mut self, source: &Arc<Source>) {
+        self.source = Some(source.clone());
+        for child in &mut self.children {
+            child.set_source(source);
+        }
+    }
+    /// Writes this (non-root) node to a string, reusing whatever parts of the given source text
+    /// haven't changed. This node must have spans.
+    pub(crate) fn into_lossless_string(mut self, source: &Source) -> String {
+        let spans = self.spans.take().unwrap();
+        let format = source.format;
+        let slice = |span: &Option<Span>| span.as_ref().map(|span| source.slice(span));
+        let mut node_parts = Vec::new();
+
+        let heading = self.take_heading_string(format);
+        node_parts.push(original_if_unchanged(
+            slice(&spans.heading),
+            heading,
+            |original| match Self::from_heading_str(original, format) {
+                Some(Ok(mut node)) => Some(node.take_heading_string(format)),
+                _ => None,
+            },
+        ));
+
+        let planning = std::mem::take(&mut self.planning).into_string();
+        let planning = original_if_unchanged(slice(&spans.planning), planning, |original| {
+            let mut planning = Planning::default();
+            for line in source_lines(original) {
+                planning.add_line(line)?.ok()?;
+            }
+            Some(planning.into_string())
+        });
+        if !planning.is_empty() {
+            node_parts.push(planning);
+        }
+
+        let properties = std::mem::take(&mut self.properties);
+        let properties = match slice(&spans.properties) {
+            Some(original) => properties.into_lossless_string(original, format, source.newline),
+            None => properties.into_string(format),
+        };
+        if !properties.is_empty() {
+            node_parts.push(properties);
+        }
+
+        let drawers = std::mem::take(&mut self.drawers);
+        let drawers = match slice(&spans.drawers) {
+            Some(original) => drawers.into_lossless_string(original, format, source.newline),
+            None => drawers.into_string(format),
+        };
+        if !drawers.is_empty() {
+            node_parts.push(drawers);
+        }
+
+        if let Some(body) = self.body.take() {
+            node_parts.push(original_if_unchanged(
+                slice(&spans.body),
+                body.to_string(format),
+                |original| canonical_body::<S>(original, format),
+            ));
+        }
+
+        for child in self.children {
+            node_parts.push(child.into_string(format));
+        }
+
+        node_parts.join(source.newline)
+    }
+}
+
+impl<I: ParseId, S: ParseString> Properties<I, S> {
+    /// Writes these properties to a drawer, based on the given original drawer. Properties that
+    /// haven't changed will keep their original lines, changed ones will be rewritten in place,
+    /// and new ones will be added at the end (except the ID, which will go at the start, as in
+    /// [`Self::into_string`]).
+    fn into_lossless_string(self, original: &str, format: Format, newline: &str) -> String {
+        let Self { id, mut inner } = self;
+        if id.is_none() && inner.is_empty() {
+            // A drawer that never had any properties in it (e.g. an empty or unclosed one) is
+            // kept as it was, but one that's had all its properties removed is dropped
+            let had_properties = source_lines(original)
+                .skip(1)
+                .filter(|line| line.trim() != format.get_properties_closer())
+                .any(|line| split_property(line).is_some());
+            return if had_properties {
+                String::new()
+            } else {
+                original.to_string()
+            };
+        }
+        let mut id = id.is_some().then(|| id.into_string());
+
+        let mut original_lines = source_lines(original).collect::<Vec<_>>();
+        // The opener is always there, but the drawer might never have been closed
+        let opener = original_lines.remove(0);
+        let closer = if original_lines
+            .last()
+            .is_some_and(|line| line.trim() == format.get_properties_closer())
+        {
+            original_lines.pop()
+        } else {
+            None
+        };
+
+        let mut lines = Vec::new();
+        for line in original_lines {
+            // Blank lines are kept as they are
+            let Some((key, value)) = split_property(line) else {
+                lines.push(line.to_string());
+                continue;
+            };
+            // Properties that have been removed will just be skipped
+            let current = if key == "ID" {
+                id.take()
+            } else {
+                inner.remove(key).map(|value| value.to_string(format))
+            };
+            if let Some(current) = current {
+                let unchanged = if key == "ID" {
+                    value == current
+                } else {
+                    canonical_property::<S>(value, format).as_ref() == Some(&current)
+                };
+                if unchanged {
+                    lines.push(line.to_string());
+                } else {
+                    // Keep everything up to and including the colon after the key
+                    let key_end = line.find(key).unwrap() + key.len();
+                    lines.push(format!("{} {current}", &line[..=key_end]));
+                }
+            }
+        }
+        if let Some(id) = id {
+            lines.insert(0, property_line("ID", &id, format));
+        }
+        let mut keys = inner.keys().cloned().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            let value = inner.remove(&key).unwrap();
+            lines.push(property_line(&key, &value.to_string(format), format));
+        }
+
+        std::iter::once(opener.to_string())
+            .chain(lines)
+            .chain(closer.map(|closer| closer.to_string()))
+            .collect::<Vec<_>>()
+            .join(newline)
+    }
+}
+
+impl<S: ParseString> Drawers<S> {
+    /// Writes these drawers to a string, based on the given original drawers. Each drawer is
+    /// matched up with the first original drawer of the same name that hasn't already been used,
+    /// and written based on that (drawers without a match are written normally).
+    fn into_lossless_string(mut self, original: &str, format: Format, newline: &str) -> String {
+        let mut originals = OriginalDrawer::split(original, format)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+
+        std::mem::take(&mut *self)
+            .into_iter()
+            .map(|drawer| {
+                let original = originals.iter_mut().find(|original| {
+                    original
+                        .as_ref()
+                        .is_some_and(|original| original.name.eq_ignore_ascii_case(&drawer.name))
+                });
+                match original.and_then(Option::take) {
+                    Some(original) => drawer.into_lossless_string(original, format, newline),
+                    None => drawer.into_string(format),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(newline)
+    }
+}
+
+impl<S: ParseString> Drawer<S> {
+    /// Writes this drawer to a string, based on the given original drawer. Logbook entries are
+    /// matched up with original entries individually, so adding a new entry won't change how any
+    /// of the others are written.
+    fn into_lossless_string(
+        self,
+        original: OriginalDrawer,
+        format: Format,
+        newline: &str,
+    ) -> String {
+        let opener = if original.name == self.name {
+            original.opener.to_string()
+        } else {
+            format.get_drawer_opener(&self.name)
+        };
+        let contents = match self.contents {
+            DrawerContents::Logbook(logbook) => {
+                let groups = Logbook::group_lines(&original.contents);
+                // New entries should be indented like the existing ones (or like the drawer, if
+                // there aren't any yet)
+                let first_line = groups.first().map_or(original.opener, |lines| lines[0]);
+                let indent = &first_line[..first_line.len() - first_line.trim_start().len()];
+                let mut original_entries = groups
+                    .into_iter()
+                    .map(|lines| Some((LogEntry::from_lines(&lines), lines.join(newline))))
+                    .collect::<Vec<_>>();
+                logbook
+                    .iter()
+                    .cloned()
+                    .map(|entry| {
+                        let original = original_entries.iter_mut().find(|original| {
+                            original
+                                .as_ref()
+                                .is_some_and(|(original, _)| *original == entry)
+                        });
+                        match original.and_then(Option::take) {
+                            Some((_, text)) => text,
+                            None => entry
+                                .into_string()
+                                .lines()
+                                .map(|line| format!("{indent}{line}"))
+                                .collect::<Vec<_>>()
+                                .join(newline),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(newline)
+            }
+            DrawerContents::Text(text) => {
+                let original_text = original.contents.join(newline);
+                original_if_unchanged(Some(&original_text), text.to_string(format), |original| {
+                    canonical_body::<S>(original, format)
+                })
+            }
+        };
+
+        let mut parts = vec![opener];
+        if !contents.is_empty() {
+            parts.push(contents);
+        }
+        parts.push(original.closer.to_string());
+        parts.join(newline)
+    }
+}
+
+/// A drawer, as it was written in the source text.
+struct OriginalDrawer<'s> {
+    name: &'s str,
+    opener: &'s str,
+    contents: Vec<&'s str>,
+    closer: &'s str,
+}
+impl<'s> OriginalDrawer<'s> {
+    /// Splits the given source text, which should consist only of complete drawers (as the parser
+    /// guarantees), into its drawers.
+    fn split(text: &'s str, format: Format) -> Vec<Self> {
+        let mut drawers = Vec::new();
+        let mut lines = source_lines(text);
+        while let Some(opener) = lines.next() {
+            let Some(name) = format.get_drawer_name(opener.trim()) else {
+                continue;
+            };
+            let mut contents = Vec::new();
+            let mut closer = format.get_drawer_closer();
+            for line in lines.by_ref() {
+                if line.trim().eq_ignore_ascii_case(format.get_drawer_closer()) {
+                    closer = line;
+                    break;
+                }
+                contents.push(line);
+            }
+            drawers.push(Self {
+                name,
+                opener,
+                contents,
+                closer,
+            });
+        }
+
+        drawers
+    }
+}
+
+/// Returns the original text of some component if it's equivalent to the current text, as
+/// determined by the given function, which should parse the original text and write it back out
+/// in the same way the current text was written. If there was no original text, or it can't be
+/// parsed anymore, the current text will be returned.
+fn original_if_unchanged(
+    original: Option<&str>,
+    current: String,
+    canonicalise: impl FnOnce(&str) -> Option<String>,
+) -> String {
+    match original {
+        Some(original) if canonicalise(original).as_ref() == Some(&current) => original.to_string(),
+        _ => current,
+    }
+}
+
+/// Splits the given source text into its lines, exactly as the parser would, including a trailing
+/// empty line if the text ends with a newline (spans never include the final line terminator, so
+/// this only occurs if there's a genuinely empty last line).
+fn source_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+}
+
+/// Writes the given body text as the regular writer would.
+fn canonical_body<S: ParseString>(original: &str, format: Format) -> Option<String> {
+    let body = source_lines(original).collect::<Vec<_>>().join("\n");
+    S::from_str(body, format)
+        .ok()
+        .map(|body| body.to_string(format))
+}
+
+/// Writes the given property value as the regular writer would.
+fn canonical_property<S: ParseString>(original: &str, format: Format) -> Option<String> {
+    S::from_str(original.to_string(), format)
+        .ok()
+        .map(|value| value.to_string(format))
+}
+
+/// Writes the given frontmatter block (including its delimiters) as the regular writer would.
+fn canonical_frontmatter(original: &str) -> Option<String> {
+    let lines = source_lines(original).collect::<Vec<_>>();
+    let contents = lines[1..lines.len() - 1].join("\n");
+    let attributes = if lines[0] == "---" {
+        Attributes::MarkdownYaml(serde_yaml::from_str(&contents).ok()?)
+    } else {
+        Attributes::MarkdownToml(toml::from_str(&contents).ok()?)
+    };
+    Some(attributes.into_string(Format::Markdown))
+}
+
+/// Splits a line in a property drawer into its key and value, as the parser would.
+fn split_property(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim();
+    let (key, value) = trimmed
+        .strip_prefix(':')
+        .unwrap_or(trimmed)
+        .split_once(':')?;
+    Some((key.trim(), value.trim()))
+}
+
+/// Works out how the given original Org attribute lines should be written to reflect the given
+/// attributes. This returns the new version of each original line (or `None` if it should be
+/// removed), and any new lines that should be added after them.
+///
+/// Attributes that appear several times (e.g. `#+todo`) will be kept as they are if their last
+/// value, which is the one the parser records, hasn't changed.
+fn org_attribute_lines(
+    original_lines: &[&str],
+    attributes: Attributes,
+) -> (Vec<Option<String>>, Vec<String>) {
+    let mut original = IndexMap::new();
+    for line in original_lines {
+        let (key, value) = parse_attribute_line(line);
+        original.insert(key.to_lowercase(), value);
+    }
+    let mut current = match attributes {
+        Attributes::Org(map) => map,
+        // The attributes have been replaced with Markdown ones, so we'll work from their
+        // stringified Org versions
+        attributes => attributes
+            .into_string(Format::Org)
+            .lines()
+            .map(|line| {
+                let (key, value) = parse_attribute_line(line);
+                (key.to_lowercase(), value.to_string())
+            })
+            .collect(),
+    };
+
+    let mut lines = Vec::new();
+    for (idx, line) in original_lines.iter().enumerate() {
+        let (key, _) = parse_attribute_line(line);
+        let lower_key = key.to_lowercase();
+        let is_last = !original_lines[idx + 1..]
+            .iter()
+            .any(|later| parse_attribute_line(later).0.to_lowercase() == lower_key);
+        lines.push(match current.get(&lower_key) {
+            Some(value) if original.get(&lower_key) == Some(&value.as_str()) => {
+                Some(line.to_string())
+            }
+            // Changed attributes are rewritten where they were last defined
+            Some(value) if is_last => Some(format!("#+{key}: {value}")),
+            _ => None,
+        });
+    }
+    let new_lines = current
+        .drain(..)
+        .filter(|(key, _)| !original.contains_key(key))
+        .map(|(key, value)| format!("#+{key}: {value}"))
+        .collect();
+
+    (lines, new_lines)
+}
+
+/// Splits an Org attribute line into its key (as written) and value.
+fn parse_attribute_line(line: &str) -> (&str, &str) {
+    let line = line.strip_prefix("#+").unwrap_or(line);
+    match line.split_once(':') {
+        Some((key, value)) => (key, value.trim()),
+        None => (line, ""),
+    }
+}
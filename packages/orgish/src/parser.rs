@@ -2,25 +2,88 @@
 
 use super::{Document, Keyword, Node, Tags};
 use super::{ParseError, ParseId};
+use crate::error::{Diagnostic, Severity};
 use crate::format::Format;
+use crate::lossless::{RootLayout, Source};
+use crate::span::{LineIndex, NodeSpans};
 use crate::{Attributes, ParseString};
 use indexmap::IndexMap;
-use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::ops::Range;
+use std::sync::Arc;
 
 impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
-    /// Parses a document from its string representation.
+    /// Parses a document from its string representation. This will fail on the first error
+    /// encountered, see [`Self::from_str_with_diagnostics`] for a more lenient alternative.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(raw_contents: &str, format: Format) -> Result<Self, ParseError> {
+        Self::parse(raw_contents, format, &mut Strict).map(|(document, _)| document)
+    }
+    /// Parses a document from its string representation, like [`Self::from_str`], but keeps the
+    /// source text around so that, when the document is written back to a string in the same
+    /// format, anything that hasn't been changed will be written exactly as it was. This covers
+    /// the order and spacing of attributes and properties, the spelling of timestamps, and
+    /// anything else the regular writer would normalise. Only the parts of the document that have
+    /// actually been changed will be rewritten (and individual properties and Org attributes will
+    /// be rewritten line-by-line).
+    ///
+    /// Every node in the document will hold a shared reference to the source text, so it will be
+    /// kept in memory as long as any of them are.
+    pub fn from_str_lossless(raw_contents: &str, format: Format) -> Result<Self, ParseError> {
+        let (mut document, layout) = Self::parse(raw_contents, format, &mut Strict)?;
+        document
+            .root
+            .set_source(&Arc::new(Source::new(raw_contents, format, layout)));
+
+        Ok(document)
+    }
+    /// Parses a document from its string representation, collecting any problems found along the
+    /// way, rather than failing on the first one. This will always produce a best-effort
+    /// document: lines that can't be parsed will be dropped, or parsed as something simpler (e.g.
+    /// an invalid planning line will become part of the body). See [`Diagnostic`] for the details
+    /// recorded about each problem.
+    ///
+    /// Unlike [`Self::from_str`], this will also produce warnings for things that parse, but
+    /// probably not as intended, like property drawers that are never closed.
+    pub fn from_str_with_diagnostics(
+        raw_contents: &str,
+        format: Format,
+    ) -> (Self, Vec<Diagnostic>) {
+        let mut diagnostics = Collecting(Vec::new());
+        // Every error is recorded rather than returned in this mode
+        let Ok((document, _)) = Self::parse(raw_contents, format, &mut diagnostics);
+
+        (document, diagnostics.0)
+    }
+    /// The actual parsing logic. Errors are handled through the given [`Diagnostics`], which will
+    /// determine whether or not they should stop the parsing process.
+    ///
+    /// This also returns some details of the layout of the root node, which are needed to write
+    /// the document back losslessly.
+    fn parse<D: Diagnostics>(
+        raw_contents: &str,
+        format: Format,
+        diagnostics: &mut D,
+    ) -> Result<(Self, RootLayout), D::Error> {
         let mut document = Document::<K, I, S>::default();
         let mut document_attributes = String::new();
+        // The line the attributes started on, if there were any
+        let mut attributes_line: Option<usize> = None;
         let mut parsed_attributes: Option<Attributes> = None;
+        let mut layout = RootLayout::default();
         // This will track the active node (*not* used for the root node!)
         let mut curr_node = Node::<K, I, S>::default();
         let mut curr_parent = &mut document.root;
         // We add lines in the body to a vector to simplify newline management
         let mut curr_body: Vec<&str> = Vec::new();
+        // The lines each component of the current node (or the root) was found on
+        let mut curr_lines = ComponentLines::default();
 
         // Joins the body and handles special cases
-        let finish_body = |curr_body: &mut Vec<&str>, body: &mut Option<S>| {
+        let finish_body = |curr_body: &mut Vec<&str>,
+                           body: &mut Option<S>,
+                           body_lines: &Option<Range<usize>>,
+                           diagnostics: &mut D| {
             // Problem: a single empty newline in the body and a completely empty (i.e.
             // nonexistent) body are difficult to represent. We have a vector of lines
             // in `curr_body`, and an empty vector will produce the same output as a
@@ -30,14 +93,24 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
             *body = if curr_body.is_empty() {
                 None
             } else {
-                Some(S::from_str(curr_body.join("\n"), format).map_err(|source| {
-                    ParseError::ParseStringFailed {
-                        source: Box::new(source),
+                match S::from_str(curr_body.join("\n"), format) {
+                    Ok(parsed) => Some(parsed),
+                    // If we're collecting diagnostics, the body will be dropped
+                    Err(source) => {
+                        diagnostics.error(
+                            ParseError::ParseStringFailed {
+                                source: Box::new(source),
+                            },
+                            body_lines.as_ref().map_or(0, |lines| lines.start),
+                            0,
+                            &curr_body.join("\n"),
+                        )?;
+                        None
                     }
-                })?)
+                }
             };
             *curr_body = Vec::new();
-            Ok::<(), ParseError>(())
+            Ok::<(), D::Error>(())
         };
 
         let mut loc = match format {
@@ -48,47 +121,78 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
         };
 
         // NOTE: This will strip a final newline if it appears, which may lead to strange behaviour
-        let lines = raw_contents.lines().collect::<Vec<_>>();
+        let index = LineIndex::new(raw_contents);
+        let lines = &index.lines;
+        // Lines inside code blocks, drawers, etc. can never be headings (or attributes)
+        let in_block = find_block_lines(lines, format);
         let mut i = 0;
         while i < lines.len() {
             let line = lines[i];
 
-            // Regardless of where we are, parsing a new node is the same (and should break the current parsing cycle)
-            if let Some(new_node) = Node::<K, I, S>::from_heading_str(line, format) {
+            // Regardless of where we are, parsing a new node is the same (and should break the
+            // current parsing cycle). If we're collecting diagnostics, headings we can't parse
+            // will be treated as if they weren't headings at all.
+            let new_node = match Node::<K, I, S>::from_heading_str(line, format) {
+                _ if in_block[i] => None,
+                Some(Ok(new_node)) => Some(new_node),
+                Some(Err(err)) => {
+                    diagnostics.error(err, i, 0, line)?;
+                    None
+                }
+                None => None,
+            };
+            if let Some(new_node) = new_node {
+                check_properties_closed(&loc, &curr_lines.properties, lines, diagnostics);
                 if loc.is_start() {
+                    layout.first_heading = Some(i);
                     // After we finish with the root node, we should just initialise `curr_node` properly, because we've
                     // been working on `curr_parent`
-                    curr_node = new_node?;
+                    curr_node = new_node;
                     // Handle the body
-                    finish_body(&mut curr_body, &mut curr_parent.body)?;
+                    finish_body(
+                        &mut curr_body,
+                        &mut curr_parent.body,
+                        &curr_lines.body,
+                        diagnostics,
+                    )?;
+                    curr_parent.spans = Some(std::mem::take(&mut curr_lines).into_spans(&index));
                 } else {
-                    let new_node = new_node?;
                     // Handle the body
-                    finish_body(&mut curr_body, &mut curr_node.body)?;
+                    finish_body(
+                        &mut curr_body,
+                        &mut curr_node.body,
+                        &curr_lines.body,
+                        diagnostics,
+                    )?;
+                    curr_node.spans = Some(std::mem::take(&mut curr_lines).into_spans(&index));
 
                     let new_level = new_node.level;
-                    let curr_level = curr_node.level;
 
                     // Extract the node we've finished with and add it to its parent
                     let last_node = std::mem::replace(&mut curr_node, new_node);
                     // This will now be a mutable reference, and the child will be in the list of children
-                    curr_parent.add_child(last_node)?;
-
-                    // Update the parent
-                    curr_parent = match new_level.cmp(&curr_level) {
-                        // We need to search upward to find another parent (we know one must exist)
-                        // NOTE: This wouldn't work for `Ordering::Greater` because we don't have anything at that
-                        // level yet
-                        Ordering::Less => document.get_last_node_at_level(new_level - 1).unwrap(),
-                        // The current node should become the new parent (this will return the current node)
-                        // BUG: This panics when we have child headings with no parents?
-                        Ordering::Greater => document.get_last_node_at_level(curr_level).unwrap(),
-                        // The parent stays the same, we don't have to do anything
-                        Ordering::Equal => curr_parent,
-                    };
+                    if let Err(err) = curr_parent.add_child(last_node) {
+                        diagnostics.error(err, i, 0, line)?;
+                    }
+
+                    // Update the parent, which is the last node above this one in the tree (if
+                    // this heading skips a level, that might not be at the level directly above)
+                    curr_parent = document.get_parent_for_level(new_level);
+                }
+                if curr_parent.level + 1 < curr_node.level {
+                    diagnostics.warning(
+                        ParseError::SkippedLevel {
+                            parent_level: curr_parent.level,
+                            level: curr_node.level,
+                        },
+                        i,
+                        0,
+                        line,
+                    );
                 }
 
                 // Skip the rest of the parsing and move onto the planning lines
+                curr_lines.heading = Some(i..i + 1);
                 loc = ParseLocation::Planning;
                 i += 1;
                 continue;
@@ -101,6 +205,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                     // We look for properties first in Org
                     OrgStartLocation::Beginning => {
                         if trimmed_line == format.get_properties_opener() {
+                            extend(&mut curr_lines.properties, i);
                             *start_loc = OrgStartLocation::Properties;
                         } else if !trimmed_line.is_empty() {
                             *start_loc = OrgStartLocation::Content;
@@ -110,12 +215,21 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                         // NOTE: Empty lines at the start of a document are skipped
                     }
                     OrgStartLocation::Properties => {
+                        extend(&mut curr_lines.properties, i);
                         if trimmed_line == format.get_properties_closer() {
                             // Attributes are line-marked and after the properties
                             *start_loc = OrgStartLocation::Content;
                         } else if !trimmed_line.is_empty() {
-                            // Parse this property
-                            curr_parent.properties.add_line(trimmed_line, format)?;
+                            // Parse this property (invalid ones will be skipped)
+                            if let Err(err) = curr_parent.properties.add_line(trimmed_line, format)
+                            {
+                                diagnostics.error(
+                                    err,
+                                    i,
+                                    column_of(line, trimmed_line),
+                                    trimmed_line,
+                                )?;
+                            }
                         }
                     }
                     // Special attribute checking is done on the *untrimmed* lines because
@@ -124,16 +238,20 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                     // back to a string? Also need to parse these into a `IndexMap` at some
                     // stage...
                     OrgStartLocation::Content => {
-                        if line.starts_with("#+") {
+                        // Block delimiters (e.g. `#+begin_src`) are part of the body
+                        if line.starts_with("#+") && !in_block[i] {
                             // Only push a newline if there was something beforehand (avoids
                             // spacing issues). We parse these at the end.
                             if !document_attributes.is_empty() {
                                 document_attributes.push('\n');
                             }
                             document_attributes.push_str(line);
+                            attributes_line.get_or_insert(i);
+                            layout.attribute_lines.push(i);
                         } else {
                             // Obviously we want to preserve spacing here
                             curr_body.push(line);
+                            extend(&mut curr_lines.body, i);
                         }
                     }
                 },
@@ -152,11 +270,14 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                         have_seen_frontmatter,
                     } => {
                         if (line == "---" || line == "+++") && !*have_seen_frontmatter {
+                            attributes_line = Some(i);
                             *start_loc = MarkdownStartLocation::Frontmatter;
                         } else if trimmed_line == format.get_properties_opener() {
                             // Nullify any newlines that might have been recorded between the
                             // frontmatter and the properties, or before the properties (see below)
                             curr_body = Vec::new();
+                            curr_lines.body = None;
+                            extend(&mut curr_lines.properties, i);
                             *start_loc = MarkdownStartLocation::Properties;
                         } else if !trimmed_line.is_empty() {
                             *start_loc = MarkdownStartLocation::Content;
@@ -169,6 +290,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                             // which we do care about. We'll record this as the latter, and if we
                             // reach the properties, we'll just nullify it.
                             curr_body.push("");
+                            extend(&mut curr_lines.body, i);
                         }
 
                         // NOTE: Spaces at the start of the document, and between the frontmatter
@@ -184,20 +306,34 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                             *start_loc = MarkdownStartLocation::Beginning {
                                 have_seen_frontmatter: true,
                             };
+                            layout.frontmatter = attributes_line.map(|start| start..i + 1);
 
-                            // Now parse the frontmatter we have
-                            if line == "---" {
-                                parsed_attributes = Some(Attributes::MarkdownYaml(
-                                    serde_yaml::from_str(&document_attributes).map_err(
-                                        |source| ParseError::YamlFrontmatterParseFailed { source },
-                                    )?,
-                                ));
+                            // Now parse the frontmatter we have (if we're collecting diagnostics,
+                            // invalid frontmatter will be dropped)
+                            let parse_res = if line == "---" {
+                                serde_yaml::from_str(&document_attributes)
+                                    .map(Attributes::MarkdownYaml)
+                                    .map_err(|source| ParseError::YamlFrontmatterParseFailed {
+                                        source,
+                                    })
                             } else {
-                                parsed_attributes = Some(Attributes::MarkdownToml(
-                                    toml::from_str(&document_attributes).map_err(|source| {
-                                        ParseError::TomlFrontmatterParseFailed { source }
-                                    })?,
-                                ));
+                                toml::from_str(&document_attributes)
+                                    .map(Attributes::MarkdownToml)
+                                    .map_err(|source| ParseError::TomlFrontmatterParseFailed {
+                                        source,
+                                    })
+                            };
+                            match parse_res {
+                                Ok(attributes) => parsed_attributes = Some(attributes),
+                                Err(err) => {
+                                    diagnostics.error(
+                                        err,
+                                        attributes_line.unwrap_or(0),
+                                        0,
+                                        &document_attributes,
+                                    )?;
+                                    document_attributes.clear();
+                                }
                             }
                         } else {
                             if !document_attributes.is_empty() {
@@ -207,27 +343,64 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                         }
                     }
                     MarkdownStartLocation::Properties => {
+                        extend(&mut curr_lines.properties, i);
                         if trimmed_line == format.get_properties_closer() {
                             // Frontmatter must come before the properties, so we've reached the
                             // end
                             *start_loc = MarkdownStartLocation::Content;
                         } else if !trimmed_line.is_empty() {
-                            // Parse this property
-                            curr_parent.properties.add_line(trimmed_line, format)?;
+                            // Parse this property (invalid ones will be skipped)
+                            if let Err(err) = curr_parent.properties.add_line(trimmed_line, format)
+                            {
+                                diagnostics.error(
+                                    err,
+                                    i,
+                                    column_of(line, trimmed_line),
+                                    trimmed_line,
+                                )?;
+                            }
                         }
                     }
                     // There are no attributes to check, or anything else
-                    MarkdownStartLocation::Content => curr_body.push(line),
+                    MarkdownStartLocation::Content => {
+                        curr_body.push(line);
+                        extend(&mut curr_lines.body, i);
+                    }
                 },
                 // Planning items directly underneath a heading
                 ParseLocation::Planning => {
                     if trimmed_line == format.get_properties_opener() {
                         // Move on to the properties, planning lines are definitely finished
+                        extend(&mut curr_lines.properties, i);
                         loc = ParseLocation::Properties
-                    } else if let Some(res) = curr_node.planning.add_line(line) {
-                        let _ = res?;
-                        // If we got here, the planning line has been parsed without errors, so we can
-                        // happily move on
+                    } else if let Some(res) = curr_node.planning.add_line_checked(line) {
+                        match res {
+                            // If we got here, the planning line has been parsed without errors, so we can
+                            // happily move on
+                            Ok(repeated) => {
+                                if repeated {
+                                    diagnostics.warning(
+                                        ParseError::PlanningRepeat {
+                                            line: line.to_string(),
+                                        },
+                                        i,
+                                        column_of(line, trimmed_line),
+                                        trimmed_line,
+                                    );
+                                }
+                                extend(&mut curr_lines.planning, i);
+                            }
+                            // If we're collecting diagnostics, treat an invalid planning line as
+                            // the start of the body instead
+                            Err(err) => {
+                                let timestamp = line
+                                    .split_once(':')
+                                    .map_or(trimmed_line, |(_, timestamp)| timestamp.trim());
+                                diagnostics.error(err, i, column_of(line, timestamp), timestamp)?;
+                                loc = ParseLocation::Body;
+                                continue;
+                            }
+                        }
                     } else {
                         // We got something that wasn't a planning line, and we aren't going into
                         // the properties, so we'll start parsing the body of the node from now
@@ -238,16 +411,46 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
                 }
                 // Properties that come after planning
                 ParseLocation::Properties => {
+                    extend(&mut curr_lines.properties, i);
                     if trimmed_line == format.get_properties_closer() {
                         loc = ParseLocation::Body;
                     } else if !trimmed_line.is_empty() {
-                        // Parse this property
-                        curr_node.properties.add_line(trimmed_line, format)?;
+                        // Parse this property (invalid ones will be skipped)
+                        if let Err(err) = curr_node.properties.add_line(trimmed_line, format) {
+                            diagnostics.error(
+                                err,
+                                i,
+                                column_of(line, trimmed_line),
+                                trimmed_line,
+                            )?;
+                        }
                     }
                 }
                 // The body of a non-root node (detection of new nodes happens above, so this
-                // is trivial)
-                ParseLocation::Body => curr_body.push(line),
+                // is trivial, except for drawers that come before anything else)
+                ParseLocation::Body => {
+                    let drawer_end = if curr_body.is_empty() {
+                        drawer_end(lines, i, format)
+                    } else {
+                        None
+                    };
+                    if let Some(end) = drawer_end {
+                        let name = format.get_drawer_name(trimmed_line).unwrap();
+                        if let Err(err) =
+                            curr_node
+                                .drawers
+                                .add_drawer(name, &lines[i + 1..end - 1], format)
+                        {
+                            diagnostics.error(err, i, 0, line)?;
+                        }
+                        curr_lines.drawers.get_or_insert(i..end).end = end;
+                        i = end;
+                        continue;
+                    }
+
+                    curr_body.push(line);
+                    extend(&mut curr_lines.body, i);
+                }
             }
 
             // Manual incrementation so we can jump
@@ -255,6 +458,7 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
         }
 
         // Finalise the body
+        check_properties_closed(&loc, &curr_lines.properties, lines, diagnostics);
         finish_body(
             &mut curr_body,
             if loc.is_start() {
@@ -262,11 +466,27 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
             } else {
                 &mut curr_node.body
             },
+            &curr_lines.body,
+            diagnostics,
         )?;
 
         // If we got into any nodes, add the last one to its parent
-        if !loc.is_start() {
-            curr_parent.add_child(curr_node)?;
+        let spans = curr_lines.into_spans(&index);
+        if loc.is_start() {
+            curr_parent.spans = Some(spans);
+        } else {
+            curr_node.spans = Some(spans);
+            if let Err(err) = curr_parent.add_child(curr_node) {
+                diagnostics.error(err, lines.len().saturating_sub(1), 0, "")?;
+            }
+        }
+        // Now the whole tree has been assembled, we can work out how far each node's subtree
+        // extends (the root always covers the whole document)
+        for child in &mut document.root.children {
+            child.extend_subtree_spans(&index);
+        }
+        if let Some(spans) = &mut document.root.spans {
+            spans.subtree = index.whole();
         }
 
         // If we've parsed Org mode, parse any attributes now (converting all keys to lowercase).
@@ -296,23 +516,303 @@ impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
         } else if document_attributes.is_empty() {
             document.attributes = Attributes::None;
         } else {
-            // This will only happen for Markdown
-            return Err(ParseError::IncompleteAttributes);
+            // This will only happen for Markdown (if we're collecting diagnostics, the incomplete
+            // attributes will be dropped)
+            diagnostics.error(
+                ParseError::IncompleteAttributes,
+                attributes_line.unwrap_or(0),
+                0,
+                &document_attributes,
+            )?;
+            document.attributes = Attributes::None;
         }
 
         // Extract title and tags from the attributes (if they're present) and put them into the
-        // root node
-        document.root.title =
-            S::from_str(document.attributes.title()?, format).map_err(|source| {
-                ParseError::ParseStringFailed {
-                    source: Box::new(source),
-                }
-            })?;
+        // root node. If we're collecting diagnostics, invalid titles and tags will be ignored.
+        let attributes_line = attributes_line.unwrap_or(0);
+        let title = match document.attributes.title() {
+            Ok(title) => title,
+            Err(err) => {
+                diagnostics.error(err, attributes_line, 0, &document_attributes)?;
+                String::new()
+            }
+        };
+        document.root.title = match S::from_str(title.clone(), format) {
+            Ok(title) => title,
+            Err(source) => {
+                diagnostics.error(
+                    ParseError::ParseStringFailed {
+                        source: Box::new(source),
+                    },
+                    attributes_line,
+                    0,
+                    &title,
+                )?;
+                S::default()
+            }
+        };
         document.root.tags = Tags {
-            inner: document.attributes.tags()?,
+            inner: match document.attributes.tags() {
+                Ok(tags) => tags,
+                Err(err) => {
+                    diagnostics.error(err, attributes_line, 0, &document_attributes)?;
+                    Vec::new()
+                }
+            },
         };
 
-        Ok(document)
+        Ok((document, layout))
+    }
+}
+
+/// Where problems found while parsing a document should go. The parser is generic over this so
+/// that, when errors are only ever recorded, it can't fail at all.
+trait Diagnostics {
+    /// The error returned when parsing should stop.
+    type Error;
+
+    /// Handles the given error, which was found on the given zero-indexed line and column (in
+    /// characters). This will either return an error so it can be propagated, or record it, in
+    /// which case the caller should recover from it.
+    fn error(
+        &mut self,
+        error: ParseError,
+        line: usize,
+        column: usize,
+        text: &str,
+    ) -> Result<(), Self::Error>;
+    /// Handles the given warning, which doesn't need to stop parsing. The line and column are
+    /// zero-indexed, as in [`Self::error`].
+    fn warning(&mut self, error: ParseError, line: usize, column: usize, text: &str);
+}
+
+/// Parsing should stop at the first error, and warnings should be ignored.
+struct Strict;
+impl Diagnostics for Strict {
+    type Error = ParseError;
+
+    fn error(&mut self, error: ParseError, _: usize, _: usize, _: &str) -> Result<(), ParseError> {
+        Err(error)
+    }
+    fn warning(&mut self, _: ParseError, _: usize, _: usize, _: &str) {}
+}
+
+/// Errors and warnings should be recorded, and parsing should continue as best it can.
+struct Collecting(Vec<Diagnostic>);
+impl Collecting {
+    fn push(
+        &mut self,
+        error: ParseError,
+        severity: Severity,
+        line: usize,
+        column: usize,
+        text: &str,
+    ) {
+        self.0.push(Diagnostic {
+            line: line + 1,
+            column: column + 1,
+            severity,
+            text: text.to_string(),
+            error,
+        });
+    }
+}
+impl Diagnostics for Collecting {
+    type Error = Infallible;
+
+    fn error(
+        &mut self,
+        error: ParseError,
+        line: usize,
+        column: usize,
+        text: &str,
+    ) -> Result<(), Infallible> {
+        self.push(error, Severity::Error, line, column, text);
+        Ok(())
+    }
+    fn warning(&mut self, error: ParseError, line: usize, column: usize, text: &str) {
+        self.push(error, Severity::Warning, line, column, text);
+    }
+}
+
+/// Warns if we're still inside a property drawer, which should have been closed before a new
+/// node or the end of the document was reached.
+fn check_properties_closed(
+    loc: &ParseLocation,
+    properties_lines: &Option<Range<usize>>,
+    lines: &[&str],
+    diagnostics: &mut impl Diagnostics,
+) {
+    if let (true, Some(properties_lines)) = (loc.is_in_properties(), properties_lines) {
+        let opener = lines[properties_lines.start];
+        diagnostics.warning(
+            ParseError::UnclosedProperties,
+            properties_lines.start,
+            column_of(opener, opener.trim()),
+            opener.trim(),
+        );
+    }
+}
+
+/// Gets the zero-indexed column (in characters) at which the given text starts in the given line,
+/// of which it should be a substring.
+fn column_of(line: &str, text: &str) -> usize {
+    line.find(text)
+        .map_or(0, |byte_idx| line[..byte_idx].chars().count())
+}
+
+impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
+    /// Extends the subtree spans of this node and all its descendants to cover their children.
+    /// Before this is called, the subtree span of each node covers only its own components.
+    fn extend_subtree_spans(&mut self, index: &LineIndex) {
+        let mut end = self.spans.as_ref().map(|spans| spans.subtree.lines.end);
+        for child in &mut self.children {
+            child.extend_subtree_spans(index);
+            let child_end = child.spans.as_ref().map(|spans| spans.subtree.lines.end);
+            end = end.max(child_end);
+        }
+
+        if let (Some(spans), Some(end)) = (&mut self.spans, end) {
+            spans.subtree = index.span(spans.subtree.lines.start..end);
+        }
+    }
+}
+
+/// The lines on which each component of a node was found while parsing it. These are converted
+/// into [`NodeSpans`] once the node is finished.
+#[derive(Default)]
+struct ComponentLines {
+    heading: Option<Range<usize>>,
+    planning: Option<Range<usize>>,
+    properties: Option<Range<usize>>,
+    drawers: Option<Range<usize>>,
+    body: Option<Range<usize>>,
+}
+impl ComponentLines {
+    /// Converts these line ranges into spans. The subtree span will cover only the node's own
+    /// components, it should be extended to cover any children once they've all been parsed.
+    fn into_spans(self, index: &LineIndex) -> NodeSpans {
+        let own_lines = [
+            &self.heading,
+            &self.planning,
+            &self.properties,
+            &self.drawers,
+            &self.body,
+        ]
+        .into_iter()
+        .flatten()
+        .fold(None, |acc: Option<Range<usize>>, lines| match acc {
+            Some(acc) => Some(acc.start.min(lines.start)..acc.end.max(lines.end)),
+            None => Some(lines.clone()),
+        });
+
+        NodeSpans {
+            heading: self.heading.map(|lines| index.span(lines)),
+            planning: self.planning.map(|lines| index.span(lines)),
+            properties: self.properties.map(|lines| index.span(lines)),
+            drawers: self.drawers.map(|lines| index.span(lines)),
+            body: self.body.map(|lines| index.span(lines)),
+            // This can only be empty for the root, which gets the whole document anyway
+            subtree: own_lines
+                .map(|lines| index.span(lines))
+                .unwrap_or_else(|| index.whole()),
+        }
+    }
+}
+
+/// Works out which of the given lines are inside blocks whose contents should never be parsed as
+/// headings, returning whether or not each line is in one (including the lines that open and
+/// close the block). In Markdown, these are code fences (which, as in CommonMark, extend to the end
+/// of the document if they're never closed). In Org mode, these are `#+begin_*`/`#+end_*` blocks
+/// (including dynamic `#+begin:`/`#+end:` blocks), which are only considered blocks if they're
+/// properly closed. In both formats, drawers other than property drawers are blocks too, if they're
+/// closed.
+fn find_block_lines(lines: &[&str], format: Format) -> Vec<bool> {
+    let mut in_block = vec![false; lines.len()];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        let end = match format {
+            Format::Markdown if format.get_drawer_name(line).is_some() => {
+                drawer_end(lines, i, format)
+            }
+            Format::Markdown => code_fence(line).map(|(fence_char, fence_len)| {
+                lines[i + 1..]
+                    .iter()
+                    .position(|line| {
+                        code_fence(line.trim()).is_some_and(|(c, len)| {
+                            // Closing fences can't have info strings
+                            c == fence_char && len >= fence_len && line.trim().len() == len
+                        })
+                    })
+                    .map_or(lines.len(), |offset| i + 1 + offset + 1)
+            }),
+            Format::Org => org_block_end(lines, i, line),
+        };
+
+        match end {
+            Some(end) => {
+                in_block[i..end].fill(true);
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+
+    in_block
+}
+
+/// If the given (trimmed) line opens or closes a Markdown code fence, returns the character the
+/// fence is made of, and how many times it's repeated.
+fn code_fence(line: &str) -> Option<(char, usize)> {
+    let fence_char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|c| *c == fence_char).count();
+    (len >= 3).then_some((fence_char, len))
+}
+
+/// If the given (trimmed) line, at the given index, opens an Org block or drawer that's closed
+/// later on, returns the index of the line after it closes.
+fn org_block_end(lines: &[&str], i: usize, line: &str) -> Option<usize> {
+    let lowercase = line.to_lowercase();
+    let closer = if let Some(name) = lowercase.strip_prefix("#+begin_") {
+        let name = name.split_whitespace().next().unwrap_or_default();
+        format!("#+end_{name}")
+    } else if lowercase.starts_with("#+begin:") {
+        "#+end:".to_string()
+    } else {
+        return drawer_end(lines, i, Format::Org);
+    };
+
+    lines[i + 1..]
+        .iter()
+        .position(|later| later.trim().eq_ignore_ascii_case(&closer))
+        .map(|offset| i + 1 + offset + 1)
+}
+
+/// If the line at the given index opens a drawer (other than a property drawer) that's closed later
+/// on, returns the index of the line after it closes.
+fn drawer_end(lines: &[&str], i: usize, format: Format) -> Option<usize> {
+    format.get_drawer_name(lines[i].trim())?;
+    for (offset, later) in lines[i + 1..].iter().enumerate() {
+        let later = later.trim();
+        if later.eq_ignore_ascii_case(format.get_drawer_closer()) {
+            return Some(i + 1 + offset + 1);
+        }
+        // Drawers can't be nested, so an unclosed drawer shouldn't swallow the next one
+        if format.get_drawer_name(later).is_some() || later == format.get_properties_opener() {
+            return None;
+        }
+    }
+
+    None
+}
+
+/// Extends the given range of lines to include the given line (which should come after every line
+/// already in the range), creating it if it doesn't exist yet.
+fn extend(lines: &mut Option<Range<usize>>, line: usize) {
+    match lines {
+        Some(lines) => lines.end = line + 1,
+        None => *lines = Some(line..line + 1),
     }
 }
 
@@ -329,10 +829,16 @@ enum ParseLocation {
 }
 impl ParseLocation {
     fn is_start(&self) -> bool {
-        match &self {
-            Self::OrgStart(_) | Self::MarkdownStart(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::OrgStart(_) | Self::MarkdownStart(_))
+    }
+    /// Whether or not we're inside a property drawer (of the root or any other node).
+    fn is_in_properties(&self) -> bool {
+        matches!(
+            self,
+            Self::Properties
+                | Self::OrgStart(OrgStartLocation::Properties)
+                | Self::MarkdownStart(MarkdownStartLocation::Properties)
+        )
     }
 }
 
@@ -371,11 +877,9 @@ enum MarkdownStartLocation {
 
 impl Tags {
     /// Parses tags from their string representation.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(tags_str: &str) -> Result<Self, ParseError> {
-        let tags = tags_str
-            .strip_prefix(":")
-            .map(|s| s.strip_suffix(":"))
-            .flatten();
+        let tags = tags_str.strip_prefix(":").and_then(|s| s.strip_suffix(":"));
         if let Some(tags) = tags {
             let tags = tags.split(':').map(|s| s.to_string()).collect();
             Ok(Self { inner: tags })
@@ -0,0 +1,180 @@
+//! A model of priorities, so that the raw priority notes on headings can be compared and defaulted
+//! as Org mode does.
+
+use crate::error::PriorityError;
+use crate::{Attributes, Document, Keyword, ParseId, ParseString, Priority};
+use std::cmp::Ordering;
+
+/// A single priority, either a letter (e.g. the `A` in `[#A]`) or a number (e.g. the `1` in
+/// `[#1]`), as Org mode allows.
+///
+/// These are ordered from the highest priority to the lowest, following Org's convention that `A`
+/// and `1` are more important than `B` and `2`, so sorting a list of them will put the most
+/// important first. Letters all come before numbers, though the two shouldn't usually be mixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PriorityValue {
+    /// An uppercase letter from `A` to `Z`.
+    Letter(char),
+    /// A number from 0 to 64.
+    Number(u8),
+}
+impl PriorityValue {
+    /// The highest numeric priority Org mode supports.
+    pub const MAX_NUMBER: u8 = 64;
+
+    /// Parses the given priority (without the surrounding `[#` and `]`).
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(priority: &str) -> Result<Self, PriorityError> {
+        let invalid = || PriorityError::InvalidPriority {
+            priority: priority.to_string(),
+        };
+        let mut chars = priority.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_uppercase() => Ok(Self::Letter(c)),
+            _ => match priority.parse::<u8>() {
+                Ok(number) if number <= Self::MAX_NUMBER => Ok(Self::Number(number)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+    /// Converts this priority into its string representation (without the surrounding `[#` and
+    /// `]`).
+    pub fn into_string(self) -> String {
+        match self {
+            Self::Letter(c) => c.to_string(),
+            Self::Number(number) => number.to_string(),
+        }
+    }
+    /// Returns whether or not this and the given priority are of the same kind (i.e. both letters
+    /// or both numbers).
+    fn same_kind(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::Letter(_), Self::Letter(_)) | (Self::Number(_), Self::Number(_))
+        )
+    }
+}
+
+impl Priority {
+    /// Parses the value of this priority, if there is one.
+    pub fn value(&self) -> Option<Result<PriorityValue, PriorityError>> {
+        self.0.as_deref().map(PriorityValue::from_str)
+    }
+}
+impl From<PriorityValue> for Priority {
+    fn from(value: PriorityValue) -> Self {
+        Self(Some(value.into_string()))
+    }
+}
+
+/// The range of priorities used in a document, along with the priority of headings which don't
+/// have one, as defined by Org mode's `#+PRIORITIES: A C B` (which gives the highest, lowest, and
+/// default priorities, in that order).
+///
+/// The default model is Org mode's default of `A C B`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriorityModel {
+    /// The highest priority.
+    pub highest: PriorityValue,
+    /// The lowest priority.
+    pub lowest: PriorityValue,
+    /// The priority of headings which don't specify one.
+    pub default: PriorityValue,
+}
+impl Default for PriorityModel {
+    fn default() -> Self {
+        Self {
+            highest: PriorityValue::Letter('A'),
+            lowest: PriorityValue::Letter('C'),
+            default: PriorityValue::Letter('B'),
+        }
+    }
+}
+impl PriorityModel {
+    /// Creates a new priority model, checking that the priorities are all of the same kind, and
+    /// that the default is between the highest and lowest.
+    pub fn new(
+        highest: PriorityValue,
+        lowest: PriorityValue,
+        default: PriorityValue,
+    ) -> Result<Self, PriorityError> {
+        if !highest.same_kind(&lowest) || !highest.same_kind(&default) {
+            return Err(PriorityError::MixedKinds);
+        }
+        if highest > lowest || default < highest || default > lowest {
+            return Err(PriorityError::InvalidRange {
+                highest: highest.into_string(),
+                lowest: lowest.into_string(),
+                default: default.into_string(),
+            });
+        }
+
+        Ok(Self {
+            highest,
+            lowest,
+            default,
+        })
+    }
+    /// Parses a priority model from the given definition, in the format of Org's `#+PRIORITIES`
+    /// (e.g. `A C B` or `1 64 32`).
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(definition: &str) -> Result<Self, PriorityError> {
+        let values = definition
+            .split_whitespace()
+            .map(PriorityValue::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        match values[..] {
+            [highest, lowest, default] => Self::new(highest, lowest, default),
+            _ => Err(PriorityError::InvalidDefinition {
+                definition: definition.to_string(),
+            }),
+        }
+    }
+    /// Gets the priority model defined in the given document attributes, if there is one. In Org
+    /// mode, this is defined by the `#+PRIORITIES` attribute, and in Markdown by the `priorities`
+    /// key in the frontmatter, in the same format.
+    pub fn from_attributes(attributes: &Attributes) -> Option<Result<Self, PriorityError>> {
+        let definition = match attributes {
+            Attributes::Org(map) => Ok(map.get("priorities")?.as_str()),
+            Attributes::MarkdownYaml(map) => map
+                .get("priorities")?
+                .as_str()
+                .ok_or(PriorityError::NotString),
+            Attributes::MarkdownToml(map) => map
+                .get("priorities")?
+                .as_str()
+                .ok_or(PriorityError::NotString),
+            Attributes::None => return None,
+        };
+        Some(definition.and_then(Self::from_str))
+    }
+    /// Returns whether or not the given priority is within the range of this model.
+    pub fn contains(&self, value: PriorityValue) -> bool {
+        value.same_kind(&self.highest) && self.highest <= value && value <= self.lowest
+    }
+    /// Gets the effective priority of a heading with the given priority note. This is the
+    /// priority itself if it's valid and within the range of this model, and otherwise the
+    /// default priority (as Org mode does for headings without priorities).
+    pub fn effective(&self, priority: &Priority) -> PriorityValue {
+        priority
+            .value()
+            .and_then(Result::ok)
+            .filter(|value| self.contains(*value))
+            .unwrap_or(self.default)
+    }
+    /// Compares the effective priorities of two headings, such that the heading with the higher
+    /// priority is ordered first.
+    pub fn compare(&self, a: &Priority, b: &Priority) -> Ordering {
+        self.effective(a).cmp(&self.effective(b))
+    }
+}
+
+impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
+    /// Gets the priority model defined by this document's attributes, if there is one. See
+    /// [`PriorityModel::from_attributes`] for details.
+    pub fn priority_model(&self) -> Option<Result<PriorityModel, PriorityError>> {
+        PriorityModel::from_attributes(&self.attributes)
+    }
+}
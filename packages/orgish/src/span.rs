@@ -0,0 +1,86 @@
+//! Positional information about where the components of parsed nodes came from in their source
+//! text.
+
+use std::ops::Range;
+
+/// A contiguous region of the source text a document was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// The byte offsets of this region in the source text, as a half-open range. This will never
+    /// include the newline (or carriage return) that ends the last line of the region.
+    pub bytes: Range<usize>,
+    /// The zero-indexed lines of this region in the source text, as a half-open range.
+    pub lines: Range<usize>,
+}
+
+/// The spans of each part of a node, as it was parsed. Components that were not present in the
+/// source (e.g. a node without any planning items) will have no span.
+///
+/// These reflect the source text at the time of parsing, and will *not* be updated if the node is
+/// modified afterward.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeSpans {
+    /// The heading line of the node. This will be `None` for the root node of a document.
+    pub heading: Option<Span>,
+    /// The planning lines directly beneath the heading.
+    pub planning: Option<Span>,
+    /// The property drawer, including its opening and closing lines.
+    pub properties: Option<Span>,
+    /// Any other drawers directly after the property drawer (e.g. `:LOGBOOK:`), including their
+    /// opening and closing lines.
+    pub drawers: Option<Span>,
+    /// The body of the node, up until its first child. For the root node of an Org document, this
+    /// may include attribute lines (e.g. `#+title`) that were interspersed with the body.
+    pub body: Option<Span>,
+    /// The whole node, including all its children. For the root node, this is the whole document.
+    pub subtree: Span,
+}
+
+/// The lines of a source text, together with the byte offsets they start at, used to construct
+/// [`Span`]s during parsing.
+pub(crate) struct LineIndex<'s> {
+    /// The lines of the source text, without their terminating newlines (as produced by
+    /// [`str::lines`]).
+    pub(crate) lines: Vec<&'s str>,
+    /// The byte offset of the start of each line.
+    starts: Vec<usize>,
+}
+impl<'s> LineIndex<'s> {
+    /// Indexes the lines of the given source text.
+    pub(crate) fn new(raw: &'s str) -> Self {
+        // `split_inclusive` produces exactly the same lines as `lines`, but keeps the terminators,
+        // so we can use it to work out where each line starts
+        let mut starts = Vec::new();
+        let mut offset = 0;
+        for line in raw.split_inclusive('\n') {
+            starts.push(offset);
+            offset += line.len();
+        }
+
+        Self {
+            lines: raw.lines().collect(),
+            starts,
+        }
+    }
+    /// Creates a span covering the given (non-empty) range of lines.
+    pub(crate) fn span(&self, lines: Range<usize>) -> Span {
+        debug_assert!(!lines.is_empty(), "attempted to create span of no lines");
+        let last = lines.end - 1;
+        Span {
+            bytes: self.starts[lines.start]..self.starts[last] + self.lines[last].len(),
+            lines,
+        }
+    }
+    /// Creates a span covering the whole source text. This is used for the root node of a
+    /// document, which may be empty.
+    pub(crate) fn whole(&self) -> Span {
+        if self.lines.is_empty() {
+            Span {
+                bytes: 0..0,
+                lines: 0..0,
+            }
+        } else {
+            self.span(0..self.lines.len())
+        }
+    }
+}
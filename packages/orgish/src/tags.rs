@@ -0,0 +1,215 @@
+//! Logic for working out the tags that apply to nodes through inheritance and tag groups, as Org
+//! mode does.
+
+use crate::error::TagConfigError;
+use crate::{Attributes, Document, Keyword, ParseId, ParseString};
+
+/// The configuration of the tags in a document, which determines the *effective* tags of each
+/// node: the tags it has itself, the tags it inherits from its ancestors and the document, and the
+/// group tags of any of those which are in a tag hierarchy.
+///
+/// The groups and known tags can be read from Org mode's `#+TAGS` attribute (see
+/// [`Self::from_str`]), while inheritance is configured here as it is in Emacs, rather than in
+/// documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagConfig {
+    /// All the tags listed in the configuration, including the members of groups (but not group
+    /// tags themselves, unless they're listed separately).
+    pub tags: Vec<String>,
+    /// Groups of tags, in the order they were defined.
+    pub groups: Vec<TagGroup>,
+    /// Whether or not tags are inherited by the children of the nodes (or documents) they're on,
+    /// like Org's `org-use-tag-inheritance`. This is `true` by default.
+    pub inherit: bool,
+    /// Tags which are never inherited, like Org's `org-tags-exclude-from-inheritance`. These will
+    /// still apply to the nodes they're on.
+    pub exclude_from_inheritance: Vec<String>,
+}
+impl Default for TagConfig {
+    fn default() -> Self {
+        Self {
+            tags: Vec::new(),
+            groups: Vec::new(),
+            inherit: true,
+            exclude_from_inheritance: Vec::new(),
+        }
+    }
+}
+
+/// A group of tags from a `#+TAGS` definition. These are written as `{ @home @work }` for groups
+/// of mutually exclusive tags, and `[ GTD : Control Persp ]` for tag hierarchies, where the first
+/// tag is the group tag, which applies to any node with one of the member tags. Exclusive groups
+/// can have group tags too (e.g. `{ Context : @home @work }`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagGroup {
+    /// The group tag, if there is one.
+    pub name: Option<String>,
+    /// The tags in this group.
+    pub members: Vec<String>,
+    /// Whether or not only one of the members should be on a node at once.
+    pub exclusive: bool,
+}
+
+impl TagConfig {
+    /// Parses the tags and groups from the given definition, in the format of Org's `#+TAGS` (e.g.
+    /// `laptop(l) { @home(h) @work(w) } [ GTD : Control Persp ]`). Fast-access keys in parentheses
+    /// are ignored, as are line breaks (`\n`). Inheritance will be configured as by default.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(definition: &str) -> Result<Self, TagConfigError> {
+        let mut config = Self::default();
+        // The closing bracket and contents of the group we're in, if any
+        let mut group: Option<(&str, Vec<&str>)> = None;
+        for token in definition.split_whitespace() {
+            match (token, &mut group) {
+                ("{", None) => group = Some(("}", Vec::new())),
+                ("[", None) => group = Some(("]", Vec::new())),
+                ("{" | "[", Some(_)) => {
+                    return Err(TagConfigError::NestedGroup {
+                        definition: definition.to_string(),
+                    })
+                }
+                ("}" | "]", Some((closer, _))) if token == *closer => {
+                    let (_, tokens) = group.take().unwrap();
+                    let (name, members) = match tokens[..] {
+                        [name, ":", ref members @ ..] => (Some(name.to_string()), members),
+                        ref members => (None, members),
+                    };
+                    let members = members.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+                    config.tags.extend(members.iter().cloned());
+                    config.groups.push(TagGroup {
+                        name,
+                        members,
+                        exclusive: token == "}",
+                    });
+                }
+                ("}" | "]", _) => {
+                    return Err(TagConfigError::UnexpectedClose {
+                        definition: definition.to_string(),
+                    })
+                }
+                ("\\n", _) => {}
+                (_, Some((_, tokens))) => tokens.push(strip_key(token)),
+                (_, None) => config.tags.push(strip_key(token).to_string()),
+            }
+        }
+        if group.is_some() {
+            return Err(TagConfigError::UnclosedGroup {
+                definition: definition.to_string(),
+            });
+        }
+
+        Ok(config)
+    }
+    /// Gets the tag configuration defined in the given document attributes, if there is one. In
+    /// Org mode, this is defined by the `#+TAGS` attribute, and in Markdown by the `tag_config` key
+    /// in the frontmatter, in the same format (as `tags` is already used for the document's own
+    /// tags there).
+    ///
+    /// Org documents only record the last of several `#+TAGS` lines, so the others will be ignored
+    /// here. Tags defined over several lines should be combined into one.
+    pub fn from_attributes(attributes: &Attributes) -> Option<Result<Self, TagConfigError>> {
+        let definition = match attributes {
+            Attributes::Org(map) => Ok(map.get("tags")?.as_str()),
+            Attributes::MarkdownYaml(map) => map
+                .get("tag_config")?
+                .as_str()
+                .ok_or(TagConfigError::NotString),
+            Attributes::MarkdownToml(map) => map
+                .get("tag_config")?
+                .as_str()
+                .ok_or(TagConfigError::NotString),
+            Attributes::None => return None,
+        };
+        Some(definition.and_then(Self::from_str))
+    }
+
+    /// Gets the tags that the children of a node will inherit, given the tags that node inherited
+    /// itself and its own tags. For the top-level nodes of a document, the inherited tags should
+    /// be empty, and the own tags should be those of the document.
+    pub fn passed_down(&self, inherited: &[String], own: &[String]) -> Vec<String> {
+        if !self.inherit {
+            return Vec::new();
+        }
+        let mut tags = Vec::new();
+        for tag in inherited.iter().chain(own) {
+            if !self.exclude_from_inheritance.contains(tag) && !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        tags
+    }
+    /// Gets the effective tags of a node, given the tags it inherited (see [`Self::passed_down`])
+    /// and its own tags. These are the inherited tags, followed by its own, followed by the group
+    /// tags of any of those which are in tag hierarchies (including groups of groups).
+    pub fn effective(&self, inherited: &[String], own: &[String]) -> Vec<String> {
+        let mut tags = Vec::new();
+        for tag in inherited.iter().chain(own) {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        // Group tags can themselves be in groups, so this keeps going over the new ones
+        let mut idx = 0;
+        while idx < tags.len() {
+            for group in &self.groups {
+                if let Some(name) = &group.name {
+                    if group.members.contains(&tags[idx]) && !tags.contains(name) {
+                        tags.push(name.clone());
+                    }
+                }
+            }
+            idx += 1;
+        }
+
+        tags
+    }
+    /// Gets the mutually exclusive groups which have more than one of their members in the given
+    /// tags.
+    pub fn conflicts(&self, tags: &[String]) -> Vec<&TagGroup> {
+        self.groups
+            .iter()
+            .filter(|group| {
+                group.exclusive
+                    && group
+                        .members
+                        .iter()
+                        .filter(|member| tags.contains(member))
+                        .count()
+                        > 1
+            })
+            .collect()
+    }
+}
+
+/// Removes the fast-access key from the given tag (e.g. `@work(w)` becomes `@work`).
+fn strip_key(tag: &str) -> &str {
+    match tag.find('(') {
+        Some(idx) if idx > 0 && tag.ends_with(')') => &tag[..idx],
+        _ => tag,
+    }
+}
+
+impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
+    /// Gets the tag configuration defined by this document's attributes, if there is one. See
+    /// [`TagConfig::from_attributes`] for details.
+    pub fn tag_config(&self) -> Option<Result<TagConfig, TagConfigError>> {
+        TagConfig::from_attributes(&self.attributes)
+    }
+    /// Gets the effective tags of the node at the given path, which is a list of child indices
+    /// starting from the top level (so `[1, 0]` is the first child of the second top-level node).
+    /// See [`TagConfig::effective`] for what this includes. If the path is empty, this gets the
+    /// effective tags of the document itself.
+    ///
+    /// This will return `None` if there's no node at the given path.
+    pub fn effective_tags(&self, path: &[usize], config: &TagConfig) -> Option<Vec<String>> {
+        let mut node = &self.root;
+        let mut inherited = Vec::new();
+        for idx in path {
+            inherited = config.passed_down(&inherited, &node.tags);
+            node = node.children().get(*idx)?;
+        }
+
+        Some(config.effective(&inherited, &node.tags))
+    }
+}
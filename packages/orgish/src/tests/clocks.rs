@@ -0,0 +1,95 @@
+use super::*;
+use crate::drawer::Clock;
+use crate::error::ClockError;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+fn time(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 1, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+#[test]
+fn clock_lines_should_parse() {
+    let clock =
+        Clock::from_str("CLOCK: [2024-01-01 Mon 09:00]--[2024-01-01 Mon 10:30] =>  1:30").unwrap();
+    assert_eq!(clock.start, time(1, 9, 0));
+    assert_eq!(clock.duration(), Some(Duration::minutes(90)));
+    assert_eq!(
+        clock.into_string(),
+        "CLOCK: [2024-01-01 Mon 09:00]--[2024-01-01 Mon 10:30] =>  1:30"
+    );
+
+    let clock = Clock::from_str("[2024-01-01 Mon 09:00]").unwrap();
+    assert_eq!(clock.end, None);
+    assert!(matches!(
+        Clock::from_str("CLOCK: <2024-01-01 Mon 09:00>"),
+        Err(ClockError::InvalidClock { .. })
+    ));
+    assert!(matches!(
+        Clock::from_str("CLOCK: [2024-01-01 Mon]"),
+        Err(ClockError::InvalidClock { .. })
+    ));
+}
+
+#[test]
+fn clocking_in_and_out_should_work() {
+    let mut node = Node::<CustomKeyword>::new(1, "Task".to_string(), None);
+    assert!(node.open_clock().is_none());
+    assert!(matches!(
+        node.clock_out(time(1, 10, 0)),
+        Err(ClockError::NotClockedIn)
+    ));
+
+    node.clock_in(time(1, 9, 0) + Duration::seconds(42))
+        .unwrap();
+    assert_eq!(node.open_clock().unwrap().start, time(1, 9, 0));
+    assert!(matches!(
+        node.clock_in(time(1, 9, 30)),
+        Err(ClockError::AlreadyClockedIn { .. })
+    ));
+    assert!(matches!(
+        node.clock_out(time(1, 8, 0)),
+        Err(ClockError::EndBeforeStart { .. })
+    ));
+    assert_eq!(
+        node.clock_out(time(1, 10, 30)).unwrap(),
+        Duration::minutes(90)
+    );
+    assert!(node.open_clock().is_none());
+
+    assert_eq!(
+        node.into_string(Format::Org),
+        r#"* Task
+:LOGBOOK:
+CLOCK: [2024-01-01 Mon 09:00]--[2024-01-01 Mon 10:30] =>  1:30
+:END:"#
+    );
+}
+
+#[test]
+fn clocked_time_should_sum_over_subtree() {
+    let text = r#"* Project
+:LOGBOOK:
+CLOCK: [2024-01-01 Mon 09:00]--[2024-01-01 Mon 10:00] =>  1:00
+CLOCK: [2024-01-03 Wed 09:00]
+:END:
+** Task
+:LOGBOOK:
+CLOCK: [2024-01-01 Mon 23:00]--[2024-01-02 Tue 01:00] =>  2:00
+CLOCK: [2024-01-05 Fri 09:00]--[2024-01-05 Fri 09:45] =>  0:45
+:END:"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Org).unwrap();
+    let project = &document.root.children()[0];
+
+    assert_eq!(
+        project.clocked_time(time(1, 0, 0), time(10, 0, 0)),
+        Duration::minutes(60 + 120 + 45)
+    );
+    // Only the first hour of the overnight clock is on the first day
+    assert_eq!(
+        project.clocked_time(time(1, 0, 0), time(2, 0, 0)),
+        Duration::minutes(60 + 60)
+    );
+}
@@ -0,0 +1,170 @@
+use super::*;
+use crate::error::{Severity, TimestampParseError};
+
+#[test]
+fn diagnostics_should_collect_errors_and_continue() {
+    let text = r#"* Heading 1
+DEADLINE: <2023-13-01 Sun>
+Body 1
+* Heading 2
+:PROPERTIES:
+:FOO: bar
+  invalid property
+:END:
+* Heading 3
+SCHEDULED: <2023-01-01 Sun>"#;
+    assert!(Document::<CustomKeyword>::from_str(text, Format::Org).is_err());
+
+    let (document, diagnostics) =
+        Document::<CustomKeyword>::from_str_with_diagnostics(text, Format::Org);
+    assert_eq!(diagnostics.len(), 2);
+
+    let bad_timestamp = &diagnostics[0];
+    assert_eq!(bad_timestamp.severity, Severity::Error);
+    assert_eq!((bad_timestamp.line, bad_timestamp.column), (2, 11));
+    assert_eq!(bad_timestamp.text, "<2023-13-01 Sun>");
+    assert!(matches!(
+        bad_timestamp.error,
+        ParseError::TimestampParseError(_)
+    ));
+
+    let bad_property = &diagnostics[1];
+    assert_eq!((bad_property.line, bad_property.column), (7, 3));
+    assert_eq!(bad_property.text, "invalid property");
+    assert!(matches!(
+        bad_property.error,
+        ParseError::InvalidProperty { .. }
+    ));
+
+    // Everything else should still be there
+    let nodes = document.root.children();
+    assert_eq!(nodes.len(), 3);
+    assert!(nodes[0].planning.deadline.is_none());
+    assert_eq!(
+        nodes[0].body.as_deref(),
+        Some("DEADLINE: <2023-13-01 Sun>\nBody 1")
+    );
+    assert_eq!(nodes[1].properties.get("FOO").unwrap(), "bar");
+    assert!(nodes[2].planning.scheduled.is_some());
+}
+
+#[test]
+fn diagnostics_should_include_warnings() {
+    let text = r#"* Heading 1
+DEADLINE: <2023-01-01 Sun>
+DEADLINE: <2023-01-02 Mon>
+* Heading 2
+:PROPERTIES:
+:FOO: bar
+* Heading 3"#;
+    // Neither of these are errors in strict mode
+    assert!(Document::<CustomKeyword>::from_str(text, Format::Org).is_ok());
+
+    let (_, diagnostics) = Document::<CustomKeyword>::from_str_with_diagnostics(text, Format::Org);
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Warning));
+    assert!(matches!(
+        diagnostics[0].error,
+        ParseError::PlanningRepeat { .. }
+    ));
+    assert_eq!(diagnostics[0].line, 3);
+    assert!(matches!(
+        diagnostics[1].error,
+        ParseError::UnclosedProperties
+    ));
+    assert_eq!(
+        diagnostics[1].to_string(),
+        "5:1: warning: found property drawer that was never closed"
+    );
+}
+
+#[test]
+fn diagnostics_should_drop_bad_frontmatter() {
+    let text = r#"---
+title: [Test
+---
+
+Root"#;
+    let (document, diagnostics) =
+        Document::<CustomKeyword>::from_str_with_diagnostics(text, Format::Markdown);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, 1);
+    assert!(matches!(
+        diagnostics[0].error,
+        ParseError::YamlFrontmatterParseFailed { .. }
+    ));
+    assert!(matches!(document.attributes, Attributes::None));
+    assert_eq!(document.root.body.as_deref(), Some("\nRoot"));
+}
+
+#[test]
+fn diagnostics_should_record_malformed_repeaters() {
+    let text = r#"* Heading 1 <2024-05-01 Wed +w>
+* Heading 2
+SCHEDULED: <2024-05-01 Wed .w>
+* Heading 3"#;
+    assert!(Document::<CustomKeyword>::from_str(text, Format::Org).is_err());
+
+    let (document, diagnostics) =
+        Document::<CustomKeyword>::from_str_with_diagnostics(text, Format::Org);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 12));
+    assert_eq!(diagnostics[0].text, "<2024-05-01 Wed .w>");
+    assert!(matches!(
+        diagnostics[0].error,
+        ParseError::TimestampParseError(TimestampParseError::InvalidRepeaterCount { .. })
+    ));
+
+    // Invalid timestamps in headings are just left in the title
+    let nodes = document.root.children();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[0].title, "Heading 1 <2024-05-01 Wed +w>");
+    assert!(nodes[0].timestamps.is_empty());
+    assert!(nodes[1].planning.scheduled.is_none());
+}
+
+#[test]
+fn diagnostics_should_attach_headings_that_skip_levels() {
+    // Each child should go under the nearest heading above it, whatever its level
+    let (document, diagnostics) =
+        Document::<CustomKeyword>::from_str_with_diagnostics("** B\n*** C", Format::Org);
+    let nodes = document.root.children();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].title, "B");
+    assert_eq!(nodes[0].children()[0].title, "C");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].line, 1);
+    assert!(matches!(
+        diagnostics[0].error,
+        ParseError::SkippedLevel {
+            parent_level: 0,
+            level: 2
+        }
+    ));
+
+    let text = "* A\n*** C\n**** D\n** E";
+    let (document, diagnostics) =
+        Document::<CustomKeyword>::from_str_with_diagnostics(text, Format::Org);
+    let a = &document.root.children()[0];
+    assert_eq!(a.children().len(), 2);
+    assert_eq!(a.children()[0].title, "C");
+    assert_eq!(a.children()[0].children()[0].title, "D");
+    assert_eq!(a.children()[1].title, "E");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, 2);
+    assert!(matches!(
+        diagnostics[0].error,
+        ParseError::SkippedLevel {
+            parent_level: 1,
+            level: 3
+        }
+    ));
+
+    // These aren't errors in strict mode
+    let document = Document::<CustomKeyword>::from_str(text, Format::Org).unwrap();
+    assert_eq!(document.into_string(Format::Org), text);
+}
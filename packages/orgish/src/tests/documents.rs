@@ -291,3 +291,86 @@ Root"#;
 
     assert_eq!(text_org, document.into_string(Format::Org));
 }
+
+#[test]
+fn blocks_and_drawers_should_not_contain_headings() {
+    let text = r#"#+title: Test Document
+#+begin_src org
+* Not a heading
+#+title: Not an attribute
+#+end_src
+
+* Heading 1
+#+BEGIN_EXAMPLE
+** Not a heading
+#+END_EXAMPLE
+:NOTES:
+* Not a heading
+:END:
+#+begin: clocktable
+* Not a heading
+#+end:
+* Heading 2
+:UNCLOSED:
+** Heading 2.1"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Org).unwrap();
+
+    assert_eq!(document.root.title, "Test Document");
+    assert_eq!(
+        document.root.body.as_deref(),
+        Some("#+begin_src org\n* Not a heading\n#+title: Not an attribute\n#+end_src\n")
+    );
+    let children = document.root.children();
+    assert_eq!(children.len(), 2);
+    assert!(children[0].children().is_empty());
+    assert_eq!(
+        children[0].body.as_deref(),
+        Some("#+BEGIN_EXAMPLE\n** Not a heading\n#+END_EXAMPLE\n:NOTES:\n* Not a heading\n:END:\n#+begin: clocktable\n* Not a heading\n#+end:")
+    );
+    // A drawer that's never closed isn't a drawer
+    assert_eq!(children[1].children()[0].title, "Heading 2.1");
+    assert_eq!(document.into_string(Format::Org), text);
+}
+
+#[test]
+fn planning_items_should_parse_on_one_line() {
+    let text = r#"* DONE Heading 1
+CLOSED: [2023-01-02 Mon 10:00] DEADLINE: <2023-01-01 Sun 09:30> SCHEDULED: <2022-12-25 Sun>
+* Heading 2
+SCHEDULED: <2022-12-25 Sun>
+DEADLINE: <2023-01-01 Sun>"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Org).unwrap();
+
+    let planning = &document.root.children()[0].planning;
+    assert!(planning.closed.is_some());
+    assert!(planning.deadline.is_some());
+    assert!(planning.scheduled.is_some());
+    assert_eq!(document.root.children()[0].body, None);
+    assert_eq!(
+        planning.clone().into_single_line_string(),
+        "CLOSED: [2023-01-02 Mon 10:00] DEADLINE: <2023-01-01 Sun 09:30> SCHEDULED: <2022-12-25 Sun>"
+    );
+    assert_eq!(
+        planning.clone().into_string(),
+        "DEADLINE: <2023-01-01 Sun 09:30>\nSCHEDULED: <2022-12-25 Sun>\nCLOSED: [2023-01-02 Mon 10:00]"
+    );
+
+    let planning = document.root.children()[1].planning.clone();
+    assert_eq!(
+        planning.into_single_line_string(),
+        "DEADLINE: <2023-01-01 Sun> SCHEDULED: <2022-12-25 Sun>"
+    );
+}
+
+#[test]
+fn invalid_planning_items_should_not_be_partially_added() {
+    let mut planning = Planning::default();
+    assert!(planning
+        .add_line("DEADLINE: <2023-01-01 Sun> SCHEDULED: <not a timestamp>")
+        .unwrap()
+        .is_err());
+    assert!(planning.deadline.is_none());
+    assert!(planning
+        .add_line("Not DEADLINE: <2023-01-01 Sun>")
+        .is_none());
+}
@@ -0,0 +1,142 @@
+use super::*;
+use crate::drawer::{Clock, DrawerContents, LogEntry};
+use chrono::NaiveDate;
+
+fn time(day: u32, hour: u32, minute: u32) -> chrono::NaiveDateTime {
+    NaiveDate::from_ymd_opt(2023, 1, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+#[test]
+fn logbook_should_be_parsed() {
+    let text = r#"* DONE Heading
+CLOSED: [2023-01-02 Mon 10:00]
+:PROPERTIES:
+:FOO: bar
+:END:
+:LOGBOOK:
+- State "DONE"       from "TODO"       [2023-01-02 Mon 10:00]
+- Note taken on [2023-01-01 Sun 12:00] \\
+  Something to remember
+  over two lines
+CLOCK: [2023-01-01 Sun 10:00]--[2023-01-01 Sun 11:30] =>  1:30
+CLOCK: [2023-01-02 Mon 09:00]
+- Refiled on [2023-01-01 Sun 09:00]
+:END:
+:NOTES:
+Freeform notes
+:END:
+Body"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Org).unwrap();
+    let node = &document.root.children()[0];
+
+    let logbook = node.drawers.logbook().unwrap();
+    assert_eq!(
+        **logbook,
+        vec![
+            LogEntry::StateChange {
+                to: "DONE".to_string(),
+                from: Some("TODO".to_string()),
+                time: time(2, 10, 0),
+                note: None,
+            },
+            LogEntry::Note {
+                time: time(1, 12, 0),
+                text: "Something to remember\nover two lines".to_string(),
+            },
+            LogEntry::Clock(Clock {
+                start: time(1, 10, 0),
+                end: Some(time(1, 11, 30)),
+            }),
+            LogEntry::Clock(Clock {
+                start: time(2, 9, 0),
+                end: None,
+            }),
+            LogEntry::Other("- Refiled on [2023-01-01 Sun 09:00]".to_string()),
+        ]
+    );
+    match &node.drawers.get("notes").unwrap().contents {
+        DrawerContents::Text(text) => assert_eq!(text, "Freeform notes"),
+        DrawerContents::Logbook(_) => panic!("expected text drawer"),
+    }
+    assert_eq!(node.body.as_deref(), Some("Body"));
+    assert_eq!(document.into_string(Format::Org), text);
+}
+
+#[test]
+fn drawers_should_work_in_markdown() {
+    let text = r#"# Heading
+<!--PROPERTIES
+FOO: bar
+-->
+<!--LOGBOOK
+- State "TODO"       from              [2023-01-01 Sun 10:00]
+-->
+Body"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Markdown).unwrap();
+    let node = &document.root.children()[0];
+    assert_eq!(
+        node.drawers.logbook().unwrap()[0],
+        LogEntry::StateChange {
+            to: "TODO".to_string(),
+            from: None,
+            time: time(1, 10, 0),
+            note: None,
+        }
+    );
+    assert_eq!(document.into_string(Format::Markdown), text);
+}
+
+#[test]
+fn drawers_after_body_should_stay_in_body() {
+    let text = r#"* Heading
+Body
+:LOGBOOK:
+CLOCK: [2023-01-01 Sun 10:00]
+:END:"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Org).unwrap();
+    let node = &document.root.children()[0];
+    assert!(node.drawers.is_empty());
+    assert_eq!(
+        node.body.as_deref(),
+        Some("Body\n:LOGBOOK:\nCLOCK: [2023-01-01 Sun 10:00]\n:END:")
+    );
+}
+
+#[test]
+fn logbook_entries_should_be_added() {
+    let text = r#"* Heading
+  :LOGBOOK:
+  CLOCK: [2023-01-01 Sun 10:00]--[2023-01-01 Sun 11:30] =>  1:30
+  :END:"#;
+    let mut document = Document::<CustomKeyword>::from_str_lossless(text, Format::Org).unwrap();
+    let node = &mut document.root.unchecked_mut_children()[0];
+    node.drawers.logbook_mut().insert(
+        0,
+        LogEntry::Clock(Clock {
+            start: time(2, 9, 0),
+            end: Some(time(2, 20, 15)),
+        }),
+    );
+    assert_eq!(
+        document.into_string(Format::Org),
+        r#"* Heading
+  :LOGBOOK:
+  CLOCK: [2023-01-02 Mon 09:00]--[2023-01-02 Mon 20:15] => 11:15
+  CLOCK: [2023-01-01 Sun 10:00]--[2023-01-01 Sun 11:30] =>  1:30
+  :END:"#
+    );
+
+    // Nodes without a logbook should get a new one
+    let mut node = Node::<CustomKeyword>::new(1, "Heading".to_string(), None);
+    node.drawers.logbook_mut().push(LogEntry::Clock(Clock {
+        start: time(1, 10, 0),
+        end: None,
+    }));
+    assert_eq!(
+        node.into_string(Format::Org),
+        "* Heading\n:LOGBOOK:\nCLOCK: [2023-01-01 Sun 10:00]\n:END:"
+    );
+}
@@ -1,3 +1,6 @@
+// These tests borrow their headings, which is left as it was written
+#![allow(clippy::needless_borrow)]
+
 use super::*;
 
 #[test]
@@ -188,6 +191,18 @@ fn heading_parser_should_count_early_timestamp() {
     assert_eq!(node.into_string(Format::Org), heading);
 }
 #[test]
+fn heading_parser_should_parse_range_timestamps() {
+    let heading = "* Conference <2023-01-01 Sun>--<2023-01-03 Tue> :work:";
+    let node = Node::<CustomKeyword>::from_heading_str(heading, Format::Org)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(node.title, "Conference");
+    assert_eq!(node.timestamps.len(), 1);
+    assert!(node.timestamps[0].end.is_some());
+    assert_eq!(node.into_string(Format::Org), heading);
+}
+#[test]
 fn heading_parser_should_fail_on_non_heading() {
     let bad_heading = " ** Test";
     let node = Node::<CustomKeyword>::from_heading_str(&bad_heading, Format::Org);
@@ -0,0 +1,154 @@
+use super::*;
+use crate::keyword::{KeywordSet, TypedWorkflow, Workflow, WorkflowKeyword};
+
+#[test]
+fn keyword_sets_should_be_parsed() {
+    let set = KeywordSet::from_str("TODO NEXT(n) | DONE(d!) KILL(k@/!)").unwrap();
+    assert_eq!(set.todo, ["TODO", "NEXT"]);
+    assert_eq!(set.done, ["DONE", "KILL"]);
+    assert!(set.is_done_keyword("KILL"));
+    assert!(!set.is_done_keyword("NEXT"));
+
+    // Without a bar, the last keyword is done
+    let set = KeywordSet::from_str("TODO WAIT FINISHED").unwrap();
+    assert_eq!(set.todo, ["TODO", "WAIT"]);
+    assert_eq!(set.done, ["FINISHED"]);
+
+    assert!(KeywordSet::from_str("  ").is_err());
+}
+
+#[test]
+fn keyword_sets_should_be_read_from_documents() {
+    let document = Document::<CustomKeyword>::from_str(
+        "#+title: Test\n#+TODO: TODO PROJ | DONE\n\n* TODO Task\n",
+        Format::Org,
+    )
+    .unwrap();
+    let set = document.keyword_set().unwrap().unwrap();
+    assert_eq!(set.todo, ["TODO", "PROJ"]);
+
+    let document = Document::<CustomKeyword>::from_str(
+        "---\ntitle: Test\ntodo: TODO | DONE KILL\n---\n\n# TODO Task\n",
+        Format::Markdown,
+    )
+    .unwrap();
+    let set = document.keyword_set().unwrap().unwrap();
+    assert_eq!(set.done, ["DONE", "KILL"]);
+
+    let document =
+        Document::<CustomKeyword>::from_str("#+title: Test\n\n* Task\n", Format::Org).unwrap();
+    assert!(document.keyword_set().is_none());
+}
+
+#[test]
+fn keyword_set_workflow_should_cycle() {
+    let set = KeywordSet::from_str("TODO PROJ | DONE").unwrap();
+    let done = CustomKeyword::Other("DONE".to_string());
+
+    assert_eq!(set.first_todo(), Some(CustomKeyword::Todo));
+    assert_eq!(set.first_done(), Some(done.clone()));
+    assert!(set.is_done(&done));
+    assert!(!set.is_done(&CustomKeyword::Proj));
+
+    assert_eq!(set.next(None), Some(CustomKeyword::Todo));
+    assert_eq!(
+        set.next(Some(&CustomKeyword::Todo)),
+        Some(CustomKeyword::Proj)
+    );
+    assert_eq!(set.next(Some(&CustomKeyword::Proj)), Some(done.clone()));
+    assert_eq!(set.next(Some(&done)), None);
+    assert_eq!(set.previous(Some(&CustomKeyword::Todo)), None);
+    assert_eq!(set.previous(None), Some(done));
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Chore {
+    Todo,
+    Done,
+    Other(String),
+}
+impl Keyword for Chore {
+    fn from_str(keyword: &str) -> Option<Self> {
+        match keyword {
+            "TODO" => Some(Self::Todo),
+            "DONE" => Some(Self::Done),
+            _ => None,
+        }
+    }
+    fn into_string(self) -> String {
+        match self {
+            Self::Todo => "TODO".to_string(),
+            Self::Done => "DONE".to_string(),
+            Self::Other(s) => s,
+        }
+    }
+    fn other(keyword: String) -> Self {
+        Self::Other(keyword)
+    }
+}
+impl WorkflowKeyword for Chore {
+    fn states() -> Vec<Self> {
+        vec![Self::Todo, Self::Done]
+    }
+    fn is_done(&self) -> bool {
+        matches!(self, Self::Done)
+    }
+}
+
+#[test]
+fn typed_workflow_should_use_keyword_states() {
+    assert_eq!(TypedWorkflow.first_done(), Some(Chore::Done));
+    assert_eq!(TypedWorkflow.next(Some(&Chore::Todo)), Some(Chore::Done));
+    // Unknown keywords start the cycle again
+    assert_eq!(
+        TypedWorkflow.next(Some(&Chore::Other("WAIT".to_string()))),
+        Some(Chore::Todo)
+    );
+    assert!(!Workflow::<Chore>::is_done(&TypedWorkflow, &Chore::Todo));
+}
+
+#[derive(orgish_derive::Keyword, Debug, Clone, PartialEq)]
+enum Errand {
+    Todo,
+    #[keyword("WAITING")]
+    Wait,
+    #[keyword(done)]
+    Done,
+    #[keyword("CANCELLED", done)]
+    Kill,
+    #[keyword(other)]
+    Other(String),
+}
+
+#[test]
+fn derived_keywords_should_parse_and_write() {
+    assert_eq!(Errand::from_str("TODO"), Some(Errand::Todo));
+    assert_eq!(Errand::from_str("WAITING"), Some(Errand::Wait));
+    assert_eq!(Errand::from_str("WAIT"), None);
+    assert_eq!(Errand::Kill.into_string(), "CANCELLED");
+    assert_eq!(Errand::Other("PROJ".to_string()).into_string(), "PROJ");
+
+    let document =
+        Document::<Errand>::from_str("* WAITING Reply\n* CANCELLED Trip\n", Format::Org).unwrap();
+    let keywords = document
+        .root
+        .children()
+        .iter()
+        .map(|node| node.keyword.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(keywords, [Some(Errand::Wait), Some(Errand::Kill)]);
+}
+
+#[test]
+fn typed_workflow_should_use_derived_keyword_states() {
+    assert_eq!(TypedWorkflow.first_done(), Some(Errand::Done));
+    assert_eq!(TypedWorkflow.next(Some(&Errand::Wait)), Some(Errand::Done));
+    assert_eq!(TypedWorkflow.previous(Some(&Errand::Todo)), None);
+    // Unknown keywords start the cycle again
+    assert_eq!(
+        TypedWorkflow.next(Some(&Errand::Other("WAIT".to_string()))),
+        Some(Errand::Todo)
+    );
+    assert!(Workflow::<Errand>::is_done(&TypedWorkflow, &Errand::Kill));
+    assert!(!Workflow::<Errand>::is_done(&TypedWorkflow, &Errand::Wait));
+}
@@ -0,0 +1,164 @@
+use super::*;
+
+const ORG_DOCUMENT: &str = r#":PROPERTIES:
+:ZETA: last
+:ALPHA: first
+:END:
+#+title: Test Document
+Some root content.
+#+author: Someone
+
+* TODO   Heading 1   :tag:
+SCHEDULED: <2023-01-01 Sun 10:00>--<2023-01-01 Sun 12:00>
+:PROPERTIES:
+:WORLD: hello
+:HELLO:    world
+:END:
+Body text.
+** Heading 1.1
+* Heading 2
+"#;
+
+#[test]
+fn unchanged_document_should_be_written_identically() {
+    let document = Document::<CustomKeyword>::from_str_lossless(ORG_DOCUMENT, Format::Org).unwrap();
+    assert_eq!(document.into_string(Format::Org), ORG_DOCUMENT);
+    // Line endings should be preserved too
+    let crlf = ORG_DOCUMENT.replace('\n', "\r\n");
+    let document = Document::<CustomKeyword>::from_str_lossless(&crlf, Format::Org).unwrap();
+    assert_eq!(document.into_string(Format::Org), crlf);
+
+    // The regular writer normalises plenty of this
+    let document = Document::<CustomKeyword>::from_str(ORG_DOCUMENT, Format::Org).unwrap();
+    assert_ne!(document.into_string(Format::Org), ORG_DOCUMENT);
+}
+
+#[test]
+fn empty_and_unclosed_drawers_should_be_written_identically() {
+    for text in [
+        ":PROPERTIES:\n:END:\nbody\n",
+        "* A\n:PROPERTIES:\n:END:\nbody\n",
+        "* A\n:PROPERTIES:\n\n:END:\n",
+        ":PROPERTIES:\n* A\n",
+        ":PROPERTIES:\n:FOO: bar\n* A\n",
+        "* A\n:PROPERTIES:\n** B\n",
+    ] {
+        let document = Document::<CustomKeyword>::from_str_lossless(text, Format::Org).unwrap();
+        assert_eq!(document.into_string(Format::Org), text);
+    }
+
+    // Drawers whose properties have all been removed are still dropped
+    let mut document = Document::<CustomKeyword>::from_str_lossless(
+        "* A\n:PROPERTIES:\n:FOO: bar\n:END:\n",
+        Format::Org,
+    )
+    .unwrap();
+    document.root.unchecked_mut_children()[0]
+        .properties
+        .remove("FOO");
+    assert_eq!(document.into_string(Format::Org), "* A\n");
+}
+
+#[test]
+fn only_changed_parts_should_be_rewritten() {
+    let mut document =
+        Document::<CustomKeyword>::from_str_lossless(ORG_DOCUMENT, Format::Org).unwrap();
+    document.root.title = "New Title".to_string();
+    document
+        .root
+        .properties
+        .insert("MIDDLE".to_string(), "new".to_string());
+    let node = &mut document.root.unchecked_mut_children()[0];
+    node.properties
+        .insert("WORLD".to_string(), "there".to_string());
+    node.properties.remove("HELLO");
+    node.unchecked_mut_children()[0].title = "Renamed".to_string();
+
+    assert_eq!(
+        document.into_string(Format::Org),
+        r#":PROPERTIES:
+:ZETA: last
+:ALPHA: first
+:MIDDLE: new
+:END:
+#+title: New Title
+Some root content.
+#+author: Someone
+
+* TODO   Heading 1   :tag:
+SCHEDULED: <2023-01-01 Sun 10:00>--<2023-01-01 Sun 12:00>
+:PROPERTIES:
+:WORLD: there
+:END:
+Body text.
+** Renamed
+* Heading 2
+"#
+    );
+}
+
+#[test]
+fn changed_headings_and_planning_should_be_rewritten() {
+    let mut document =
+        Document::<CustomKeyword>::from_str_lossless(ORG_DOCUMENT, Format::Org).unwrap();
+    let node = &mut document.root.unchecked_mut_children()[0];
+    node.keyword = None;
+    node.planning.scheduled = None;
+    node.body = Some("New body.".to_string());
+
+    assert_eq!(
+        document.into_string(Format::Org),
+        r#":PROPERTIES:
+:ZETA: last
+:ALPHA: first
+:END:
+#+title: Test Document
+Some root content.
+#+author: Someone
+
+* Heading 1 :tag:
+:PROPERTIES:
+:WORLD: hello
+:HELLO:    world
+:END:
+New body.
+** Heading 1.1
+* Heading 2
+"#
+    );
+}
+
+#[test]
+fn markdown_frontmatter_should_be_preserved_until_changed() {
+    let text = r#"---
+title: "Test Document"
+tags:   [a, b]
+---
+
+Content.
+# Heading
+<!--PROPERTIES
+B: 2
+A: 1
+-->
+"#;
+    let document = Document::<CustomKeyword>::from_str_lossless(text, Format::Markdown).unwrap();
+    assert_eq!(document.into_string(Format::Markdown), text);
+
+    let mut document =
+        Document::<CustomKeyword>::from_str_lossless(text, Format::Markdown).unwrap();
+    document.root.tags.push("c".to_string());
+    let written = document.into_string(Format::Markdown);
+    assert!(written.starts_with("---\ntitle: Test Document\ntags:\n- a\n- b\n- c\n---\n"));
+    assert!(written.ends_with("\nContent.\n# Heading\n<!--PROPERTIES\nB: 2\nA: 1\n-->\n"));
+}
+
+#[test]
+fn lossless_documents_should_be_normalised_in_other_formats() {
+    let lossless = Document::<CustomKeyword>::from_str_lossless(ORG_DOCUMENT, Format::Org).unwrap();
+    let regular = Document::<CustomKeyword>::from_str(ORG_DOCUMENT, Format::Org).unwrap();
+    assert_eq!(
+        lossless.into_string(Format::Markdown),
+        regular.into_string(Format::Markdown)
+    );
+}
@@ -55,3 +55,39 @@ Test"#;
     // The easiest way of testing this is to ensure that everything gets rewritten correctly
     assert_eq!(document.into_string(Format::Markdown), text);
 }
+
+#[test]
+fn code_fences_should_not_contain_headings() {
+    let text = r#"```bash
+# Not a heading
+```
+
+# Heading 1
+~~~~
+# Still not a heading
+```
+## Nor this
+~~~~
+# Heading 2
+```
+# The rest of the document is in this unclosed fence"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Markdown).unwrap();
+
+    assert_eq!(
+        document.root.body.as_deref(),
+        Some("```bash\n# Not a heading\n```\n")
+    );
+    let children = document.root.children();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0].title, "Heading 1");
+    assert!(children[0].children().is_empty());
+    assert_eq!(
+        children[0].body.as_deref(),
+        Some("~~~~\n# Still not a heading\n```\n## Nor this\n~~~~")
+    );
+    assert_eq!(
+        children[1].body.as_deref(),
+        Some("```\n# The rest of the document is in this unclosed fence")
+    );
+    assert_eq!(document.into_string(Format::Markdown), text);
+}
@@ -1,11 +1,22 @@
+mod clocks;
+mod diagnostics;
 mod documents;
+mod drawers;
 mod headings;
+mod keywords;
+mod lossless;
 mod markdown_documents;
+mod priorities;
+mod spans;
+mod tag_inheritance;
 mod timestamps;
+mod timezones;
+mod traversal;
+mod user_input;
 
 pub use super::*;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CustomKeyword {
     Todo,
     Proj,
@@ -0,0 +1,72 @@
+use super::*;
+use crate::priority::{PriorityModel, PriorityValue};
+
+#[test]
+fn priority_values_should_be_parsed_and_ordered() {
+    assert_eq!(
+        PriorityValue::from_str("A").unwrap(),
+        PriorityValue::Letter('A')
+    );
+    assert_eq!(
+        PriorityValue::from_str("12").unwrap(),
+        PriorityValue::Number(12)
+    );
+    assert!(PriorityValue::from_str("a").is_err());
+    assert!(PriorityValue::from_str("AB").is_err());
+    assert!(PriorityValue::from_str("65").is_err());
+
+    // Higher priorities come first
+    let mut values = ["C", "A", "B"].map(|p| PriorityValue::from_str(p).unwrap());
+    values.sort();
+    assert_eq!(values.map(PriorityValue::into_string), ["A", "B", "C"]);
+    assert!(PriorityValue::Number(2) < PriorityValue::Number(10));
+}
+
+#[test]
+fn priority_models_should_be_parsed() {
+    let model = PriorityModel::from_str("1 64 32").unwrap();
+    assert_eq!(model.highest, PriorityValue::Number(1));
+    assert_eq!(model.default, PriorityValue::Number(32));
+    assert!(model.contains(PriorityValue::Number(64)));
+    assert!(!model.contains(PriorityValue::Number(0)));
+    assert!(!model.contains(PriorityValue::Letter('A')));
+
+    assert!(PriorityModel::from_str("A C").is_err());
+    assert!(PriorityModel::from_str("C A B").is_err());
+    assert!(PriorityModel::from_str("A C 2").is_err());
+    assert!(PriorityModel::from_str("A C D").is_err());
+}
+
+#[test]
+fn effective_priorities_should_apply_default() {
+    let document = Document::<CustomKeyword>::from_str(
+        "#+title: Test\n#+PRIORITIES: A E C\n\n* TODO [#A] First\n* Second\n* [#D] Third\n* [#Z] Fourth\n",
+        Format::Org,
+    )
+    .unwrap();
+    let model = document.priority_model().unwrap().unwrap();
+    let effective = document
+        .root
+        .children()
+        .iter()
+        .map(|node| model.effective(&node.priority).into_string())
+        .collect::<Vec<_>>();
+    // Out of range priorities fall back to the default
+    assert_eq!(effective, ["A", "C", "D", "C"]);
+
+    let mut nodes = document.root.children().iter().collect::<Vec<_>>();
+    nodes.sort_by(|a, b| model.compare(&a.priority, &b.priority));
+    let titles = nodes
+        .iter()
+        .map(|node| node.title.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(titles, ["First", "Second", "Fourth", "Third"]);
+
+    // Numeric priorities are written back as they were
+    let priority = Priority::from(PriorityValue::Number(10));
+    assert_eq!(priority.into_string(), "[#10]");
+    assert_eq!(
+        PriorityModel::default().effective(&Priority(None)),
+        PriorityValue::Letter('B')
+    );
+}
@@ -0,0 +1,73 @@
+use super::*;
+
+#[test]
+fn spans_should_cover_node_components() {
+    let text = r#"#+title: Test Document
+
+Root
+
+* Heading 1
+DEADLINE: <2023-01-01 Sun>
+:PROPERTIES:
+:FOO: bar
+:END:
+Body
+** Heading 1.1
+* Heading 2"#;
+    let document = Document::<CustomKeyword>::from_str(text, Format::Org).unwrap();
+
+    let root_spans = document.root.spans().unwrap();
+    assert_eq!(root_spans.heading, None);
+    assert_eq!(root_spans.subtree.lines, 0..12);
+    assert_eq!(root_spans.subtree.bytes, 0..text.len());
+    assert_eq!(root_spans.body.as_ref().unwrap().lines, 1..4);
+
+    let node = &document.root.children()[0];
+    let spans = node.spans().unwrap();
+    let slice = |span: &Option<Span>| &text[span.as_ref().unwrap().bytes.clone()];
+    assert_eq!(slice(&spans.heading), "* Heading 1");
+    assert_eq!(slice(&spans.planning), "DEADLINE: <2023-01-01 Sun>");
+    assert_eq!(slice(&spans.properties), ":PROPERTIES:\n:FOO: bar\n:END:");
+    assert_eq!(slice(&spans.body), "Body");
+    assert_eq!(spans.subtree.lines, 4..11);
+    assert_eq!(
+        &text[spans.subtree.bytes.clone()],
+        "* Heading 1\nDEADLINE: <2023-01-01 Sun>\n:PROPERTIES:\n:FOO: bar\n:END:\nBody\n** Heading 1.1"
+    );
+
+    let child_spans = node.children()[0].spans().unwrap();
+    assert_eq!(child_spans.heading.as_ref().unwrap().lines, 10..11);
+    assert_eq!(child_spans.planning, None);
+    assert_eq!(child_spans.properties, None);
+    assert_eq!(child_spans.body, None);
+    assert_eq!(child_spans.subtree.lines, 10..11);
+
+    let last_spans = document.root.children()[1].spans().unwrap();
+    assert_eq!(last_spans.subtree.lines, 11..12);
+}
+
+#[test]
+fn spans_should_handle_md_frontmatter_and_crlf() {
+    let text =
+        "---\r\ntitle: Test\r\n---\r\n<!--PROPERTIES\r\nFOO: bar\r\n-->\r\n# Heading\r\nBody\r\n";
+    let document = Document::<CustomKeyword>::from_str(text, Format::Markdown).unwrap();
+
+    let root_spans = document.root.spans().unwrap();
+    assert_eq!(root_spans.properties.as_ref().unwrap().lines, 3..6);
+    assert_eq!(root_spans.body, None);
+
+    let spans = document.root.children()[0].spans().unwrap();
+    let heading = spans.heading.as_ref().unwrap();
+    // Carriage returns shouldn't be included
+    assert_eq!(&text[heading.bytes.clone()], "# Heading");
+    assert_eq!(&text[spans.subtree.bytes.clone()], "# Heading\r\nBody");
+}
+
+#[test]
+fn spans_should_not_exist_for_manual_nodes() {
+    let node = Node::<CustomKeyword>::new(1, "Test".to_string(), None);
+    assert!(node.spans().is_none());
+
+    let document = Document::<CustomKeyword>::from_str("", Format::Org).unwrap();
+    assert_eq!(document.root.spans().unwrap().subtree.bytes, 0..0);
+}
@@ -0,0 +1,89 @@
+use super::*;
+use crate::tags::{TagConfig, TagGroup};
+
+#[test]
+fn tag_configs_should_be_parsed() {
+    let config =
+        TagConfig::from_str("laptop(l) { @home(h) @work(w) } \\n [ GTD : Control Persp ]").unwrap();
+    assert_eq!(
+        config.tags,
+        ["laptop", "@home", "@work", "Control", "Persp"]
+    );
+    assert_eq!(
+        config.groups,
+        [
+            TagGroup {
+                name: None,
+                members: vec!["@home".to_string(), "@work".to_string()],
+                exclusive: true,
+            },
+            TagGroup {
+                name: Some("GTD".to_string()),
+                members: vec!["Control".to_string(), "Persp".to_string()],
+                exclusive: false,
+            },
+        ]
+    );
+
+    assert!(TagConfig::from_str("{ a b").is_err());
+    assert!(TagConfig::from_str("a ] b").is_err());
+    assert!(TagConfig::from_str("{ a [ b ] }").is_err());
+}
+
+#[test]
+fn effective_tags_should_be_inherited() {
+    let document = Document::<CustomKeyword>::from_str(
+        r#"#+title: Test
+#+filetags: :project:
+#+tags: [ Work : Meeting Review ] [ Everything : Work Home ] { @home @office }
+
+* Parent :Meeting:private:
+** Child :@home:@office:
+*** Grandchild
+* Other
+"#,
+        Format::Org,
+    )
+    .unwrap();
+    let mut config = document.tag_config().unwrap().unwrap();
+
+    assert_eq!(document.effective_tags(&[], &config).unwrap(), ["project"]);
+    assert_eq!(
+        document.effective_tags(&[0], &config).unwrap(),
+        ["project", "Meeting", "private", "Work", "Everything"]
+    );
+    assert_eq!(
+        document.effective_tags(&[0, 0, 0], &config).unwrap(),
+        [
+            "project",
+            "Meeting",
+            "private",
+            "@home",
+            "@office",
+            "Work",
+            "Everything"
+        ]
+    );
+    assert_eq!(document.effective_tags(&[1], &config).unwrap(), ["project"]);
+    assert!(document.effective_tags(&[2], &config).is_none());
+
+    let child_tags = document.effective_tags(&[0, 0], &config).unwrap();
+    assert_eq!(config.conflicts(&child_tags).len(), 1);
+
+    // Excluded tags still apply where they're set
+    config.exclude_from_inheritance = vec!["private".to_string()];
+    assert!(document
+        .effective_tags(&[0], &config)
+        .unwrap()
+        .contains(&"private".to_string()));
+    assert!(!document
+        .effective_tags(&[0, 0], &config)
+        .unwrap()
+        .contains(&"private".to_string()));
+
+    config.inherit = false;
+    assert_eq!(
+        document.effective_tags(&[1], &config).unwrap(),
+        Vec::<String>::new()
+    );
+}
@@ -1,3 +1,6 @@
+// Dates are written with zero-padding to mirror the timestamps they test
+#![allow(clippy::zero_prefixed_literal)]
+
 use super::*;
 use chrono::{Datelike, Duration, NaiveDate};
 
@@ -50,6 +53,25 @@ test_timestamp!(
     range_timestamp_with_times_should_work,
     "<2023-10-18 Wed 09:00>--<2023-10-19 Thu 10:00>"
 );
+test_timestamp!(
+    timestamp_with_warning_period_should_work,
+    "<2024-05-01 Wed -3d>"
+);
+test_timestamp!(timestamp_with_delay_no_day_should_work, "<2024-05-01 --2d>" => "<2024-05-01 Wed --2d>");
+test_timestamp!(
+    timestamp_with_repeater_and_delay_should_work,
+    "<2024-05-01 Wed 09:00 +1w -2d>"
+);
+test_timestamp!(timestamp_with_delay_before_repeater_should_work, "<2024-05-01 Wed -2d +1w>" => "<2024-05-01 Wed +1w -2d>");
+test_timestamp!(
+    timestamp_with_catch_up_repeater_should_work,
+    "<2024-05-01 Wed 09:00 ++1w>"
+);
+test_timestamp!(timestamp_with_restart_repeater_should_work, "<2024-05-01 .+2d -1d>" => "<2024-05-01 Wed .+2d -1d>");
+test_timestamp!(
+    range_timestamp_with_delay_should_work,
+    "<2024-05-01 Wed 09:00 -1d>--<2024-05-01 Wed 10:00>" => "<2024-05-01 Wed 09:00-10:00 -1d>"
+);
 
 macro_rules! date {
     ($year:literal, $month:literal, $day:literal) => {
@@ -283,3 +305,499 @@ fn timestamp_next_date_works_for_years() {
         Some(date!(2026, 01, 02))
     );
 }
+
+#[test]
+fn delays_should_be_parsed() {
+    use crate::timestamp::{Delay, DelayKind, RepeaterUnit};
+
+    let ts = Timestamp::from_str("<2024-05-01 Wed --2w>").unwrap();
+    assert_eq!(
+        ts.delay,
+        Some(Delay {
+            count: 2,
+            unit: RepeaterUnit::Week,
+            kind: DelayKind::First,
+        })
+    );
+    assert!(Timestamp::from_str("<2024-05-01 Wed -3x>").is_err());
+    assert!(Timestamp::from_str("<2024-05-01 Wed ---3d>").is_err());
+}
+
+#[test]
+fn delays_should_reject_trailing_characters() {
+    use crate::error::TimestampParseError;
+
+    for ts in [
+        "<2024-05-01 Wed -3d5>",
+        "<2024-05-01 Wed -3dd>",
+        "<2024-05-01 Wed -3d->",
+        "<2024-05-01 Wed +1w -3d5>",
+    ] {
+        assert!(matches!(
+            Timestamp::from_str(ts),
+            Err(TimestampParseError::BadCharacter { .. })
+        ));
+    }
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed -99999999999999999999999d>"),
+        Err(TimestampParseError::InvalidDelayCount { .. })
+    ));
+    // Repeaters can still come after the delay
+    let ts = Timestamp::from_str("<2024-05-01 Wed -3d +1w>").unwrap();
+    assert!(ts.delay.is_some() && ts.repeater.is_some());
+}
+
+#[test]
+fn malformed_delays_should_fail() {
+    use crate::error::TimestampParseError;
+
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed -3>"),
+        Err(TimestampParseError::MissingDelayUnit { count }) if count == "3"
+    ));
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed +1w -3>"),
+        Err(TimestampParseError::MissingDelayUnit { .. })
+    ));
+    for ts in [
+        "<2024-05-01 Wed ->",
+        "<2024-05-01 Wed -->",
+        "<2024-05-01 10:00 +1d ->",
+    ] {
+        assert!(matches!(
+            Timestamp::from_str(ts),
+            Err(TimestampParseError::InvalidDelayCount { count }) if count.is_empty()
+        ));
+    }
+    for ts in ["<2024-05-01 Wed -0d>", "<2024-05-01 Wed --0d>"] {
+        assert!(matches!(
+            Timestamp::from_str(ts),
+            Err(TimestampParseError::ZeroDelayCount)
+        ));
+    }
+
+    // The end of a range can't have its own delay
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed>--<2024-05-03 Fri -2d>"),
+        Err(TimestampParseError::RangeEndModifiers { .. })
+    ));
+    let ts = Timestamp::from_str("<2024-05-01 Wed -2d>--<2024-05-03 Fri -2d>").unwrap();
+    assert_eq!(ts.delay.unwrap().count, 2);
+}
+
+#[test]
+fn malformed_repeaters_should_fail() {
+    use crate::error::TimestampParseError;
+
+    for ts in ["<2024-05-01 Wed +1>", "<2024-05-01 Wed 10:00 ++2>"] {
+        assert!(matches!(
+            Timestamp::from_str(ts),
+            Err(TimestampParseError::MissingRepeaterUnit { .. })
+        ));
+    }
+    for ts in [
+        "<2024-05-01 Wed +>",
+        "<2024-05-01 Wed .+>",
+        "<2024-05-01 Wed -2d +>",
+    ] {
+        assert!(matches!(
+            Timestamp::from_str(ts),
+            Err(TimestampParseError::InvalidRepeaterCount { count }) if count.is_empty()
+        ));
+    }
+    // Nothing can be merged into the count
+    for ts in [
+        "<2024-05-01 Wed +1d +2w>",
+        "<2024-05-01 Wed +1d->",
+        "<2024-05-01 Wed +1d/3d/4d>",
+        "<2024-05-01 Wed -2d +1w -3d>",
+    ] {
+        assert!(matches!(
+            Timestamp::from_str(ts),
+            Err(TimestampParseError::BadCharacter { .. })
+        ));
+    }
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed .+1d/3>"),
+        Err(TimestampParseError::MissingRepeaterUnit { repeater }) if repeater == "/3"
+    ));
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed .+1d/0d>"),
+        Err(TimestampParseError::ZeroRepeaterCount)
+    ));
+
+    // These shouldn't be silently dropped from planning lines either
+    assert!(Document::<CustomKeyword>::from_str(
+        "* TODO Task\nSCHEDULED: <2024-05-01 Wed +>",
+        Format::Org
+    )
+    .is_err());
+}
+
+#[test]
+fn habit_repeaters_should_be_kept() {
+    use crate::timestamp::RepeaterUnit;
+
+    let ts = Timestamp::from_str("<2024-05-01 Wed .+1d/3d -1d>").unwrap();
+    let repeater = ts.repeater.clone().unwrap();
+    assert_eq!(repeater.count, 1);
+    assert_eq!(repeater.habit_max, Some((3, RepeaterUnit::Day)));
+    assert_eq!(ts.delay.as_ref().unwrap().count, 1);
+    assert_eq!(ts.into_string(), "<2024-05-01 Wed .+1d/3d -1d>");
+
+    // The maximum interval doesn't change how the timestamp repeats
+    let ts = Timestamp::from_str("<2024-05-01 Wed +1w/2w>").unwrap();
+    let next = ts.into_next_repeat();
+    assert_eq!(next.unwrap().into_string(), "<2024-05-08 Wed +1w/2w>");
+}
+
+#[test]
+fn warning_period_should_apply_before_deadline() {
+    let ts = Timestamp::from_str("<2024-05-01 Wed -3d>").unwrap();
+    assert!(!ts.is_within_warning_period(date!(2024, 04, 27), 14));
+    assert!(ts.is_within_warning_period(date!(2024, 04, 28), 14));
+    assert!(ts.is_within_warning_period(date!(2024, 05, 01), 14));
+    assert!(!ts.is_within_warning_period(date!(2024, 05, 02), 14));
+
+    // Without a warning period, the default is used
+    let ts = Timestamp::from_str("<2024-05-01 Wed>").unwrap();
+    assert!(ts.is_within_warning_period(date!(2024, 04, 17), 14));
+    assert!(!ts.is_within_warning_period(date!(2024, 04, 16), 14));
+
+    // Repeating deadlines warn about their next repeat
+    let ts = Timestamp::from_str("<2024-05-01 Wed +1m -1w>").unwrap();
+    assert!(ts.is_within_warning_period(date!(2024, 05, 28), 14));
+    assert!(!ts.is_within_warning_period(date!(2024, 05, 20), 14));
+}
+
+#[test]
+fn repeater_kinds_should_progress_from_completion() {
+    // Completed well after the timestamp, on a Saturday
+    let completion = date!(2024, 05, 18).and_hms_opt(12, 0, 0).unwrap();
+
+    let ts = Timestamp::from_str("<2024-05-01 Wed +1w>").unwrap();
+    assert_eq!(
+        ts.into_next_repeat_from(completion).unwrap().into_string(),
+        "<2024-05-08 Wed +1w>"
+    );
+    let ts = Timestamp::from_str("<2024-05-01 Wed ++1w>").unwrap();
+    assert_eq!(
+        ts.into_next_repeat_from(completion).unwrap().into_string(),
+        "<2024-05-22 Wed ++1w>"
+    );
+    let ts = Timestamp::from_str("<2024-05-01 Wed 09:00 .+1w>").unwrap();
+    assert_eq!(
+        ts.into_next_repeat_from(completion).unwrap().into_string(),
+        "<2024-05-25 Sat 09:00 .+1w>"
+    );
+
+    // Catch-up repeaters still move forward if they're already in the future
+    let ts = Timestamp::from_str("<2024-05-20 Mon ++1w>").unwrap();
+    assert_eq!(
+        ts.into_next_repeat_from(completion).unwrap().into_string(),
+        "<2024-05-27 Mon ++1w>"
+    );
+
+    assert!(Timestamp::from_str("<2024-05-01 Wed .1w>").is_err());
+    assert!(Timestamp::from_str("<2024-05-01 Wed +++1w>").is_err());
+}
+
+#[test]
+fn hourly_catch_up_repeaters_should_jump_past_completion() {
+    use crate::timestamp::{Repeater, RepeaterKind, RepeaterUnit};
+
+    let next = |ts: &str, completion: chrono::NaiveDateTime| {
+        Timestamp::from_str(ts)
+            .unwrap()
+            .into_next_repeat_from(completion)
+            .unwrap()
+            .into_string()
+    };
+    let at = |day, hour| {
+        chrono::NaiveDate::from_ymd_opt(2024, 5, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    };
+
+    assert_eq!(
+        next("<2024-05-01 Wed 22:00 ++3h>", at(18, 12)),
+        "<2024-05-18 Sat 13:00 ++3h>"
+    );
+    // Completing exactly on a repeat moves past it
+    assert_eq!(
+        next("<2024-05-01 Wed 22:00 ++3h>", at(2, 4)),
+        "<2024-05-02 Thu 07:00 ++3h>"
+    );
+    // Timestamps in the future still move by one interval
+    assert_eq!(
+        next("<2024-05-01 Wed 22:00 ++3h>", at(1, 1)),
+        "<2024-05-02 Thu 01:00 ++3h>"
+    );
+    // Completions long after the timestamp don't need to step through every interval
+    let completion = date!(2124, 05, 01).and_hms_opt(0, 30, 0).unwrap();
+    assert_eq!(
+        next("<2024-05-01 Wed 22:00 ++1h>", completion),
+        "<2124-05-01 Mon 01:00 ++1h>"
+    );
+
+    // A zero-hour repeater (which can only be constructed by hand) still moves forward
+    let mut ts = Timestamp::from_str("<2024-05-01 Wed 22:00>").unwrap();
+    ts.repeater = Some(Repeater {
+        count: 0,
+        unit: RepeaterUnit::Hour,
+        kind: RepeaterKind::CatchUp,
+        habit_max: None,
+    });
+    let next = ts.into_next_repeat_from(at(2, 4)).unwrap();
+    assert_eq!(next.start.date, date!(2024, 05, 02));
+    assert_eq!(next.start.time, chrono::NaiveTime::from_hms_opt(5, 0, 0));
+}
+
+#[test]
+fn repeaters_without_counts_should_fail() {
+    use crate::error::TimestampParseError;
+
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed +w>"),
+        Err(TimestampParseError::InvalidRepeaterCount { .. })
+    ));
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed .w>"),
+        Err(TimestampParseError::InvalidRepeaterCount { .. })
+    ));
+    assert!(matches!(
+        Timestamp::from_str("<2024-05-01 Wed ++99999999999999999999999d>"),
+        Err(TimestampParseError::InvalidRepeaterCount { .. })
+    ));
+}
+
+test_timestamp!(
+    timestamp_with_hourly_repeater_should_work,
+    "<2024-05-01 Wed 09:00 +4h>"
+);
+
+#[test]
+fn hourly_repeaters_should_occur_several_times_a_day() {
+    use crate::timestamp::TimestampApplies;
+
+    let ts = Timestamp::from_str("<2024-05-01 Wed 22:00-23:30 +8h>").unwrap();
+    assert!(!ts.includes_date(date!(2024, 04, 30)));
+    assert!(ts.includes_date(date!(2024, 05, 01)));
+    assert_eq!(
+        ts.get_next_repeat(date!(2024, 05, 03)),
+        Some(date!(2024, 05, 03))
+    );
+
+    let time = |h, m| chrono::NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    let applies = ts.applies_all(date!(2024, 05, 02));
+    assert_eq!(applies.len(), 3);
+    assert!(
+        matches!(applies[0], TimestampApplies::Block(s, e) if s == time(6, 0) && e == time(7, 30))
+    );
+    assert!(
+        matches!(applies[1], TimestampApplies::Block(s, e) if s == time(14, 0) && e == time(15, 30))
+    );
+    assert!(
+        matches!(applies[2], TimestampApplies::Block(s, e) if s == time(22, 0) && e == time(23, 30))
+    );
+
+    // Occurrences can cross midnight
+    let ts = Timestamp::from_str("<2024-05-01 Wed 23:00 +12h>--<2024-05-02 Thu 01:00>").unwrap();
+    let applies = ts.applies_all(date!(2024, 05, 02));
+    assert!(matches!(applies[0], TimestampApplies::End(e) if e == time(1, 0)));
+    assert!(
+        matches!(applies[1], TimestampApplies::Block(s, e) if s == time(11, 0) && e == time(13, 0))
+    );
+    assert!(matches!(applies[2], TimestampApplies::Start(s) if s == time(23, 0)));
+
+    let ts = Timestamp::from_str("<2024-05-01 Wed 22:00 +3h>").unwrap();
+    assert_eq!(
+        ts.clone().into_next_repeat().unwrap().into_string(),
+        "<2024-05-02 Thu 01:00 +3h>"
+    );
+    assert_eq!(
+        ts.into_next_repeat_after(date!(2024, 05, 03))
+            .unwrap()
+            .into_string(),
+        "<2024-05-03 Fri 01:00 +3h>"
+    );
+}
+
+#[test]
+fn occurrences_should_cover_date_span() {
+    let starts = |ts: &Timestamp, from, to| {
+        ts.occurrences(from, to)
+            .map(|occurrence| occurrence.start.date)
+            .collect::<Vec<_>>()
+    };
+
+    // Monthly repeats skip months without the right day
+    let ts = Timestamp::from_str("<2024-01-31 Wed +1m>").unwrap();
+    assert_eq!(
+        starts(&ts, date!(2024, 01, 01), date!(2024, 06, 30)),
+        vec![
+            date!(2024, 01, 31),
+            date!(2024, 03, 31),
+            date!(2024, 05, 31)
+        ]
+    );
+
+    // Ranges are included if they overlap the span at all
+    let ts = Timestamp::from_str("<2024-05-01 Wed 09:00 +1w>--<2024-05-03 Fri 17:00>").unwrap();
+    let occurrences = ts
+        .occurrences(date!(2024, 05, 10), date!(2024, 05, 20))
+        .collect::<Vec<_>>();
+    assert_eq!(occurrences.len(), 2);
+    assert_eq!(occurrences[0].start.date, date!(2024, 05, 08));
+    assert_eq!(
+        occurrences[0].end.as_ref().unwrap().date,
+        date!(2024, 05, 10)
+    );
+    assert_eq!(
+        occurrences[0].end.as_ref().unwrap().time,
+        chrono::NaiveTime::from_hms_opt(17, 0, 0)
+    );
+    assert_eq!(occurrences[1].start.date, date!(2024, 05, 15));
+
+    // Non-repeating timestamps occur at most once
+    let ts = Timestamp::from_str("<2024-05-01 Wed>").unwrap();
+    assert_eq!(
+        starts(&ts, date!(2024, 04, 01), date!(2024, 05, 31)).len(),
+        1
+    );
+    assert!(starts(&ts, date!(2024, 05, 02), date!(2024, 05, 31)).is_empty());
+
+    // Hourly repeats can occur many times a day
+    let ts = Timestamp::from_str("<2024-05-01 Wed 22:00 +6h>").unwrap();
+    assert_eq!(
+        ts.occurrences(date!(2024, 05, 02), date!(2024, 05, 02))
+            .count(),
+        4
+    );
+}
+
+#[test]
+fn zero_repeaters_should_not_loop_forever() {
+    use crate::error::TimestampParseError;
+    use crate::timestamp::{Repeater, RepeaterKind, RepeaterUnit};
+
+    for ts in [
+        "<2024-05-01 Wed +0d>",
+        "<2024-05-01 Wed 09:00 +0h>",
+        "<2024-05-01 Wed ++0w>",
+        "<2024-05-01 Wed .+00m>",
+    ] {
+        assert!(matches!(
+            Timestamp::from_str(ts),
+            Err(TimestampParseError::ZeroRepeaterCount)
+        ));
+    }
+
+    // They can still be constructed by hand, in which case they only occur once
+    for unit in [RepeaterUnit::Hour, RepeaterUnit::Day, RepeaterUnit::Month] {
+        let mut ts = Timestamp::from_str("<2024-05-01 Wed 09:00>").unwrap();
+        ts.repeater = Some(Repeater {
+            count: 0,
+            unit,
+            kind: RepeaterKind::Cumulate,
+            habit_max: None,
+        });
+        let occurrences = ts
+            .occurrences(date!(2024, 04, 01), date!(2024, 06, 30))
+            .collect::<Vec<_>>();
+        assert_eq!(occurrences.len(), 1);
+        assert_eq!(occurrences[0].start.date, date!(2024, 05, 01));
+        assert_eq!(
+            ts.occurrences(date!(2024, 05, 02), date!(2024, 06, 30))
+                .count(),
+            0
+        );
+    }
+}
+
+#[test]
+fn occurrences_should_know_how_they_apply() {
+    use crate::timestamp::TimestampApplies;
+
+    let time = |h| chrono::NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+    let ts = Timestamp::from_str("<2024-05-01 Wed 09:00 +1w>--<2024-05-03 Fri 17:00>").unwrap();
+    let occurrence = ts
+        .occurrences(date!(2024, 05, 08), date!(2024, 05, 08))
+        .next()
+        .unwrap();
+    assert_eq!(
+        occurrence.applies(date!(2024, 05, 08)),
+        TimestampApplies::Start(time(9))
+    );
+    assert_eq!(
+        occurrence.applies(date!(2024, 05, 09)),
+        TimestampApplies::AllDay
+    );
+    assert_eq!(
+        occurrence.applies(date!(2024, 05, 10)),
+        TimestampApplies::End(time(17))
+    );
+    assert_eq!(
+        occurrence.applies(date!(2024, 05, 11)),
+        TimestampApplies::None
+    );
+}
+
+#[test]
+fn timestamps_should_be_ordered_by_start() {
+    let mut timestamps = [
+        "<2024-05-02 Thu>",
+        "<2024-05-01 Wed 09:00>",
+        "<2024-05-01 Wed 08:00-10:00>",
+        "<2024-05-01 Wed>",
+        "<2024-04-30 Tue 23:00>",
+    ]
+    .map(|ts| Timestamp::from_str(ts).unwrap());
+    timestamps.sort();
+    assert_eq!(
+        timestamps.map(|ts| ts.into_string()),
+        [
+            "<2024-04-30 Tue 23:00>",
+            "<2024-05-01 Wed>",
+            "<2024-05-01 Wed 08:00-10:00>",
+            "<2024-05-01 Wed 09:00>",
+            "<2024-05-02 Thu>",
+        ]
+    );
+}
+
+#[test]
+fn timestamps_should_shift_start_and_end() {
+    let mut ts = Timestamp::from_str("<2024-05-01 Wed 22:00 +1w>--<2024-05-02 Thu 01:00>").unwrap();
+    ts.shift(Duration::try_hours(3).unwrap());
+    assert_eq!(ts.clone().into_string(), "<2024-05-02 Thu 01:00-04:00 +1w>");
+    ts.shift_days(-2);
+    assert_eq!(ts.into_string(), "<2024-04-30 Tue 01:00-04:00 +1w>");
+
+    let mut ts = Timestamp::from_str("<2024-01-31 Wed -2d>").unwrap();
+    ts.shift_months(1);
+    assert_eq!(ts.clone().into_string(), "<2024-02-29 Thu -2d>");
+    ts.shift_months(-12);
+    assert_eq!(ts.clone().into_string(), "<2023-02-28 Tue -2d>");
+    ts.shift(Duration::try_hours(-1).unwrap());
+    assert_eq!(ts.into_string(), "<2023-02-27 Mon -2d>");
+}
+
+#[test]
+fn timestamp_duration_should_cover_range() {
+    let duration = |ts| Timestamp::from_str(ts).unwrap().duration();
+    assert_eq!(duration("<2024-05-01 Wed>"), None);
+    assert_eq!(
+        duration("<2024-05-01 Wed 09:00-10:30>"),
+        Some(Duration::try_minutes(90).unwrap())
+    );
+    assert_eq!(
+        duration("<2024-05-01 Wed>--<2024-05-03 Fri>"),
+        Some(Duration::try_days(3).unwrap())
+    );
+    assert_eq!(
+        duration("<2024-05-01 Wed 22:00>--<2024-05-02 Thu 01:00>"),
+        Some(Duration::try_hours(3).unwrap())
+    );
+}
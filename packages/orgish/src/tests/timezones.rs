@@ -0,0 +1,56 @@
+use super::*;
+use chrono::FixedOffset;
+
+#[test]
+fn timestamps_should_convert_between_timezones() {
+    let from = FixedOffset::east_opt(10 * 3600).unwrap();
+    let to = FixedOffset::west_opt(4 * 3600).unwrap();
+
+    let ts = Timestamp::from_str("<2024-05-01 Wed 09:00-10:30 +1w>").unwrap();
+    assert_eq!(
+        ts.start_in(&from).unwrap().to_rfc3339(),
+        "2024-05-01T09:00:00+10:00"
+    );
+    assert_eq!(
+        ts.into_timezone(&from, &to).into_string(),
+        "<2024-04-30 Tue 19:00-20:30 +1w>"
+    );
+
+    // Dates on their own aren't converted
+    let ts = Timestamp::from_str("<2024-05-01 Wed>").unwrap();
+    assert_eq!(
+        ts.into_timezone(&from, &to).into_string(),
+        "<2024-05-01 Wed>"
+    );
+}
+
+#[cfg(feature = "timezones")]
+#[test]
+fn timezones_should_be_read_from_documents_and_nodes() {
+    let document = Document::<CustomKeyword>::from_str(
+        r#"#+title: Meetings
+#+TIMEZONE: Australia/Sydney
+
+* Standup
+:PROPERTIES:
+:TZ: America/New_York
+:END:
+* Retro
+:PROPERTIES:
+:TZ: Mars/Olympus_Mons
+:END:"#,
+        Format::Org,
+    )
+    .unwrap();
+
+    assert_eq!(
+        document.timezone().unwrap().unwrap(),
+        chrono_tz::Australia::Sydney
+    );
+    let children = document.root.children();
+    assert_eq!(
+        children[0].timezone(Format::Org).unwrap().unwrap(),
+        chrono_tz::America::New_York
+    );
+    assert!(children[1].timezone(Format::Org).unwrap().is_err());
+}
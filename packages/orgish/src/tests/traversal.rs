@@ -0,0 +1,170 @@
+use super::*;
+use crate::traverse::{NodeMut, NodePath, VisitAction, Visitor, VisitorMut};
+
+fn document() -> Document<CustomKeyword> {
+    Document::from_str(
+        r#"* A
+** A1
+*** A1a
+** A2
+* B
+** B1
+"#,
+        Format::Org,
+    )
+    .unwrap()
+}
+
+#[test]
+fn depth_first_should_follow_document_order() {
+    let document = document();
+    let visited = document
+        .root
+        .depth_first()
+        .map(|(path, node)| (node.title.as_str(), path.titles, path.indices))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        visited,
+        [
+            ("A", vec![], vec![0]),
+            ("A1", vec!["A".to_string()], vec![0, 0]),
+            (
+                "A1a",
+                vec!["A".to_string(), "A1".to_string()],
+                vec![0, 0, 0]
+            ),
+            ("A2", vec!["A".to_string()], vec![0, 1]),
+            ("B", vec![], vec![1]),
+            ("B1", vec!["B".to_string()], vec![1, 0]),
+        ]
+    );
+}
+
+#[test]
+fn breadth_first_should_go_level_by_level() {
+    let document = document();
+    let visited = document
+        .root
+        .breadth_first()
+        .map(|(path, node)| (node.title.as_str(), path.depth()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        visited,
+        [
+            ("A", 1),
+            ("B", 1),
+            ("A1", 2),
+            ("A2", 2),
+            ("B1", 2),
+            ("A1a", 3)
+        ]
+    );
+}
+
+#[test]
+fn mutable_iterators_should_modify_nodes() {
+    let mut document = document();
+    for (path, node) in document.root.depth_first_mut() {
+        if path.depth() == 2 {
+            *node.keyword = Some(CustomKeyword::Todo);
+        }
+        // Paths use the titles from before they were changed
+        node.title
+            .push_str(&format!(" ({})", path.titles.join("/")));
+    }
+    for (_, node) in document.root.breadth_first_mut() {
+        let level = node.level();
+        node.tags.push(format!("level{level}"));
+    }
+
+    assert_eq!(
+        document.into_string(Format::Org),
+        r#"* A () :level1:
+** TODO A1 (A) :level2:
+*** A1a (A/A1) :level3:
+** TODO A2 (A) :level2:
+* B () :level1:
+** TODO B1 (B) :level2:"#
+    );
+}
+
+/// Records the titles of the nodes it enters and leaves, skipping the children of `A1`.
+#[derive(Default)]
+struct Recorder {
+    events: Vec<String>,
+    stop_at: Option<&'static str>,
+}
+impl Visitor<CustomKeyword, StringId, String> for Recorder {
+    fn enter(&mut self, node: &Node<CustomKeyword>, _path: &NodePath<String>) -> VisitAction {
+        self.events.push(format!("+{}", node.title));
+        if Some(node.title.as_str()) == self.stop_at {
+            VisitAction::Stop
+        } else if node.title == "A1" {
+            VisitAction::SkipChildren
+        } else {
+            VisitAction::Continue
+        }
+    }
+    fn leave(&mut self, node: &Node<CustomKeyword>, _path: &NodePath<String>) {
+        self.events.push(format!("-{}", node.title));
+    }
+}
+
+#[test]
+fn visitors_should_enter_leave_and_prune() {
+    let document = document();
+    let mut recorder = Recorder::default();
+    assert!(document.root.walk(&mut recorder));
+    assert_eq!(
+        recorder.events,
+        ["+A", "+A1", "-A1", "+A2", "-A2", "-A", "+B", "+B1", "-B1", "-B"]
+    );
+
+    let mut recorder = Recorder {
+        stop_at: Some("A2"),
+        ..Default::default()
+    };
+    assert!(!document.root.walk(&mut recorder));
+    assert_eq!(recorder.events, ["+A", "+A1", "-A1", "+A2"]);
+}
+
+/// Numbers the nodes it leaves, and marks those with children as projects.
+struct Numberer {
+    count: usize,
+}
+impl VisitorMut<CustomKeyword, StringId, String> for Numberer {
+    fn enter(
+        &mut self,
+        node: NodeMut<'_, CustomKeyword, StringId, String>,
+        path: &NodePath<String>,
+    ) -> VisitAction {
+        if path.depth() == 1 {
+            *node.keyword = Some(CustomKeyword::Proj);
+        }
+        VisitAction::Continue
+    }
+    fn leave(
+        &mut self,
+        node: NodeMut<'_, CustomKeyword, StringId, String>,
+        _path: &NodePath<String>,
+    ) {
+        self.count += 1;
+        node.title.push_str(&format!(" {}", self.count));
+    }
+}
+
+#[test]
+fn mutable_visitors_should_modify_nodes() {
+    let mut document = document();
+    let mut numberer = Numberer { count: 0 };
+    assert!(document.root.walk_mut(&mut numberer));
+    assert_eq!(
+        document.into_string(Format::Org),
+        r#"* PROJ A 4
+** A1 2
+*** A1a 1
+** A2 3
+* PROJ B 6
+** B1 5"#
+    );
+}
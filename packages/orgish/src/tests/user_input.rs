@@ -0,0 +1,81 @@
+use super::*;
+use chrono::NaiveDate;
+
+macro_rules! test_user_input {
+    ($name:ident, $input:literal => $output:literal) => {
+        #[test]
+        fn $name() {
+            // This is a Wednesday
+            let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+            let ts = Timestamp::from_user_input($input, today).unwrap();
+            assert_eq!(ts.into_string(), $output);
+        }
+    };
+}
+
+test_user_input!(offset_input_should_work, "+2d" => "<2024-05-03 Fri>");
+test_user_input!(negative_offset_input_should_work, "-1w" => "<2024-04-24 Wed>");
+test_user_input!(unsigned_offset_input_should_work, "3w" => "<2024-05-22 Wed>");
+test_user_input!(weekday_input_should_work, "fri" => "<2024-05-03 Fri>");
+test_user_input!(weekday_today_input_should_work, "Wednesday" => "<2024-05-01 Wed>");
+test_user_input!(next_weekday_input_should_work, "next wed 14:00" => "<2024-05-08 Wed 14:00>");
+test_user_input!(date_and_range_input_should_work, "2024-06-01 9:00-10:30" => "<2024-06-01 Sat 09:00-10:30>");
+test_user_input!(named_date_and_repeater_input_should_work, "tomorrow +1w" => "<2024-05-02 Thu +1w>");
+test_user_input!(time_only_input_should_work, "18:30" => "<2024-05-01 Wed 18:30>");
+test_user_input!(repeater_and_delay_input_should_work, "mon .+2d --1d" => "<2024-05-06 Mon .+2d --1d>");
+test_user_input!(timestamp_input_should_work, "[2024-06-01 Sat]" => "[2024-06-01 Sat]");
+
+#[test]
+fn invalid_user_input_should_fail() {
+    let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    assert!(Timestamp::from_user_input("", today).is_err());
+    assert!(Timestamp::from_user_input("soon", today).is_err());
+    assert!(Timestamp::from_user_input("fri mon", today).is_err());
+    assert!(Timestamp::from_user_input("++1w", today).is_err());
+    assert!(Timestamp::from_user_input("next week", today).is_err());
+    assert!(Timestamp::from_user_input("+2h", today).is_err());
+    assert!(Timestamp::from_user_input("25:00", today).is_err());
+}
+
+#[test]
+fn backwards_time_ranges_should_fail() {
+    use crate::error::TimestampInputError;
+
+    let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    assert!(matches!(
+        Timestamp::from_user_input("10:00-9:00", today),
+        Err(TimestampInputError::EndBeforeStart { token }) if token == "10:00-9:00"
+    ));
+    assert!(matches!(
+        Timestamp::from_user_input("fri 23:30-00:15", today),
+        Err(TimestampInputError::EndBeforeStart { .. })
+    ));
+    // Ranges that end as they start are fine
+    let ts = Timestamp::from_user_input("10:00-10:00", today).unwrap();
+    assert_eq!(ts.into_string(), "<2024-05-01 Wed 10:00-10:00>");
+}
+
+#[test]
+fn zero_repeaters_and_delays_should_fail() {
+    use crate::error::TimestampInputError;
+
+    let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    for input in [
+        "tomorrow +0d",
+        "fri .+0w",
+        "mon ++00m",
+        "mon -0d",
+        "mon --0w",
+    ] {
+        assert!(
+            matches!(
+                Timestamp::from_user_input(input, today),
+                Err(TimestampInputError::ZeroCount { .. })
+            ),
+            "{input} should fail"
+        );
+    }
+    // Zero offsets from today are still fine
+    let ts = Timestamp::from_user_input("+0d", today).unwrap();
+    assert_eq!(ts.into_string(), "<2024-05-01 Wed>");
+}
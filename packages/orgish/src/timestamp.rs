@@ -3,34 +3,119 @@
 //! creating new timestamps relative to a given date.
 
 use super::error::TimestampParseError;
-use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime};
 
 /// An abstraction over dates and times where the times are optional.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DateTime {
     pub date: NaiveDate,
     pub time: Option<NaiveTime>,
 }
 /// The repeater in a timestamp (e.g. `+1w`).
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Repeater {
     pub count: usize,
     pub unit: RepeaterUnit,
+    pub kind: RepeaterKind,
+    /// The longest the item should go between repeats, for Org mode habits (e.g. the `3d` in
+    /// `.+2d/3d`). This is kept so it can be written back, but it doesn't affect how the
+    /// timestamp repeats.
+    pub habit_max: Option<(usize, RepeaterUnit)>,
 }
 impl Repeater {
-    /// Converts this repeater into its mode representation (e.g. `+10d`).
+    /// Converts this repeater into its mode representation (e.g. `+10d` or `.+2d/3d`).
     fn into_string(self) -> String {
-        format!("+{}{}", self.count, self.unit.into_char())
+        let mark = match self.kind {
+            RepeaterKind::Cumulate => "+",
+            RepeaterKind::CatchUp => "++",
+            RepeaterKind::Restart => ".+",
+        };
+        let habit_max = self
+            .habit_max
+            .map(|(count, unit)| format!("/{count}{}", unit.into_char()))
+            .unwrap_or_default();
+        format!("{mark}{}{}{habit_max}", self.count, self.unit.into_char())
     }
+    /// Gets the datetime one interval of this repeater after the given datetime. If this would
+    /// fall outside the bounds of a month (e.g. monthly from the 31st), the last day of that month
+    /// will be used.
+    pub(crate) fn after(&self, datetime: NaiveDateTime) -> Option<NaiveDateTime> {
+        let count = self.count as u32;
+        match self.unit {
+            RepeaterUnit::Hour => datetime.checked_add_signed(Duration::try_hours(count as i64)?),
+            RepeaterUnit::Day => datetime.checked_add_days(Days::new(count as u64)),
+            RepeaterUnit::Week => datetime.checked_add_days(Days::new(count as u64 * 7)),
+            RepeaterUnit::Month => datetime.checked_add_months(Months::new(count)),
+            RepeaterUnit::Year => datetime.checked_add_months(Months::new(count * 12)),
+        }
+    }
+}
+/// The different ways a repeater can progress when the item it's attached to is completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RepeaterKind {
+    /// The timestamp is moved forward by exactly one interval, regardless of when the item was
+    /// completed (written as `+`).
+    Cumulate,
+    /// The timestamp is moved forward by as many intervals as are needed to put it after the
+    /// completion date, keeping it aligned to the original date (e.g. on the same weekday). This
+    /// is written as `++`.
+    CatchUp,
+    /// The timestamp is moved to one interval after the completion date (written as `.+`).
+    Restart,
+}
+/// A warning period or delay in a timestamp (e.g. `-3d`). On a deadline, this is how long before
+/// the deadline warnings should start to appear. On a scheduled item, this is how long after the
+/// scheduled date the item should first appear.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delay {
+    pub count: usize,
+    pub unit: RepeaterUnit,
+    pub kind: DelayKind,
+}
+impl Delay {
+    /// Converts this delay into its mode representation (e.g. `-3d` or `--2d`).
+    fn into_string(self) -> String {
+        let dashes = match self.kind {
+            DelayKind::All => "-",
+            DelayKind::First => "--",
+        };
+        format!("{dashes}{}{}", self.count, self.unit.into_char())
+    }
+    /// Gets the date this delay before the given date (i.e. where a warning period ending on the
+    /// given date would start).
+    pub(crate) fn before(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let count = self.count as u32;
+        match self.unit {
+            // We only deal in dates here, so only whole days of hours count
+            RepeaterUnit::Hour => date.checked_sub_days(Days::new(count as u64 / 24)),
+            RepeaterUnit::Day => date.checked_sub_days(Days::new(count as u64)),
+            RepeaterUnit::Week => date.checked_sub_days(Days::new(count as u64 * 7)),
+            RepeaterUnit::Month => date.checked_sub_months(Months::new(count)),
+            RepeaterUnit::Year => date.checked_sub_months(Months::new(count * 12)),
+        }
+    }
+}
+/// Which occurrences of a repeating timestamp a [`Delay`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DelayKind {
+    /// The delay applies to every occurrence (written as `-`).
+    All,
+    /// The delay applies only to the first occurrence (written as `--`). This is only meaningful
+    /// for scheduled items.
+    First,
 }
 /// The different units for repeaters.
 // TODO Org's documentation doesn't list all possible repeaters, so I am literally
 // guessing here given I can't look at the source!
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RepeaterUnit {
+    Hour,
     Day,
     Week,
     Month,
@@ -38,8 +123,9 @@ pub enum RepeaterUnit {
 }
 impl RepeaterUnit {
     /// Converts the given character into a repeater unit if possible.
-    fn from_char(c: char) -> Option<Self> {
+    pub(crate) fn from_char(c: char) -> Option<Self> {
         match c {
+            'h' => Some(Self::Hour),
             'd' => Some(Self::Day),
             'w' => Some(Self::Week),
             'm' => Some(Self::Month),
@@ -51,6 +137,7 @@ impl RepeaterUnit {
     /// represent it.
     fn into_char(self) -> char {
         match self {
+            Self::Hour => 'h',
             Self::Day => 'd',
             Self::Week => 'w',
             Self::Month => 'm',
@@ -68,7 +155,14 @@ impl RepeaterUnit {
 /// `<2023-01-01 Sun 9:00>--<2023-01-01 Sun 10:00>` would be, because the start and end
 /// are on the same day, be simplified when the timestamp is written back to a string as
 /// `<2023-01-01 Sun 9:00-10:00>`.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// # Ordering
+///
+/// Timestamps are ordered by their start dates, and then by their start times, with untimed
+/// timestamps coming before timed ones on the same date. Any further ties are broken by the end,
+/// then the repeater, then the delay, and then whether or not the timestamp is active (which is
+/// the order of the fields below, so it must be kept).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timestamp {
     /// The date (and optional time) that the timestamp begins at. If it has only one
@@ -78,6 +172,8 @@ pub struct Timestamp {
     pub end: Option<DateTime>,
     /// An expression indicating how, if at all, the timestamp should repeat over time.
     pub repeater: Option<Repeater>,
+    /// A warning period (for deadlines) or delay (for scheduled items), if there is one.
+    pub delay: Option<Delay>,
     /// Whether or not the timestamp is active.
     pub active: bool,
 }
@@ -86,6 +182,7 @@ impl Timestamp {
     pub fn includes_date(&self, date: NaiveDate) -> bool {
         if let Some(repeater) = &self.repeater {
             match repeater.unit {
+                RepeaterUnit::Hour => !self.hourly_occurrences_on(date, repeater.count).is_empty(),
                 RepeaterUnit::Day => {
                     // For checking if the day is a repeat, just turn the end and target dates into
                     // a number of days since the start date, and then check if the target is in
@@ -240,7 +337,17 @@ impl Timestamp {
     /// should use when querying about timestamps, as it contains a large deal of information. However, computing
     /// this for range timestamps is slightly more expensive, so [`Self::includes_date`] should be preferred
     /// where the additional information is not required immediately.
+    ///
+    /// Timestamps with hourly repeaters can apply several times on a single date, in which case
+    /// this will give the first of these: use [`Self::applies_all`] to get all of them.
     pub fn applies(&self, date: NaiveDate) -> TimestampApplies {
+        if let Some(count) = self.hourly_repeat() {
+            return self
+                .hourly_applies(date, count)
+                .into_iter()
+                .next()
+                .unwrap_or(TimestampApplies::None);
+        }
         if !self.includes_date(date) {
             return TimestampApplies::None;
         }
@@ -255,16 +362,15 @@ impl Timestamp {
             } else if self.start.date == end.date {
                 // If we have a timestamp with start and end dates, we can figure the application
                 // out easily
-                if self.start.time.is_some() && end.time.is_some() {
-                    TimestampApplies::Block(self.start.time.unwrap(), end.time.unwrap())
-                } else if self.start.time.is_some() {
-                    TimestampApplies::Start(self.start.time.unwrap())
-                } else if end.time.is_some() {
+                match (self.start.time, end.time) {
+                    (Some(start_time), Some(end_time)) => {
+                        TimestampApplies::Block(start_time, end_time)
+                    }
+                    (Some(start_time), None) => TimestampApplies::Start(start_time),
                     // This would be expressed by a range timestamp
-                    TimestampApplies::End(end.time.unwrap())
-                } else {
+                    (None, Some(end_time)) => TimestampApplies::End(end_time),
                     // Redundant, handled by the previous branch, but here to appease the compiler
-                    TimestampApplies::AllDay
+                    (None, None) => TimestampApplies::AllDay,
                 }
             } else {
                 // We have a range timestamp that goes over multiple days
@@ -272,12 +378,14 @@ impl Timestamp {
                     start: self.start.clone(),
                     end: None,
                     repeater: self.repeater.clone(),
+                    delay: None,
                     active: self.active,
                 };
                 let end_only_ts = Timestamp {
                     start: end.clone(),
                     end: None,
                     repeater: self.repeater.clone(),
+                    delay: None,
                     active: self.active,
                 };
 
@@ -309,6 +417,115 @@ impl Timestamp {
             }
         }
     }
+    /// Returns every period at which this timestamp applies to the given date, in order. This will
+    /// only contain more than one entry for timestamps with hourly repeaters, which can occur
+    /// several times in a single day. If the timestamp doesn't apply to this date at all, this will
+    /// be empty.
+    pub fn applies_all(&self, date: NaiveDate) -> Vec<TimestampApplies> {
+        if let Some(count) = self.hourly_repeat() {
+            return self.hourly_applies(date, count);
+        }
+
+        match self.applies(date) {
+            TimestampApplies::None => Vec::new(),
+            applies => vec![applies],
+        }
+    }
+    /// Gets the count of this timestamp's repeater, if it repeats by the hour.
+    fn hourly_repeat(&self) -> Option<usize> {
+        self.repeater
+            .as_ref()
+            .filter(|repeater| repeater.unit == RepeaterUnit::Hour)
+            .map(|repeater| repeater.count)
+    }
+    /// Gets the start and end datetimes of this timestamp. Untimed timestamps are treated as
+    /// starting at midnight, and an end without a time is treated as having the start time.
+    fn datetimes(&self) -> (NaiveDateTime, NaiveDateTime) {
+        let start_time = self.start.time.unwrap_or(NaiveTime::MIN);
+        let start = self.start.date.and_time(start_time);
+        let end = match &self.end {
+            Some(end) => end.date.and_time(end.time.unwrap_or(start_time)),
+            None => start,
+        };
+        (start, end)
+    }
+    /// Gets the start and end datetimes of every occurrence of this timestamp that overlaps the
+    /// given date, assuming it repeats every `count` hours. This handles occurrences that cross
+    /// midnight from the previous day.
+    fn hourly_occurrences_on(
+        &self,
+        date: NaiveDate,
+        count: usize,
+    ) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        // We work in minutes, and make sure a zero-hour repeater can't loop forever
+        let period = (count as i64 * 60).max(1);
+        let (start, end) = self.datetimes();
+        let length = (end - start).num_minutes().max(0);
+        let day_start = date.and_time(NaiveTime::MIN);
+        let day_end = day_start + Duration::try_days(1).unwrap();
+
+        // Skip straight to the first occurrence that hasn't ended before this day starts
+        let behind = (day_start - start).num_minutes() - length;
+        let mut repeats = if behind > 0 {
+            (behind + period - 1) / period
+        } else {
+            0
+        };
+        let mut occurrences = Vec::new();
+        loop {
+            let occurrence_start = start + Duration::try_minutes(repeats * period).unwrap();
+            if occurrence_start >= day_end {
+                break;
+            }
+            occurrences.push((
+                occurrence_start,
+                occurrence_start + Duration::try_minutes(length).unwrap(),
+            ));
+            repeats += 1;
+        }
+
+        occurrences
+    }
+    /// Gets the start of the first occurrence of this timestamp on or after the start of the given
+    /// date, assuming it repeats every `count` hours.
+    fn next_hourly_start(&self, after_date: NaiveDate, count: usize) -> Option<NaiveDateTime> {
+        let period = (count as i64 * 60).max(1);
+        let (start, _) = self.datetimes();
+        let minutes_diff = (after_date.and_time(NaiveTime::MIN) - start).num_minutes();
+        if minutes_diff <= 0 {
+            Some(start)
+        } else {
+            let repeats = (minutes_diff + period - 1) / period;
+            start.checked_add_signed(Duration::try_minutes(repeats * period)?)
+        }
+    }
+    /// Works out how each occurrence of this timestamp on the given date applies to it, assuming
+    /// it repeats every `count` hours.
+    fn hourly_applies(&self, date: NaiveDate, count: usize) -> Vec<TimestampApplies> {
+        let occurrences = self.hourly_occurrences_on(date, count);
+        // Without any times, all we can say is that the timestamp applies all day
+        if self.start.time.is_none() && self.end.as_ref().is_none_or(|end| end.time.is_none()) {
+            return if occurrences.is_empty() {
+                Vec::new()
+            } else {
+                vec![TimestampApplies::AllDay]
+            };
+        }
+
+        occurrences
+            .into_iter()
+            .map(
+                |(start, end)| match (start.date() == date, end.date() == date) {
+                    (true, true) if self.end.is_some() => {
+                        TimestampApplies::Block(start.time(), end.time())
+                    }
+                    (true, _) => TimestampApplies::Start(start.time()),
+                    (false, true) => TimestampApplies::End(end.time()),
+                    (false, false) => TimestampApplies::AllDay,
+                },
+            )
+            .collect()
+    }
     /// Gets the next date after the given date on which this timestamp will repeat. This is
     /// calculated by advancing the original date of the timestamp by its repeater until a date
     /// after `after_date` is reached.
@@ -329,11 +546,14 @@ impl Timestamp {
 
         let repeater = self.repeater.as_ref()?;
         match repeater.unit {
+            RepeaterUnit::Hour => self
+                .next_hourly_start(after_date, repeater.count)
+                .map(|start| start.date()),
             RepeaterUnit::Day => {
                 // Guaranteed to be positive because of the earlier sanity check
                 let days_diff = (after_date - date).num_days() as usize;
                 // If we're on a repeat, today is the day
-                if days_diff % repeater.count == 0 {
+                if days_diff.is_multiple_of(repeater.count) {
                     Some(after_date)
                 } else {
                     // We'll increment by one more than the number of completed repeats
@@ -349,7 +569,7 @@ impl Timestamp {
                 // Guaranteed to be positive because of the earlier sanity check
                 let days_diff = (after_date - date).num_days() as usize;
                 // If we're on a repeat, today is the day
-                if days_diff % repeater_days_count == 0 {
+                if days_diff.is_multiple_of(repeater_days_count) {
                     Some(after_date)
                 } else {
                     // We'll increment by one more than the number of completed repeats
@@ -379,7 +599,7 @@ impl Timestamp {
                     date.day(),
                 )
                 .unwrap();
-                if months_diff % repeater.count as u32 == 0 {
+                if months_diff.is_multiple_of(repeater.count as u32) {
                     // We're in the right month, but now the day index matters
                     if after_date.day0() > date.day0() {
                         // We're after the repeat in this month, go to the next repeat
@@ -411,7 +631,7 @@ impl Timestamp {
                     date_years + (years_diff / repeater.count as u32 + 1) * repeater.count as u32;
                 let next_date =
                     NaiveDate::from_ymd_opt(next_years as i32, date.month(), date.day()).unwrap();
-                if years_diff % repeater.count as u32 == 0 {
+                if years_diff.is_multiple_of(repeater.count as u32) {
                     // We're in the right year, but now the month and day matter (we can't use ordinals
                     // because of leap years); this is a simple way of doing the comparisons all in one
                     if after_date.month0() * 100 + after_date.day0()
@@ -448,6 +668,151 @@ impl Timestamp {
             }
         }
     }
+    /// Returns an iterator over every occurrence of this timestamp that overlaps the given date span
+    /// (inclusive), in order. Each occurrence has concrete start and end dates, with times where
+    /// this timestamp has them (timestamps with hourly repeaters will always have times).
+    ///
+    /// This follows the same rules as [`Self::get_next_repeat`], so a monthly repeat on a day that
+    /// doesn't exist in some month (e.g. the 31st) will skip that month entirely. Non-repeating
+    /// timestamps will produce at most one occurrence.
+    pub fn occurrences(&self, from: NaiveDate, to: NaiveDate) -> Occurrences<'_> {
+        let (start, end) = self.datetimes();
+        // Skip straight to the last repeat that definitely ends before `from`, so we don't have to
+        // step through every repeat since the original date
+        let first_index = match &self.repeater {
+            Some(repeater) => {
+                let count = (repeater.count as i64).max(1);
+                let behind = match repeater.unit {
+                    RepeaterUnit::Hour => {
+                        let behind = (from.and_time(NaiveTime::MIN) - start).num_minutes()
+                            - (end - start).num_minutes();
+                        behind / (count * 60)
+                    }
+                    RepeaterUnit::Day | RepeaterUnit::Week => {
+                        let days = if repeater.unit == RepeaterUnit::Week {
+                            count * 7
+                        } else {
+                            count
+                        };
+                        let behind = (from - start.date()).num_days()
+                            - (end.date() - start.date()).num_days();
+                        behind / days
+                    }
+                    RepeaterUnit::Month => {
+                        let months =
+                            |date: NaiveDate| date.year() as i64 * 12 + date.month0() as i64;
+                        let behind = months(from)
+                            - months(start.date())
+                            - (months(end.date()) - months(start.date()))
+                            - 1;
+                        behind / count
+                    }
+                    RepeaterUnit::Year => {
+                        let behind = (from.year() - start.year()) as i64
+                            - (end.year() - start.year()) as i64
+                            - 1;
+                        behind / count
+                    }
+                };
+                behind.max(0)
+            }
+            None => 0,
+        };
+
+        Occurrences {
+            timestamp: self,
+            index: first_index,
+            from,
+            to,
+            done: false,
+        }
+    }
+    /// Gets the `n`th repeat of this timestamp (where the original is the zeroth), ignoring its
+    /// end. This returns the earliest date that repeat could start on (used to know when to stop
+    /// iterating), along with the actual start, which will be `None` if the repeat doesn't exist
+    /// (e.g. a monthly repeat on the 31st in a month with 30 days). If the repeat can't be
+    /// represented at all, this will return `None`.
+    fn nth_repeat(&self, n: i64) -> Option<(NaiveDate, Option<DateTime>)> {
+        let repeater = match &self.repeater {
+            // A zero-length repeater would land on the original forever, so it can only occur once
+            Some(repeater) if repeater.count > 0 => repeater,
+            _ if n == 0 => return Some((self.start.date, Some(self.start.clone()))),
+            _ => return None,
+        };
+        let steps = n.checked_mul(repeater.count as i64)?;
+        let date = self.start.date;
+        let in_month = |year: i32, month0: u32| {
+            let earliest = NaiveDate::from_ymd_opt(year, month0 + 1, 1)?;
+            let start =
+                NaiveDate::from_ymd_opt(year, month0 + 1, date.day()).map(|date| DateTime {
+                    date,
+                    time: self.start.time,
+                });
+            Some((earliest, start))
+        };
+
+        match repeater.unit {
+            RepeaterUnit::Hour => {
+                let start = self
+                    .datetimes()
+                    .0
+                    .checked_add_signed(Duration::try_hours(steps)?)?;
+                Some((
+                    start.date(),
+                    Some(DateTime {
+                        date: start.date(),
+                        time: Some(start.time()),
+                    }),
+                ))
+            }
+            RepeaterUnit::Day | RepeaterUnit::Week => {
+                let days = if repeater.unit == RepeaterUnit::Week {
+                    steps.checked_mul(7)?
+                } else {
+                    steps
+                };
+                let start = date.checked_add_days(Days::new(days as u64))?;
+                Some((
+                    start,
+                    Some(DateTime {
+                        date: start,
+                        time: self.start.time,
+                    }),
+                ))
+            }
+            RepeaterUnit::Month => {
+                let months = (date.year() as i64 * 12 + date.month0() as i64).checked_add(steps)?;
+                in_month(i32::try_from(months / 12).ok()?, (months % 12) as u32)
+            }
+            RepeaterUnit::Year => {
+                let year = i32::try_from((date.year() as i64).checked_add(steps)?).ok()?;
+                in_month(year, date.month0())
+            }
+        }
+    }
+    /// Determines whether or not the given date is within the warning period of this timestamp,
+    /// treating it as a deadline. This will be the case if the date is on or before the deadline
+    /// (or its next repeat), but no further before it than the warning period (e.g. `-3d`). If
+    /// the timestamp doesn't specify a warning period, the given default number of days will be
+    /// used (Org mode uses 14 days by default).
+    ///
+    /// Once a deadline has passed, its date will no longer be in the warning period (unless it
+    /// repeats, in which case the next repeat will be considered).
+    pub fn is_within_warning_period(&self, date: NaiveDate, default_days: u32) -> bool {
+        // The deadline we're warning about is the next one on or after the given date
+        let deadline = match date.pred_opt() {
+            Some(day_before) => self.get_next_repeat(day_before),
+            None => Some(self.start.date),
+        }
+        .unwrap_or(self.start.date);
+        let warning_start = match &self.delay {
+            Some(delay) => delay.before(deadline),
+            None => deadline.checked_sub_days(Days::new(default_days as u64)),
+        }
+        .unwrap_or(NaiveDate::MIN);
+
+        warning_start <= date && date <= deadline
+    }
     /// Converts this timestamp into the next repeat of itself, or its original self if there is no
     /// repeat.
     ///
@@ -455,12 +820,52 @@ impl Timestamp {
     /// computing the distance between the end date and the start date, and adding this on to the
     /// new start date from [`Self::get_next_repeat`].
     pub fn into_next_repeat_after(self, after_date: NaiveDate) -> Result<Self, Self> {
+        // Hourly repeats need their times to change as well
+        if let Some(count) = self.hourly_repeat() {
+            return match self.next_hourly_start(after_date, count) {
+                Some(next_start) if after_date > self.start.date => {
+                    Ok(self.into_moved_to_datetime(next_start))
+                }
+                Some(_) => Ok(self),
+                None => Err(self),
+            };
+        }
+
         // Verbose to avoid later moved value errors
         let next_repeat = match self.get_next_repeat(after_date) {
             Some(r) => r,
             None => return Err(self),
         };
 
+        Ok(self.into_moved_to(next_repeat))
+    }
+    /// Converts this timestamp into a copy of itself starting at the given datetime, moving the
+    /// end (if there is one) by the same amount. Unlike [`Self::into_moved_to`], this will give
+    /// the timestamp a time if it doesn't already have one.
+    fn into_moved_to_datetime(self, next_start: NaiveDateTime) -> Self {
+        let (start, end) = self.datetimes();
+        let next_end = self.end.map(|_| {
+            let next_end = next_start + (end - start);
+            DateTime {
+                date: next_end.date(),
+                time: Some(next_end.time()),
+            }
+        });
+
+        Timestamp {
+            start: DateTime {
+                date: next_start.date(),
+                time: Some(next_start.time()),
+            },
+            end: next_end,
+            repeater: self.repeater,
+            delay: self.delay,
+            active: self.active,
+        }
+    }
+    /// Converts this timestamp into a copy of itself starting on the given date, moving the end
+    /// date (if there is one) by the same amount.
+    fn into_moved_to(self, next_repeat: NaiveDate) -> Self {
         let next_end = if let Some(end) = self.end {
             Some(DateTime {
                 date: next_repeat
@@ -471,15 +876,16 @@ impl Timestamp {
             None
         };
 
-        Ok(Timestamp {
+        Timestamp {
             start: DateTime {
                 date: next_repeat,
                 time: self.start.time,
             },
             end: next_end,
             repeater: self.repeater,
+            delay: self.delay,
             active: self.active,
-        })
+        }
     }
     /// Converts this timestamp into the next repeat of itself. This is the same as calling
     /// [`Self::into_next_repeat_after`] with the date one day after the current date on this
@@ -489,21 +895,92 @@ impl Timestamp {
     /// This is useful for mimicking the behaviour of Org mode when an entry is marked as `DONE`
     /// and timestamps need to be progressed to their next repeats (if a deadline has not yet been
     /// reached, it will still need to be progressed).
+    ///
+    /// Note that this treats every repeater as a cumulate (`+`) repeater, since the other kinds
+    /// depend on when the item was completed: see [`Self::into_next_repeat_from`] for that.
+    ///
+    /// Timestamps with hourly repeaters will be moved forward by exactly one interval, which may
+    /// leave them on the same date, and will always be given a time.
     pub fn into_next_repeat(self) -> Result<Self, Self> {
+        if let Some(count) = self.hourly_repeat() {
+            let (start, _) = self.datetimes();
+            return match start.checked_add_signed(Duration::try_hours(count as i64).unwrap()) {
+                Some(next_start) => Ok(self.into_moved_to_datetime(next_start)),
+                None => Err(self),
+            };
+        }
+
         let date_one_after = self.start.date + Duration::try_days(1).unwrap();
         self.into_next_repeat_after(date_one_after)
     }
+    /// Converts this timestamp into its next repeat, given that the item it belongs to was
+    /// completed at the given time. This respects the kind of the repeater:
+    ///
+    /// - `+` (cumulate) repeaters are moved forward by exactly one interval, as in
+    ///   [`Self::into_next_repeat`];
+    /// - `++` (catch-up) repeaters are moved forward by whole intervals until they fall after the
+    ///   completion date (or, for hourly repeaters, the completion time);
+    /// - `.+` (restart) repeaters are moved to one interval after the completion date (or, for
+    ///   hourly repeaters, the completion time).
+    ///
+    /// If this timestamp has no repeat, it will be returned as an error.
+    pub fn into_next_repeat_from(self, completion: NaiveDateTime) -> Result<Self, Self> {
+        let repeater = match &self.repeater {
+            Some(repeater) => repeater,
+            None => return Err(self),
+        };
+        let hourly = repeater.unit == RepeaterUnit::Hour;
+        match repeater.kind {
+            RepeaterKind::Cumulate => self.into_next_repeat(),
+            RepeaterKind::CatchUp if hourly => {
+                // Work out how many intervals put us after the completion time directly (always
+                // moving by at least one, even if the timestamp is already in the future)
+                let (start, _) = self.datetimes();
+                let hours = (repeater.count as i64).max(1);
+                let steps = ((completion - start).num_minutes() / (hours * 60) + 1).max(1);
+                let next_start = steps
+                    .checked_mul(hours)
+                    .and_then(Duration::try_hours)
+                    .and_then(|duration| start.checked_add_signed(duration));
+                match next_start {
+                    Some(next_start) => Ok(self.into_moved_to_datetime(next_start)),
+                    None => Err(self),
+                }
+            }
+            RepeaterKind::CatchUp => {
+                // We always move by at least one interval, even if the timestamp is already in
+                // the future
+                let after_date =
+                    self.start.date.max(completion.date()) + Duration::try_days(1).unwrap();
+                self.into_next_repeat_after(after_date)
+            }
+            RepeaterKind::Restart if hourly => match repeater.after(completion) {
+                Some(next_start) => Ok(self.into_moved_to_datetime(next_start)),
+                None => Err(self),
+            },
+            RepeaterKind::Restart => {
+                let completion = completion.date().and_time(self.datetimes().0.time());
+                match repeater.after(completion) {
+                    Some(next_start) => Ok(self.into_moved_to(next_start.date())),
+                    None => Err(self),
+                }
+            }
+        }
+    }
 }
 impl Timestamp {
     /// Parses a timestamp from the given string.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(raw: &str) -> Result<Self, TimestampParseError> {
         let raw = raw.trim();
 
-        // Handle range timestamps recursively
-        if raw.contains("--") {
-            // We know there must be exactly two components because we know the range delimiter
-            // exists
-            let range_parts = raw.splitn(2, "--").collect::<Vec<_>>();
+        // Handle range timestamps recursively (`--` can also appear in delays, so we make sure
+        // it's between two timestamps)
+        let range_delimiter = raw.match_indices("--").find(|(idx, _)| {
+            raw[..*idx].ends_with(['>', ']']) && raw[idx + 2..].starts_with(['<', '['])
+        });
+        if let Some((idx, _)) = range_delimiter {
+            let range_parts = [&raw[..idx], &raw[idx + 2..]];
             let start_ts = Self::from_str(range_parts[0])?;
             let end_ts = Self::from_str(range_parts[1])?;
 
@@ -513,11 +990,19 @@ impl Timestamp {
                 Err(TimestampParseError::RangeInRange {
                     timestamp: raw.to_string(),
                 })
+            } else if (end_ts.repeater.is_some() || end_ts.delay.is_some())
+                && (end_ts.repeater != start_ts.repeater || end_ts.delay != start_ts.delay)
+            {
+                // We can only keep one repeater and delay, so the end can only repeat the start's
+                Err(TimestampParseError::RangeEndModifiers {
+                    timestamp: raw.to_string(),
+                })
             } else {
                 Ok(Self {
                     start: start_ts.start,
                     end: Some(end_ts.start),
                     repeater: start_ts.repeater,
+                    delay: start_ts.delay,
                     // It will be active if either component is
                     active: start_ts.active || end_ts.active,
                 })
@@ -549,7 +1034,7 @@ impl Timestamp {
         };
         // We can safely strip the boundary characters (`<>` or `[]`)
         // NOTE: This is all valid ASCII, and has at least 10 elements
-        let mut raw = (&raw[1..raw.len() - 1]).to_string();
+        let mut raw = raw[1..raw.len() - 1].to_string();
 
         // Get out the date component first (we've guaranteed this won't panic in the earlier length check)
         let remaining = raw.split_off(10);
@@ -582,14 +1067,21 @@ impl Timestamp {
             start: DateTime { date, time: None },
             end: None,
             repeater: None,
+            delay: None,
             active,
         };
 
         let chars = remaining.chars().collect::<Vec<_>>();
         // Used to keep track of the length of the day name
         let mut day_name = String::new();
-        // This will consist solely of numeric characters
+        // These will consist solely of numeric characters
         let mut repeater_count = String::new();
+        // The `+`, `++`, or `.+` before the repeater count
+        let mut repeater_mark = String::new();
+        // The count of the maximum interval of a habit (after the `/`), if there is one
+        let mut habit_count = String::new();
+        let mut delay_count = String::new();
+        let mut delay_kind = DelayKind::All;
         let mut has_end_time = false;
         let mut start_time = String::new();
         let mut end_time = String::new();
@@ -614,9 +1106,14 @@ impl Timestamp {
                         // We have a time, parse this first character again
                         loc = TimestampLocation::Time;
                         continue;
-                    } else if c == '+' {
-                        // We have a repeater (but we don't need to parse the `+` again)
+                    } else if c == '+' || c == '.' {
+                        // We have a repeater, parse this first character again (it tells us the
+                        // repeater kind)
                         loc = TimestampLocation::Repeater;
+                        continue;
+                    } else if c == '-' {
+                        // Same for a delay
+                        loc = TimestampLocation::Delay;
                     } else {
                         return Err(TimestampParseError::BadCharacter { c });
                     }
@@ -627,10 +1124,11 @@ impl Timestamp {
                         if let Some(next_c) = next_c {
                             if next_c.is_numeric() {
                                 loc = TimestampLocation::Time;
-                            } else if *next_c == '+' {
-                                // As above, we don't need to parse the `+` (but we're looking at `next_c`, so
-                                // increment here and then again later on)
+                            } else if *next_c == '+' || *next_c == '.' {
+                                // The repeater will parse its own mark as the next character
                                 loc = TimestampLocation::Repeater;
+                            } else if *next_c == '-' {
+                                loc = TimestampLocation::Delay;
                                 i += 1;
                             } else {
                                 return Err(TimestampParseError::BadCharacter { c });
@@ -650,11 +1148,14 @@ impl Timestamp {
                 }
                 TimestampLocation::Time => {
                     if c == ' ' {
-                        // End of the time, if we have anything, it has to be a repeater
+                        // End of the time, if we have anything, it has to be a repeater or delay
                         if let Some(next_c) = next_c {
-                            if *next_c == '+' {
-                                // As above, we don't need to parse the `+`
+                            if *next_c == '+' || *next_c == '.' {
+                                // As above, the repeater will parse its own mark
                                 loc = TimestampLocation::Repeater;
+                            } else if *next_c == '-' {
+                                loc = TimestampLocation::Delay;
+                                i += 1;
                             } else {
                                 return Err(TimestampParseError::BadCharacter { c });
                             }
@@ -674,28 +1175,136 @@ impl Timestamp {
                         return Err(TimestampParseError::BadCharacter { c });
                     }
                 }
-                TimestampLocation::Repeater => {
-                    if c.is_numeric() {
+                TimestampLocation::Repeater if timestamp.repeater.is_none() => {
+                    if (c == '+' || c == '.') && repeater_count.is_empty() {
+                        // Part of the mark before the count (we'll validate this with the unit)
+                        repeater_mark.push(c);
+                    } else if c.is_numeric() {
                         // We have a number
                         repeater_count.push(c);
                     } else if c.is_alphabetic() {
-                        // We've reached the unit, parse the count first (this will only consist of numeric characters)
-                        let repeater_count_num = repeater_count.parse::<usize>().unwrap();
+                        // We've reached the unit, parse the count first (this will only consist of
+                        // numeric characters, but it might be empty or too large)
+                        let repeater_count_num = parse_repeater_count(&repeater_count)?;
+                        let kind = match repeater_mark.as_str() {
+                            "+" => RepeaterKind::Cumulate,
+                            "++" => RepeaterKind::CatchUp,
+                            ".+" => RepeaterKind::Restart,
+                            _ => {
+                                return Err(TimestampParseError::BadRepeaterMark {
+                                    mark: repeater_mark,
+                                })
+                            }
+                        };
                         if let Some(unit) = RepeaterUnit::from_char(c) {
                             let repeater = Repeater {
                                 count: repeater_count_num,
                                 unit,
+                                kind,
+                                habit_max: None,
                             };
                             timestamp.repeater = Some(repeater);
                         } else {
                             return Err(TimestampParseError::BadRepeaterUnit { c });
                         }
+                    } else {
+                        return Err(TimestampParseError::BadCharacter { c });
+                    }
+                }
+                TimestampLocation::Repeater => {
+                    // We've parsed the whole repeater, all that can come after it is the maximum
+                    // interval of a habit, or a delay
+                    let has_habit_max = timestamp
+                        .repeater
+                        .as_ref()
+                        .is_some_and(|repeater| repeater.habit_max.is_some());
+                    if c == '/' && !has_habit_max {
+                        loc = TimestampLocation::HabitMax;
+                    } else if c == ' ' && next_c == Some(&'-') && timestamp.delay.is_none() {
+                        // A delay can come after the repeater
+                        loc = TimestampLocation::Delay;
+                        i += 1;
+                    } else {
+                        return Err(TimestampParseError::BadCharacter { c });
+                    }
+                }
+                TimestampLocation::HabitMax => {
+                    if c.is_numeric() {
+                        habit_count.push(c);
+                    } else if c.is_alphabetic() {
+                        let count = parse_repeater_count(&habit_count)?;
+                        let unit = RepeaterUnit::from_char(c)
+                            .ok_or(TimestampParseError::BadRepeaterUnit { c })?;
+                        // We only get here after parsing a repeater
+                        timestamp.repeater.as_mut().unwrap().habit_max = Some((count, unit));
+                        loc = TimestampLocation::Repeater;
+                    } else {
+                        return Err(TimestampParseError::BadCharacter { c });
+                    }
+                }
+                TimestampLocation::Delay => {
+                    // Nothing but a repeater can come after the unit of the delay
+                    let in_delay = timestamp.delay.is_none();
+                    if c == '-'
+                        && in_delay
+                        && delay_count.is_empty()
+                        && delay_kind == DelayKind::All
+                    {
+                        // A second dash means the delay only applies to the first repeat
+                        delay_kind = DelayKind::First;
+                    } else if c.is_numeric() && in_delay {
+                        delay_count.push(c);
+                    } else if c.is_alphabetic() && in_delay && !delay_count.is_empty() {
+                        let unit = RepeaterUnit::from_char(c)
+                            .ok_or(TimestampParseError::BadRepeaterUnit { c })?;
+                        // This consists only of numeric characters, but it might be too large
+                        let count = delay_count.parse().map_err(|_| {
+                            TimestampParseError::InvalidDelayCount {
+                                count: delay_count.clone(),
+                            }
+                        })?;
+                        if count == 0 {
+                            return Err(TimestampParseError::ZeroDelayCount);
+                        }
+                        timestamp.delay = Some(Delay {
+                            count,
+                            unit,
+                            kind: delay_kind,
+                        });
+                    } else if c == ' '
+                        && matches!(next_c, Some('+' | '.'))
+                        && timestamp.delay.is_some()
+                        && timestamp.repeater.is_none()
+                    {
+                        // We're allowed to have a repeater after the delay too
+                        loc = TimestampLocation::Repeater;
+                    } else {
+                        return Err(TimestampParseError::BadCharacter { c });
                     }
                 }
             }
 
             i += 1;
         }
+        // Make sure we didn't stop halfway through a repeater or delay
+        if loc == TimestampLocation::Repeater && timestamp.repeater.is_none() {
+            parse_repeater_count(&repeater_count)?;
+            return Err(TimestampParseError::MissingRepeaterUnit {
+                repeater: format!("{repeater_mark}{repeater_count}"),
+            });
+        } else if loc == TimestampLocation::HabitMax {
+            parse_repeater_count(&habit_count)?;
+            return Err(TimestampParseError::MissingRepeaterUnit {
+                repeater: format!("/{habit_count}"),
+            });
+        }
+        if loc == TimestampLocation::Delay && timestamp.delay.is_none() {
+            return Err(if delay_count.is_empty() {
+                TimestampParseError::InvalidDelayCount { count: delay_count }
+            } else {
+                TimestampParseError::MissingDelayUnit { count: delay_count }
+            });
+        }
 
         // We will have parsed everything valid in the timestamp by this point, but we still
         // need to parse the actual start and end timestamps!
@@ -751,6 +1360,7 @@ impl Timestamp {
                     start: end,
                     end: None,
                     repeater: None,
+                    delay: None,
                     active: self.active,
                 }
                 .into_string();
@@ -764,14 +1374,148 @@ impl Timestamp {
             ts_str.push(' ');
             ts_str.push_str(&repeater.into_string());
         }
+        if let Some(delay) = self.delay {
+            ts_str.push(' ');
+            ts_str.push_str(&delay.into_string());
+        }
         ts_str.push(if self.active { '>' } else { ']' });
 
         ts_str
     }
 }
 
+/// A single concrete occurrence of a (possibly repeating) timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Occurrence {
+    /// When this occurrence starts.
+    pub start: DateTime,
+    /// When this occurrence ends, if the timestamp has an end.
+    pub end: Option<DateTime>,
+}
+impl Occurrence {
+    /// Returns when this occurrence applies to the given date. This follows the same rules as
+    /// [`Timestamp::applies`], but for this occurrence alone.
+    pub fn applies(&self, date: NaiveDate) -> TimestampApplies {
+        let end = match &self.end {
+            Some(end) => end,
+            None if date == self.start.date => {
+                return match self.start.time {
+                    Some(time) => TimestampApplies::Start(time),
+                    None => TimestampApplies::AllDay,
+                }
+            }
+            None => return TimestampApplies::None,
+        };
+
+        if date < self.start.date || end.date < date {
+            TimestampApplies::None
+        } else if self.start.date == end.date {
+            match (self.start.time, end.time) {
+                (Some(start_time), Some(end_time)) => TimestampApplies::Block(start_time, end_time),
+                (Some(start_time), None) => TimestampApplies::Start(start_time),
+                (None, Some(end_time)) => TimestampApplies::End(end_time),
+                (None, None) => TimestampApplies::AllDay,
+            }
+        } else if date == self.start.date {
+            self.start
+                .time
+                .map(TimestampApplies::Start)
+                .unwrap_or(TimestampApplies::AllDay)
+        } else if date == end.date {
+            end.time
+                .map(TimestampApplies::End)
+                .unwrap_or(TimestampApplies::AllDay)
+        } else {
+            TimestampApplies::AllDay
+        }
+    }
+}
+
+/// An iterator over the occurrences of a timestamp within a date span. See
+/// [`Timestamp::occurrences`] for details.
+pub struct Occurrences<'a> {
+    timestamp: &'a Timestamp,
+    /// The index of the next repeat to consider.
+    index: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+    /// Whether or not we've passed the end of the span.
+    done: bool,
+}
+impl Iterator for Occurrences<'_> {
+    type Item = Occurrence;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ts = self.timestamp;
+        let (original_start, original_end) = ts.datetimes();
+        while !self.done {
+            let repeat = ts.nth_repeat(self.index);
+            self.index += 1;
+
+            let (earliest, start) = match repeat {
+                Some(repeat) => repeat,
+                None => break,
+            };
+            if earliest > self.to {
+                break;
+            }
+            // This repeat doesn't exist (see `Timestamp::get_next_repeat`)
+            let start = match start {
+                Some(start) => start,
+                None => continue,
+            };
+            if start.date > self.to {
+                break;
+            }
+
+            let end = match &ts.end {
+                // Hourly repeats are moved by their exact length
+                Some(_) if ts.hourly_repeat().is_some() => {
+                    let end = start.date.and_time(start.time.unwrap_or(NaiveTime::MIN))
+                        + (original_end - original_start);
+                    Some(DateTime {
+                        date: end.date(),
+                        time: Some(end.time()),
+                    })
+                }
+                Some(end) => Some(DateTime {
+                    date: start.date + (end.date - ts.start.date),
+                    time: end.time,
+                }),
+                None => None,
+            };
+            if end.as_ref().unwrap_or(&start).date < self.from {
+                continue;
+            }
+
+            return Some(Occurrence { start, end });
+        }
+
+        self.done = true;
+        None
+    }
+}
+
+/// Parses the count of a repeater (or the maximum interval of a habit), which should be a
+/// non-zero number.
+fn parse_repeater_count(count: &str) -> Result<usize, TimestampParseError> {
+    let parsed = count
+        .parse::<usize>()
+        .map_err(|_| TimestampParseError::InvalidRepeaterCount {
+            count: count.to_string(),
+        })?;
+    // A timestamp that repeats every zero units would never move
+    if parsed == 0 {
+        return Err(TimestampParseError::ZeroRepeaterCount);
+    }
+
+    Ok(parsed)
+}
+
 /// The location we're in while parsing a timestamp. This covers everything *after* the mandatory
 /// date.
+#[derive(PartialEq, Eq)]
 enum TimestampLocation {
     /// We've parsed the mandatory date, and we're now up to parsing whatever comes after that.
     Start,
@@ -781,6 +1525,10 @@ enum TimestampLocation {
     Time,
     /// A repeater.
     Repeater,
+    /// The maximum interval of a habit, after the repeater (e.g. the `3d` in `.+2d/3d`).
+    HabitMax,
+    /// A warning period or delay.
+    Delay,
 }
 
 /// Checks if the given value is within the given range, modulus the given value `c`. This assumes
@@ -812,6 +1560,8 @@ fn in_range_mod(x: i64, (start, end): (i64, Option<i64>), c: usize) -> (bool, u6
 /// When a timestamp occurs relative to a given date. This does *not* regard repeaters, as it is
 /// used for determining if something like a deadline has lapsed. Org mode by default progresses
 /// a timestamp to its next repeat when a node is marked as `DONE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TimestampWhen {
     /// The timestamp occurs today.
     Present,
@@ -822,6 +1572,8 @@ pub enum TimestampWhen {
 }
 
 /// The period at which a timestamp applies on a certain date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TimestampApplies {
     /// The timestamp applies for the whole day. This timestamp may have started, or may end, on other
     /// days.
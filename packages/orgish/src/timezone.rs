@@ -0,0 +1,114 @@
+//! Logic for resolving timestamps, which are written in local time, to particular timezones.
+
+use crate::timestamp::DateTime;
+use crate::Timestamp;
+use chrono::{NaiveTime, TimeZone};
+
+impl DateTime {
+    /// Resolves this date and time to an instant in the given timezone. Untimed dates will be
+    /// resolved to the start of their day.
+    ///
+    /// If the time is ambiguous in the given timezone (e.g. when clocks go back), the earlier of
+    /// the two possibilities will be used. If it doesn't exist at all (e.g. when clocks go
+    /// forward), this will return `None`.
+    pub fn in_timezone<Tz: TimeZone>(&self, tz: &Tz) -> Option<chrono::DateTime<Tz>> {
+        let naive = self.date.and_time(self.time.unwrap_or(NaiveTime::MIN));
+        tz.from_local_datetime(&naive).earliest()
+    }
+}
+
+impl Timestamp {
+    /// Resolves the start of this timestamp to an instant in the given timezone. See
+    /// [`DateTime::in_timezone`] for how this works.
+    pub fn start_in<Tz: TimeZone>(&self, tz: &Tz) -> Option<chrono::DateTime<Tz>> {
+        self.start.in_timezone(tz)
+    }
+    /// Resolves the end of this timestamp to an instant in the given timezone, if it has an end.
+    /// See [`DateTime::in_timezone`] for how this works.
+    pub fn end_in<Tz: TimeZone>(&self, tz: &Tz) -> Option<chrono::DateTime<Tz>> {
+        self.end.as_ref()?.in_timezone(tz)
+    }
+    /// Converts this timestamp, which is taken to be written in the timezone `from`, into the same
+    /// timestamp written in the timezone `to` (e.g. for showing to someone in another timezone).
+    /// This may change the dates of the timestamp as well as its times.
+    ///
+    /// Only timed parts of the timestamp will be converted, as a date on its own has no particular
+    /// instant to convert. Similarly, any part that can't be resolved in `from` (see
+    /// [`DateTime::in_timezone`]) will be left as is. Repeaters are not adjusted, so repeats which
+    /// fall on the other side of a daylight saving change may be an hour out.
+    pub fn into_timezone<Tz1: TimeZone, Tz2: TimeZone>(mut self, from: &Tz1, to: &Tz2) -> Self {
+        fn convert<Tz1: TimeZone, Tz2: TimeZone>(
+            datetime: DateTime,
+            from: &Tz1,
+            to: &Tz2,
+        ) -> DateTime {
+            if datetime.time.is_none() {
+                return datetime;
+            }
+            match datetime.in_timezone(from) {
+                Some(resolved) => {
+                    let converted = resolved.with_timezone(to).naive_local();
+                    DateTime {
+                        date: converted.date(),
+                        time: Some(converted.time()),
+                    }
+                }
+                None => datetime,
+            }
+        }
+
+        self.start = convert(self.start, from, to);
+        self.end = self.end.map(|end| convert(end, from, to));
+        self
+    }
+}
+
+#[cfg(feature = "timezones")]
+mod named {
+    use crate::error::TimezoneError;
+    use crate::{Attributes, Document, Format, Keyword, Node, ParseId, ParseString};
+    use chrono_tz::Tz;
+
+    impl<K: Keyword, I: ParseId, S: ParseString> Document<K, I, S> {
+        /// Gets the timezone of this document, if it has one. This is defined by the `#+TIMEZONE`
+        /// attribute in Org mode, or the `timezone` key in Markdown frontmatter, and should be an
+        /// IANA timezone name (e.g. `Europe/Berlin`).
+        pub fn timezone(&self) -> Option<Result<Tz, TimezoneError>> {
+            let name = match &self.attributes {
+                Attributes::Org(map) => Ok(map.get("timezone")?.as_str()),
+                Attributes::MarkdownYaml(map) => map
+                    .get("timezone")?
+                    .as_str()
+                    .ok_or(TimezoneError::NotString),
+                Attributes::MarkdownToml(map) => map
+                    .get("timezone")?
+                    .as_str()
+                    .ok_or(TimezoneError::NotString),
+                Attributes::None => return None,
+            };
+            Some(name.and_then(parse_timezone))
+        }
+    }
+
+    impl<K: Keyword, I: ParseId, S: ParseString> Node<K, I, S> {
+        /// Gets the timezone of this node, if it has one. This is defined by the `TZ` property,
+        /// which should be an IANA timezone name (e.g. `America/New_York`), and the format is
+        /// needed to read it.
+        ///
+        /// Note that this only checks this node itself: callers should fall back to the node's
+        /// ancestors and then the document's timezone (see [`Document::timezone`]).
+        pub fn timezone(&self, format: Format) -> Option<Result<Tz, TimezoneError>> {
+            let name = self.properties.get("TZ")?.to_string(format);
+            Some(parse_timezone(&name))
+        }
+    }
+
+    /// Parses the given timezone name.
+    fn parse_timezone(name: &str) -> Result<Tz, TimezoneError> {
+        name.trim()
+            .parse()
+            .map_err(|_| TimezoneError::UnknownTimezone {
+                name: name.to_string(),
+            })
+    }
+}
@@ -0,0 +1,343 @@
+//! Traversal of the trees of nodes in documents, through iterators (both depth-first and
+//! breadth-first) and visitors. Every node visited comes with its [`NodePath`], which records
+//! where it is in the tree.
+//!
+//! Mutable traversal doesn't hand out `&mut Node`s, as those would allow the levels and children
+//! of nodes to be changed in ways that break the tree (see [`Node::add_child`]). Instead, it uses
+//! [`NodeMut`], which gives mutable access to everything else.
+
+use crate::{
+    Drawers, Keyword, Node, ParseId, ParseString, Planning, Priority, Properties, Tags, Timestamp,
+};
+use std::collections::VecDeque;
+
+/// Where a node is in the tree being traversed, relative to the node the traversal started from
+/// (which is usually the root of a document).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodePath<S> {
+    /// The titles of the node's ancestors, from the top level down. This doesn't include the
+    /// title of the node itself, nor that of the node the traversal started from.
+    pub titles: Vec<S>,
+    /// The index of each of the node's ancestors among its siblings, from the top level down,
+    /// followed by the index of the node itself. When traversing from the root of a document,
+    /// this can be given to [`crate::Document::effective_tags`].
+    pub indices: Vec<usize>,
+}
+impl<S: Clone> NodePath<S> {
+    /// Gets how deep the node is in the tree, where the children of the node the traversal started
+    /// from have depth `1`. This isn't necessarily the same as the level of the node.
+    pub fn depth(&self) -> usize {
+        self.indices.len()
+    }
+    /// Gets the index of the node among its siblings.
+    pub fn index(&self) -> usize {
+        // Paths are only created for nodes below the one the traversal started from
+        *self.indices.last().unwrap()
+    }
+    /// Creates the path to a child of the node this is the path to.
+    fn child(&self, title: &S, idx: usize) -> Self {
+        let mut titles = self.titles.clone();
+        titles.push(title.clone());
+        let mut indices = self.indices.clone();
+        indices.push(idx);
+
+        Self { titles, indices }
+    }
+    /// Creates the path to a child of the node the traversal starts from.
+    fn top(idx: usize) -> Self {
+        Self {
+            titles: Vec::new(),
+            indices: vec![idx],
+        }
+    }
+}
+
+/// Mutable access to a node during a traversal. This has mutable references to all the public
+/// fields of the node, but not to its level or its children, so the structure of the tree can't be
+/// changed.
+pub struct NodeMut<'a, K: Keyword, I: ParseId, S: ParseString> {
+    level: u8,
+    /// See [`Node::title`].
+    pub title: &'a mut S,
+    /// See [`Node::priority`].
+    pub priority: &'a mut Priority,
+    /// See [`Node::tags`].
+    pub tags: &'a mut Tags,
+    /// See [`Node::planning`].
+    pub planning: &'a mut Planning,
+    /// See [`Node::properties`].
+    pub properties: &'a mut Properties<I, S>,
+    /// See [`Node::drawers`].
+    pub drawers: &'a mut Drawers<S>,
+    /// See [`Node::keyword`].
+    pub keyword: &'a mut Option<K>,
+    /// See [`Node::body`].
+    pub body: &'a mut Option<S>,
+    /// See [`Node::timestamps`].
+    pub timestamps: &'a mut Vec<Timestamp>,
+}
+impl<K: Keyword, I: ParseId, S: ParseString> NodeMut<'_, K, I, S> {
+    /// Gets the level of the node.
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+}
+
+/// Splits a node into mutable access to itself and its children, so the two can be handed out
+/// separately.
+#[allow(clippy::type_complexity)]
+fn split<K: Keyword, I: ParseId, S: ParseString>(
+    node: &mut Node<K, I, S>,
+) -> (NodeMut<'_, K, I, S>, &mut Vec<Node<K, I, S>>) {
+    let Node {
+        level,
+        title,
+        priority,
+        tags,
+        planning,
+        properties,
+        drawers,
+        keyword,
+        body,
+        timestamps,
+        children,
+        ..
+    } = node;
+    let node = NodeMut {
+        level: *level,
+        title,
+        priority,
+        tags,
+        planning,
+        properties,
+        drawers,
+        keyword,
+        body,
+        timestamps,
+    };
+
+    (node, children)
+}
+
+/// The orders in which the nodes of a tree can be traversed.
+#[derive(Debug, Clone, Copy)]
+enum Order {
+    /// Each node is followed by all of its descendants (i.e. document order).
+    DepthFirst,
+    /// Each level of the tree is gone through before the next one.
+    BreadthFirst,
+}
+
+/// An iterator over the descendants of a node, along with their paths. This is created by
+/// [`Node::depth_first`] or [`Node::breadth_first`].
+pub struct Iter<'a, K: Keyword, I: ParseId, S: ParseString> {
+    #[allow(clippy::type_complexity)]
+    queue: VecDeque<(&'a Node<K, I, S>, NodePath<S>)>,
+    order: Order,
+}
+impl<'a, K: Keyword, I: ParseId, S: ParseString + Clone> Iter<'a, K, I, S> {
+    fn new(node: &'a Node<K, I, S>, order: Order) -> Self {
+        Self {
+            queue: node
+                .children
+                .iter()
+                .enumerate()
+                .map(|(idx, child)| (child, NodePath::top(idx)))
+                .collect(),
+            order,
+        }
+    }
+}
+impl<'a, K: Keyword, I: ParseId, S: ParseString + Clone> Iterator for Iter<'a, K, I, S> {
+    type Item = (NodePath<S>, &'a Node<K, I, S>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, path) = self.queue.pop_front()?;
+        let children = node
+            .children
+            .iter()
+            .enumerate()
+            .map(|(idx, child)| (child, path.child(&node.title, idx)));
+        match self.order {
+            // Children go to the front of the queue, so they come before the node's siblings
+            Order::DepthFirst => children
+                .rev()
+                .for_each(|child| self.queue.push_front(child)),
+            Order::BreadthFirst => self.queue.extend(children),
+        }
+
+        Some((path, node))
+    }
+}
+
+/// A mutable iterator over the descendants of a node, along with their paths. This is created by
+/// [`Node::depth_first_mut`] or [`Node::breadth_first_mut`].
+pub struct IterMut<'a, K: Keyword, I: ParseId, S: ParseString> {
+    #[allow(clippy::type_complexity)]
+    queue: VecDeque<(&'a mut Node<K, I, S>, NodePath<S>)>,
+    order: Order,
+}
+impl<'a, K: Keyword, I: ParseId, S: ParseString + Clone> IterMut<'a, K, I, S> {
+    fn new(node: &'a mut Node<K, I, S>, order: Order) -> Self {
+        Self {
+            queue: node
+                .children
+                .iter_mut()
+                .enumerate()
+                .map(|(idx, child)| (child, NodePath::top(idx)))
+                .collect(),
+            order,
+        }
+    }
+}
+impl<'a, K: Keyword, I: ParseId, S: ParseString + Clone> Iterator for IterMut<'a, K, I, S> {
+    type Item = (NodePath<S>, NodeMut<'a, K, I, S>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, path) = self.queue.pop_front()?;
+        let (node, children) = split(node);
+        // The paths of the children are worked out now, so they'll have the title the node has
+        // before it's modified
+        let title = &*node.title;
+        let children = children
+            .iter_mut()
+            .enumerate()
+            .map(|(idx, child)| (child, path.child(title, idx)));
+        match self.order {
+            Order::DepthFirst => children
+                .rev()
+                .for_each(|child| self.queue.push_front(child)),
+            Order::BreadthFirst => self.queue.extend(children),
+        }
+
+        Some((path, node))
+    }
+}
+
+/// What a traversal with a visitor should do after entering a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitAction {
+    /// Go on to the children of the node.
+    Continue,
+    /// Skip the children of the node, going straight on to leaving it.
+    SkipChildren,
+    /// Stop the traversal entirely. No more nodes will be entered or left, including the ones
+    /// that have already been entered.
+    Stop,
+}
+
+/// A visitor over the descendants of a node, which can be run with [`Node::walk`]. Nodes are
+/// entered in document order, and each is left after all its descendants have been.
+pub trait Visitor<K: Keyword, I: ParseId, S: ParseString> {
+    /// Called when a node is reached, before any of its children. The returned action determines
+    /// whether or not its children will be visited.
+    fn enter(&mut self, _node: &Node<K, I, S>, _path: &NodePath<S>) -> VisitAction {
+        VisitAction::Continue
+    }
+    /// Called after a node and all its children have been visited.
+    fn leave(&mut self, _node: &Node<K, I, S>, _path: &NodePath<S>) {}
+}
+
+/// A mutable visitor over the descendants of a node, which can be run with [`Node::walk_mut`].
+/// This works in the same way as [`Visitor`], but with mutable access to the nodes.
+pub trait VisitorMut<K: Keyword, I: ParseId, S: ParseString> {
+    /// Called when a node is reached, before any of its children. The returned action determines
+    /// whether or not its children will be visited.
+    fn enter(&mut self, _node: NodeMut<'_, K, I, S>, _path: &NodePath<S>) -> VisitAction {
+        VisitAction::Continue
+    }
+    /// Called after a node and all its children have been visited.
+    fn leave(&mut self, _node: NodeMut<'_, K, I, S>, _path: &NodePath<S>) {}
+}
+
+impl<K: Keyword, I: ParseId, S: ParseString + Clone> Node<K, I, S> {
+    /// Iterates over all the descendants of this node (not including the node itself) in
+    /// depth-first order, which is the order they appear in in documents. Each comes with its path
+    /// relative to this node.
+    pub fn depth_first(&self) -> Iter<'_, K, I, S> {
+        Iter::new(self, Order::DepthFirst)
+    }
+    /// Iterates over all the descendants of this node (not including the node itself) in
+    /// breadth-first order, so all the children of this node will come first, then all their
+    /// children, and so on. Each comes with its path relative to this node.
+    pub fn breadth_first(&self) -> Iter<'_, K, I, S> {
+        Iter::new(self, Order::BreadthFirst)
+    }
+    /// Mutably iterates over all the descendants of this node in depth-first order. See
+    /// [`Self::depth_first`] and [`NodeMut`].
+    ///
+    /// Note that the paths of nodes use the titles of their ancestors from *before* they were
+    /// yielded by this iterator, so changing a title won't affect the paths of the node's
+    /// descendants.
+    pub fn depth_first_mut(&mut self) -> IterMut<'_, K, I, S> {
+        IterMut::new(self, Order::DepthFirst)
+    }
+    /// Mutably iterates over all the descendants of this node in breadth-first order. See
+    /// [`Self::breadth_first`] and [`NodeMut`].
+    ///
+    /// As with [`Self::depth_first_mut`], changing the title of a node won't affect the paths of
+    /// its descendants.
+    pub fn breadth_first_mut(&mut self) -> IterMut<'_, K, I, S> {
+        IterMut::new(self, Order::BreadthFirst)
+    }
+    /// Runs the given visitor over all the descendants of this node (not including the node
+    /// itself). This returns `false` if the visitor stopped the traversal early.
+    pub fn walk(&self, visitor: &mut impl Visitor<K, I, S>) -> bool {
+        fn walk_node<K: Keyword, I: ParseId, S: ParseString + Clone>(
+            node: &Node<K, I, S>,
+            path: &NodePath<S>,
+            visitor: &mut impl Visitor<K, I, S>,
+        ) -> bool {
+            match visitor.enter(node, path) {
+                VisitAction::Stop => return false,
+                VisitAction::SkipChildren => {}
+                VisitAction::Continue => {
+                    for (idx, child) in node.children.iter().enumerate() {
+                        if !walk_node(child, &path.child(&node.title, idx), visitor) {
+                            return false;
+                        }
+                    }
+                }
+            }
+            visitor.leave(node, path);
+
+            true
+        }
+
+        self.children
+            .iter()
+            .enumerate()
+            .all(|(idx, child)| walk_node(child, &NodePath::top(idx), visitor))
+    }
+    /// Runs the given mutable visitor over all the descendants of this node (not including the
+    /// node itself). This returns `false` if the visitor stopped the traversal early.
+    ///
+    /// The paths of a node's descendants will use its title as it was after it was entered.
+    pub fn walk_mut(&mut self, visitor: &mut impl VisitorMut<K, I, S>) -> bool {
+        fn walk_node<K: Keyword, I: ParseId, S: ParseString + Clone>(
+            node: &mut Node<K, I, S>,
+            path: &NodePath<S>,
+            visitor: &mut impl VisitorMut<K, I, S>,
+        ) -> bool {
+            match visitor.enter(split(node).0, path) {
+                VisitAction::Stop => return false,
+                VisitAction::SkipChildren => {}
+                VisitAction::Continue => {
+                    for (idx, child) in node.children.iter_mut().enumerate() {
+                        if !walk_node(child, &path.child(&node.title, idx), visitor) {
+                            return false;
+                        }
+                    }
+                }
+            }
+            visitor.leave(split(node).0, path);
+
+            true
+        }
+
+        self.children
+            .iter_mut()
+            .enumerate()
+            .all(|(idx, child)| walk_node(child, &NodePath::top(idx), visitor))
+    }
+}
@@ -0,0 +1,267 @@
+//! Logic for creating timestamps from human input (e.g. `fri 14:00` or `+2d`), relative to some
+//! given date.
+
+use crate::error::TimestampInputError;
+use crate::timestamp::{DateTime, Delay, DelayKind, Repeater, RepeaterKind, RepeaterUnit};
+use crate::Timestamp;
+use chrono::{Datelike, Days, NaiveDate, NaiveTime, Weekday};
+
+impl Timestamp {
+    /// Parses a timestamp from human input, relative to the given date (usually today). The input
+    /// is a series of whitespace-separated parts, each of which may be:
+    ///
+    /// - a date, either as `YYYY-MM-DD`, `today`, `tomorrow`, or `yesterday`;
+    /// - a day of the week (e.g. `fri` or `friday`), which is the first such day on or after the
+    ///   given date, or strictly after it if preceded by `next` (e.g. `next mon`);
+    /// - an offset from the given date, like `+2d`, `-1w`, or just `3w` (a count without a unit is
+    ///   in days);
+    /// - a time, like `14:00`, or a time range, like `9:00-10:30`;
+    /// - a repeater (e.g. `+1w`, `++1m`, or `.+2d`) or delay (e.g. `-3d` or `--1d`), which must
+    ///   come after a date or offset (so `+1w` alone is an offset, but `tomorrow +1w` repeats).
+    ///
+    /// If no date is given, the given date will be used. For example, `tomorrow +1w` gives a
+    /// weekly timestamp starting the day after the given date, and `2024-06-01 9:00-10:30` gives
+    /// a timestamp on that date with a time range. Anything already written as an Org mode
+    /// timestamp (i.e. starting with `<` or `[`) will be parsed as such.
+    ///
+    /// The resulting timestamp will always be active.
+    pub fn from_user_input(input: &str, today: NaiveDate) -> Result<Self, TimestampInputError> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(TimestampInputError::Empty);
+        }
+        if input.starts_with(['<', '[']) {
+            return Ok(Self::from_str(input)?);
+        }
+
+        let mut date = None;
+        let mut times = None;
+        let mut repeater = None;
+        let mut delay = None;
+        let mut tokens = input.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let lower = token.to_lowercase();
+
+            // Handle everything that could be a date first
+            let out_of_range = || TimestampInputError::OutOfRange {
+                token: token.to_string(),
+            };
+            let new_date = match lower.as_str() {
+                "today" => Some(today),
+                "tomorrow" => Some(today.succ_opt().ok_or_else(out_of_range)?),
+                "yesterday" => Some(today.pred_opt().ok_or_else(out_of_range)?),
+                "next" => {
+                    let next_token = tokens.next().unwrap_or_default();
+                    let weekday = next_token.to_lowercase().parse::<Weekday>().map_err(|_| {
+                        TimestampInputError::ExpectedWeekday {
+                            token: next_token.to_string(),
+                        }
+                    })?;
+                    Some(weekday_from(today, weekday, true).ok_or_else(out_of_range)?)
+                }
+                _ if lower.parse::<Weekday>().is_ok() => {
+                    let weekday = lower.parse::<Weekday>().unwrap();
+                    Some(weekday_from(today, weekday, false).ok_or_else(out_of_range)?)
+                }
+                _ if NaiveDate::parse_from_str(token, "%Y-%m-%d").is_ok() => {
+                    NaiveDate::parse_from_str(token, "%Y-%m-%d").ok()
+                }
+                // Once we have a date, these would be repeaters or delays (and `++`/`--` always
+                // are)
+                _ if date.is_none() && lower.starts_with('+') && !lower.starts_with("++") => {
+                    Some(offset_date(today, &lower[1..], token, true)?)
+                }
+                _ if date.is_none() && lower.starts_with('-') && !lower.starts_with("--") => {
+                    Some(offset_date(today, &lower[1..], token, false)?)
+                }
+                _ if lower.starts_with(|c: char| c.is_ascii_digit()) && !lower.contains(':') => {
+                    Some(offset_date(today, &lower, token, true)?)
+                }
+                _ => None,
+            };
+            if let Some(new_date) = new_date {
+                if date.is_some() {
+                    return Err(TimestampInputError::Duplicate {
+                        token: token.to_string(),
+                    });
+                }
+                date = Some(new_date);
+                continue;
+            }
+
+            if lower.contains(':') {
+                if times.is_some() {
+                    return Err(TimestampInputError::Duplicate {
+                        token: token.to_string(),
+                    });
+                }
+                let parse_time = |time: &str| {
+                    NaiveTime::parse_from_str(time, "%H:%M").map_err(|err| {
+                        TimestampInputError::InvalidTime {
+                            time: time.to_string(),
+                            source: err,
+                        }
+                    })
+                };
+                times = Some(match lower.split_once('-') {
+                    Some((start, end)) => {
+                        let (start, end) = (parse_time(start)?, parse_time(end)?);
+                        // Ranges within a single day can't go backwards
+                        if end < start {
+                            return Err(TimestampInputError::EndBeforeStart {
+                                token: token.to_string(),
+                            });
+                        }
+                        (start, Some(end))
+                    }
+                    None => (parse_time(&lower)?, None),
+                });
+                continue;
+            }
+
+            // Anything else has to be a repeater or a delay, which need a date first
+            let (mark, interval) = match lower.find(|c: char| c.is_ascii_digit()) {
+                Some(idx) => lower.split_at(idx),
+                None => {
+                    return Err(TimestampInputError::UnknownToken {
+                        token: token.to_string(),
+                    })
+                }
+            };
+            let (count, unit) =
+                parse_interval(interval).ok_or_else(|| TimestampInputError::UnknownToken {
+                    token: token.to_string(),
+                })?;
+            let (repeater_kind, delay_kind) = match mark {
+                "+" => (Some(RepeaterKind::Cumulate), None),
+                "++" => (Some(RepeaterKind::CatchUp), None),
+                ".+" => (Some(RepeaterKind::Restart), None),
+                "-" => (None, Some(DelayKind::All)),
+                "--" => (None, Some(DelayKind::First)),
+                _ => {
+                    return Err(TimestampInputError::UnknownToken {
+                        token: token.to_string(),
+                    })
+                }
+            };
+            if count == 0 {
+                return Err(TimestampInputError::ZeroCount {
+                    token: token.to_string(),
+                });
+            }
+            if date.is_none() {
+                return Err(TimestampInputError::NoDate {
+                    token: token.to_string(),
+                });
+            }
+            if let Some(kind) = repeater_kind {
+                if repeater.is_some() {
+                    return Err(TimestampInputError::Duplicate {
+                        token: token.to_string(),
+                    });
+                }
+                repeater = Some(Repeater {
+                    count,
+                    unit,
+                    kind,
+                    habit_max: None,
+                });
+            }
+            if let Some(kind) = delay_kind {
+                if delay.is_some() {
+                    return Err(TimestampInputError::Duplicate {
+                        token: token.to_string(),
+                    });
+                }
+                delay = Some(Delay { count, unit, kind });
+            }
+        }
+
+        let date = date.unwrap_or(today);
+        let (start_time, end_time) = match times {
+            Some((start, end)) => (Some(start), end),
+            None => (None, None),
+        };
+        Ok(Self {
+            start: DateTime {
+                date,
+                time: start_time,
+            },
+            end: end_time.map(|end| DateTime {
+                date,
+                time: Some(end),
+            }),
+            repeater,
+            delay,
+            active: true,
+        })
+    }
+}
+
+/// Gets the first date with the given weekday on or after the given date (or strictly after it,
+/// if `strict` is `true`).
+fn weekday_from(date: NaiveDate, weekday: Weekday, strict: bool) -> Option<NaiveDate> {
+    let mut days_ahead =
+        (7 + weekday.num_days_from_monday() - date.weekday().num_days_from_monday()) % 7;
+    if strict && days_ahead == 0 {
+        days_ahead = 7;
+    }
+    date.checked_add_days(Days::new(days_ahead as u64))
+}
+
+/// Parses an interval like `3w` into its count and unit. A count without a unit is in days.
+fn parse_interval(interval: &str) -> Option<(usize, RepeaterUnit)> {
+    let unit_idx = interval
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(interval.len());
+    let (count, unit) = interval.split_at(unit_idx);
+    let count = count.parse().ok()?;
+    let mut unit_chars = unit.chars();
+    let unit = match (unit_chars.next(), unit_chars.next()) {
+        (None, _) => RepeaterUnit::Day,
+        (Some(c), None) => RepeaterUnit::from_char(c)?,
+        _ => return None,
+    };
+
+    Some((count, unit))
+}
+
+/// Gets the date the given offset (e.g. `3w`, without any sign) after or before the given date.
+/// The original token is used for errors.
+fn offset_date(
+    date: NaiveDate,
+    offset: &str,
+    token: &str,
+    forward: bool,
+) -> Result<NaiveDate, TimestampInputError> {
+    let (count, unit) =
+        parse_interval(offset).ok_or_else(|| TimestampInputError::UnknownToken {
+            token: token.to_string(),
+        })?;
+    if unit == RepeaterUnit::Hour {
+        return Err(TimestampInputError::RelativeHours {
+            token: token.to_string(),
+        });
+    }
+
+    let new_date = if forward {
+        Repeater {
+            count,
+            unit,
+            kind: RepeaterKind::Cumulate,
+            habit_max: None,
+        }
+        .after(date.and_time(NaiveTime::MIN))
+        .map(|datetime| datetime.date())
+    } else {
+        Delay {
+            count,
+            unit,
+            kind: DelayKind::All,
+        }
+        .before(date)
+    };
+    new_date.ok_or_else(|| TimestampInputError::OutOfRange {
+        token: token.to_string(),
+    })
+}